@@ -0,0 +1,551 @@
+extern crate std;
+
+use error::{Error,Result};
+use utility::{linear_to_srgb,srgb_to_linear};
+
+//A surface that can be drawn on. Colors going in are 8-bit sRGB and alpha is how much of the color
+//covers the existing pixel from 0.0 (none) to 1.0 (completely replaced). Each canvas decides how
+//precisely it stores the result.
+pub trait Canvas {
+    fn width(&self) -> usize;
+    fn height(&self) -> usize;
+
+    //Blend color over the pixel at x,y.
+    fn blend(&mut self,x: usize,y: usize,red: u8,green: u8,blue: u8,alpha: f32);
+
+    //Get the pixel at x,y as sRGB red, green, blue, and alpha from 0.0 to 1.0. The color is not
+    //premultiplied by alpha.
+    fn pixel(&self,x: usize,y: usize) -> (f32,f32,f32,f32);
+
+    //Get the pixel at x,y as linear light red, green, and blue premultiplied by alpha.
+    fn linear_pixel(&self,x: usize,y: usize) -> (f32,f32,f32,f32) {
+        let (red,green,blue,alpha) = self.pixel(x,y);
+        (srgb_to_linear(red) * alpha,srgb_to_linear(green) * alpha,srgb_to_linear(blue) * alpha,alpha)
+    }
+
+    //Get the pixel at x,y as sRGB red, green, and blue from 0.0 to 1.0 after placing it on top of
+    //a black background.
+    fn flattened_pixel(&self,x: usize,y: usize) -> (f32,f32,f32) {
+        let (red,green,blue,alpha) = self.pixel(x,y);
+        (red * alpha,green * alpha,blue * alpha)
+    }
+
+    //Packed 8-bit RGB on a black background.
+    fn to_rgb8(&self) -> Vec<u8> {
+        let mut result = Vec::with_capacity(self.width() * self.height() * 3);
+        for y in 0..self.height() {
+            for x in 0..self.width() {
+                let (red,green,blue) = self.flattened_pixel(x,y);
+                result.push(to_u8(red));
+                result.push(to_u8(green));
+                result.push(to_u8(blue));
+            }
+        }
+        result
+    }
+
+    //Packed 8-bit RGBA.
+    fn to_rgba8(&self) -> Vec<u8> {
+        let mut result = Vec::with_capacity(self.width() * self.height() * 4);
+        for y in 0..self.height() {
+            for x in 0..self.width() {
+                let (red,green,blue,alpha) = self.pixel(x,y);
+                result.push(to_u8(red));
+                result.push(to_u8(green));
+                result.push(to_u8(blue));
+                result.push(to_u8(alpha));
+            }
+        }
+        result
+    }
+
+    //Packed 16-bit RGB on a black background.
+    fn to_rgb16(&self) -> Vec<u16> {
+        let mut result = Vec::with_capacity(self.width() * self.height() * 3);
+        for y in 0..self.height() {
+            for x in 0..self.width() {
+                let (red,green,blue) = self.flattened_pixel(x,y);
+                result.push(to_u16(red));
+                result.push(to_u16(green));
+                result.push(to_u16(blue));
+            }
+        }
+        result
+    }
+
+    //Packed 16-bit RGBA.
+    fn to_rgba16(&self) -> Vec<u16> {
+        let mut result = Vec::with_capacity(self.width() * self.height() * 4);
+        for y in 0..self.height() {
+            for x in 0..self.width() {
+                let (red,green,blue,alpha) = self.pixel(x,y);
+                result.push(to_u16(red));
+                result.push(to_u16(green));
+                result.push(to_u16(blue));
+                result.push(to_u16(alpha));
+            }
+        }
+        result
+    }
+}
+
+//Allocate the pixels for a canvas. Fails instead of panicking or aborting when the canvas is empty,
+//too large to address, or doesn't fit in memory.
+fn allocate_pixels<T: Clone>(width: usize,height: usize,channels: usize,value: T) -> Result<Vec<T>> {
+    if width == 0 || height == 0 {
+        return Err(Error::EmptyImage);
+    }
+
+    let too_large = || Error::InvalidParameter(format!("A {}x{} canvas is too large",width,height));
+    let size = width.checked_mul(height).and_then(|size| size.checked_mul(channels)).ok_or_else(too_large)?;
+    let mut pixels = vec![];
+    pixels.try_reserve_exact(size).map_err(|_| too_large())?;
+    pixels.resize(size,value);
+    Ok(pixels)
+}
+
+fn to_u8(value: f32) -> u8 {
+    (value.clamp(0.0,1.0) * 255.0).round() as u8
+}
+
+fn to_u16(value: f32) -> u16 {
+    (value.clamp(0.0,1.0) * 65535.0).round() as u16
+}
+
+fn lerp(start: f32,end: f32,t: f32) -> f32 {
+    start + (end - start) * t
+}
+
+//8-bit RGB. This is the fastest canvas but translucent lines lose precision quickly.
+pub struct RgbCanvas {
+    width: usize,
+    height: usize,
+    pixels: Vec<u8>,
+}
+
+impl RgbCanvas {
+    //Create a black canvas.
+    pub fn new(width: usize,height: usize) -> Result<RgbCanvas> {
+        Ok(RgbCanvas {
+            width,
+            height,
+            pixels: allocate_pixels(width,height,3,0)?,
+        })
+    }
+
+    pub fn as_raw(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    pub fn into_raw(self) -> Vec<u8> {
+        self.pixels
+    }
+}
+
+impl Canvas for RgbCanvas {
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    fn height(&self) -> usize {
+        self.height
+    }
+
+    fn blend(&mut self,x: usize,y: usize,red: u8,green: u8,blue: u8,alpha: f32) {
+        let index = (y * self.width + x) * 3;
+        if alpha >= 1.0 {
+            self.pixels[index] = red;
+            self.pixels[index + 1] = green;
+            self.pixels[index + 2] = blue;
+            return;
+        }
+
+        for (offset,&value) in [red,green,blue].iter().enumerate() {
+            let existing = self.pixels[index + offset] as f32;
+            self.pixels[index + offset] = lerp(existing,value as f32,alpha).round() as u8;
+        }
+    }
+
+    fn pixel(&self,x: usize,y: usize) -> (f32,f32,f32,f32) {
+        let index = (y * self.width + x) * 3;
+        (self.pixels[index] as f32 / 255.0,
+         self.pixels[index + 1] as f32 / 255.0,
+         self.pixels[index + 2] as f32 / 255.0,
+         1.0)
+    }
+
+    fn to_rgb8(&self) -> Vec<u8> {
+        self.pixels.clone()
+    }
+}
+
+//8-bit RGBA that starts out completely transparent.
+pub struct RgbaCanvas {
+    width: usize,
+    height: usize,
+    pixels: Vec<u8>,
+}
+
+impl RgbaCanvas {
+    pub fn new(width: usize,height: usize) -> Result<RgbaCanvas> {
+        Ok(RgbaCanvas {
+            width,
+            height,
+            pixels: allocate_pixels(width,height,4,0)?,
+        })
+    }
+
+    pub fn as_raw(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    pub fn into_raw(self) -> Vec<u8> {
+        self.pixels
+    }
+}
+
+impl Canvas for RgbaCanvas {
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    fn height(&self) -> usize {
+        self.height
+    }
+
+    fn blend(&mut self,x: usize,y: usize,red: u8,green: u8,blue: u8,alpha: f32) {
+        let index = (y * self.width + x) * 4;
+        let alpha = alpha.clamp(0.0,1.0);
+        let existing_alpha = self.pixels[index + 3] as f32 / 255.0;
+
+        //Porter-Duff over with straight (not premultiplied) alpha.
+        let result_alpha = alpha + existing_alpha * (1.0 - alpha);
+        if result_alpha == 0.0 {
+            return;
+        }
+
+        for (offset,&value) in [red,green,blue].iter().enumerate() {
+            let existing = self.pixels[index + offset] as f32;
+            let color = (value as f32 * alpha + existing * existing_alpha * (1.0 - alpha)) / result_alpha;
+            self.pixels[index + offset] = color.round() as u8;
+        }
+        self.pixels[index + 3] = to_u8(result_alpha);
+    }
+
+    fn pixel(&self,x: usize,y: usize) -> (f32,f32,f32,f32) {
+        let index = (y * self.width + x) * 4;
+        (self.pixels[index] as f32 / 255.0,
+         self.pixels[index + 1] as f32 / 255.0,
+         self.pixels[index + 2] as f32 / 255.0,
+         self.pixels[index + 3] as f32 / 255.0)
+    }
+
+    fn to_rgba8(&self) -> Vec<u8> {
+        self.pixels.clone()
+    }
+}
+
+//16-bit RGB. Keeps enough precision for thousands of overlapping translucent lines.
+pub struct Rgb16Canvas {
+    width: usize,
+    height: usize,
+    pixels: Vec<u16>,
+}
+
+impl Rgb16Canvas {
+    //Create a black canvas.
+    pub fn new(width: usize,height: usize) -> Result<Rgb16Canvas> {
+        Ok(Rgb16Canvas {
+            width,
+            height,
+            pixels: allocate_pixels(width,height,3,0)?,
+        })
+    }
+
+    pub fn as_raw(&self) -> &[u16] {
+        &self.pixels
+    }
+
+    pub fn into_raw(self) -> Vec<u16> {
+        self.pixels
+    }
+}
+
+impl Canvas for Rgb16Canvas {
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    fn height(&self) -> usize {
+        self.height
+    }
+
+    fn blend(&mut self,x: usize,y: usize,red: u8,green: u8,blue: u8,alpha: f32) {
+        let index = (y * self.width + x) * 3;
+        let alpha = alpha.clamp(0.0,1.0);
+        for (offset,&value) in [red,green,blue].iter().enumerate() {
+            let existing = self.pixels[index + offset] as f32;
+            self.pixels[index + offset] = lerp(existing,value as f32 * 257.0,alpha).round() as u16;
+        }
+    }
+
+    fn pixel(&self,x: usize,y: usize) -> (f32,f32,f32,f32) {
+        let index = (y * self.width + x) * 3;
+        (self.pixels[index] as f32 / 65535.0,
+         self.pixels[index + 1] as f32 / 65535.0,
+         self.pixels[index + 2] as f32 / 65535.0,
+         1.0)
+    }
+
+    fn to_rgb16(&self) -> Vec<u16> {
+        self.pixels.clone()
+    }
+}
+
+//16-bit RGBA that starts out completely transparent.
+pub struct Rgba16Canvas {
+    width: usize,
+    height: usize,
+    pixels: Vec<u16>,
+}
+
+impl Rgba16Canvas {
+    pub fn new(width: usize,height: usize) -> Result<Rgba16Canvas> {
+        Ok(Rgba16Canvas {
+            width,
+            height,
+            pixels: allocate_pixels(width,height,4,0)?,
+        })
+    }
+
+    pub fn as_raw(&self) -> &[u16] {
+        &self.pixels
+    }
+
+    pub fn into_raw(self) -> Vec<u16> {
+        self.pixels
+    }
+}
+
+impl Canvas for Rgba16Canvas {
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    fn height(&self) -> usize {
+        self.height
+    }
+
+    fn blend(&mut self,x: usize,y: usize,red: u8,green: u8,blue: u8,alpha: f32) {
+        let index = (y * self.width + x) * 4;
+        let alpha = alpha.clamp(0.0,1.0);
+        let existing_alpha = self.pixels[index + 3] as f32 / 65535.0;
+
+        //Porter-Duff over with straight (not premultiplied) alpha.
+        let result_alpha = alpha + existing_alpha * (1.0 - alpha);
+        if result_alpha == 0.0 {
+            return;
+        }
+
+        for (offset,&value) in [red,green,blue].iter().enumerate() {
+            let existing = self.pixels[index + offset] as f32;
+            let color = (value as f32 * 257.0 * alpha + existing * existing_alpha * (1.0 - alpha)) / result_alpha;
+            self.pixels[index + offset] = color.round() as u16;
+        }
+        self.pixels[index + 3] = to_u16(result_alpha);
+    }
+
+    fn pixel(&self,x: usize,y: usize) -> (f32,f32,f32,f32) {
+        let index = (y * self.width + x) * 4;
+        (self.pixels[index] as f32 / 65535.0,
+         self.pixels[index + 1] as f32 / 65535.0,
+         self.pixels[index + 2] as f32 / 65535.0,
+         self.pixels[index + 3] as f32 / 65535.0)
+    }
+
+    fn to_rgba16(&self) -> Vec<u16> {
+        self.pixels.clone()
+    }
+}
+
+//32-bit floating point RGBA stored as premultiplied linear light. Blending happens in linear light
+//so overlapping colors mix the way light does instead of getting muddy. Starts out completely
+//transparent which is the same as black when saved without alpha.
+pub struct LinearCanvas {
+    width: usize,
+    height: usize,
+    pixels: Vec<f32>,
+}
+
+impl LinearCanvas {
+    pub fn new(width: usize,height: usize) -> Result<LinearCanvas> {
+        Ok(LinearCanvas {
+            width,
+            height,
+            pixels: allocate_pixels(width,height,4,0.0)?,
+        })
+    }
+
+    pub fn as_raw(&self) -> &[f32] {
+        &self.pixels
+    }
+
+    pub fn into_raw(self) -> Vec<f32> {
+        self.pixels
+    }
+}
+
+impl Canvas for LinearCanvas {
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    fn height(&self) -> usize {
+        self.height
+    }
+
+    fn blend(&mut self,x: usize,y: usize,red: u8,green: u8,blue: u8,alpha: f32) {
+        let index = (y * self.width + x) * 4;
+        let alpha = alpha.clamp(0.0,1.0);
+        for (offset,&value) in [red,green,blue].iter().enumerate() {
+            let color = srgb_to_linear(value as f32 / 255.0);
+            self.pixels[index + offset] = color * alpha + self.pixels[index + offset] * (1.0 - alpha);
+        }
+        self.pixels[index + 3] = alpha + self.pixels[index + 3] * (1.0 - alpha);
+    }
+
+    fn pixel(&self,x: usize,y: usize) -> (f32,f32,f32,f32) {
+        let index = (y * self.width + x) * 4;
+        let alpha = self.pixels[index + 3];
+        if alpha == 0.0 {
+            return (0.0,0.0,0.0,0.0);
+        }
+
+        (linear_to_srgb(self.pixels[index] / alpha),
+         linear_to_srgb(self.pixels[index + 1] / alpha),
+         linear_to_srgb(self.pixels[index + 2] / alpha),
+         alpha)
+    }
+
+    fn linear_pixel(&self,x: usize,y: usize) -> (f32,f32,f32,f32) {
+        let index = (y * self.width + x) * 4;
+        (self.pixels[index],self.pixels[index + 1],self.pixels[index + 2],self.pixels[index + 3])
+    }
+
+    //The color is already premultiplied so it only needs to be converted back to sRGB.
+    fn flattened_pixel(&self,x: usize,y: usize) -> (f32,f32,f32) {
+        let index = (y * self.width + x) * 4;
+        (linear_to_srgb(self.pixels[index]),
+         linear_to_srgb(self.pixels[index + 1]),
+         linear_to_srgb(self.pixels[index + 2]))
+    }
+}
+
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum DownsampleFilter {
+    //Average each block of pixels. Fast and never rings but slightly soft.
+    Box,
+    //Three lobe Lanczos. Sharper but can ring a little around hard edges.
+    Lanczos,
+}
+
+//Shrink a canvas by an integer factor in each direction. Filtering is done in linear light with
+//premultiplied alpha so thin lines keep their brightness and don't pick up dark fringes. Besides
+//the result, one extra buffer of (width / factor) * height four float pixels is used.
+pub fn downsample(canvas: &dyn Canvas,factor: usize,filter: DownsampleFilter) -> Result<LinearCanvas> {
+    if factor == 0 {
+        return Err(Error::InvalidParameter("Downsample factor must be at least 1".to_string()));
+    }
+
+    let (width,height) = (canvas.width(),canvas.height());
+    let mut result = LinearCanvas::new(std::cmp::max(1,width / factor),std::cmp::max(1,height / factor))?;
+    let horizontal_weights = downsample_weights(width,result.width,factor,filter);
+    let vertical_weights = downsample_weights(height,result.height,factor,filter);
+
+    //Filter each row horizontally.
+    let mut row = vec![0.0; width * 4];
+    let mut columns = allocate_pixels(result.width,height,4,0.0)?;
+    for y in 0..height {
+        for x in 0..width {
+            let (red,green,blue,alpha) = canvas.linear_pixel(x,y);
+            row[x * 4..x * 4 + 4].copy_from_slice(&[red,green,blue,alpha]);
+        }
+
+        for (x,&(start,ref weights)) in horizontal_weights.iter().enumerate() {
+            let index = (y * result.width + x) * 4;
+            for (offset,&weight) in weights.iter().enumerate() {
+                let source_index = (start + offset) * 4;
+                for channel in 0..4 {
+                    columns[index + channel] += row[source_index + channel] * weight;
+                }
+            }
+        }
+    }
+
+    //Then filter the columns vertically.
+    for (y,&(start,ref weights)) in vertical_weights.iter().enumerate() {
+        for x in 0..result.width {
+            let index = (y * result.width + x) * 4;
+            for (offset,&weight) in weights.iter().enumerate() {
+                let source_index = ((start + offset) * result.width + x) * 4;
+                for channel in 0..4 {
+                    result.pixels[index + channel] += columns[source_index + channel] * weight;
+                }
+            }
+
+            //Lanczos can overshoot so keep the pixel valid.
+            let alpha = result.pixels[index + 3].clamp(0.0,1.0);
+            result.pixels[index + 3] = alpha;
+            for channel in 0..3 {
+                result.pixels[index + channel] = result.pixels[index + channel].max(0.0).min(alpha);
+            }
+        }
+    }
+
+    Ok(result)
+}
+
+//Find the first source pixel and the weight of each source pixel that makes up every destination
+//pixel. Weights always add up to 1.0.
+fn downsample_weights(source_length: usize,destination_length: usize,factor: usize,filter: DownsampleFilter) -> Vec<(usize,Vec<f32>)> {
+    const LANCZOS_LOBES: f32 = 3.0;
+
+    let sinc = |x: f32| {
+        if x == 0.0 {
+            1.0
+        }
+        else {
+            let x = x * std::f32::consts::PI;
+            x.sin() / x
+        }
+    };
+
+    let factor_f32 = factor as f32;
+    (0..destination_length).map(|index| {
+        let (start,end) = match filter {
+            DownsampleFilter::Box => (index * factor,std::cmp::min(source_length,(index + 1) * factor)),
+            DownsampleFilter::Lanczos => {
+                let radius = (LANCZOS_LOBES * factor_f32).ceil() as usize;
+                let center = index * factor + factor / 2;
+                (center.saturating_sub(radius),std::cmp::min(source_length,center + radius + 1))
+            },
+        };
+
+        let center = (index as f32 + 0.5) * factor_f32 - 0.5;
+        let mut weights: Vec<f32> = (start..end).map(|position| match filter {
+            DownsampleFilter::Box => 1.0,
+            DownsampleFilter::Lanczos => {
+                let distance = (position as f32 - center) / factor_f32;
+                if distance.abs() < LANCZOS_LOBES { sinc(distance) * sinc(distance / LANCZOS_LOBES) } else { 0.0 }
+            },
+        }).collect();
+
+        let total: f32 = weights.iter().sum();
+        if total != 0.0 {
+            for weight in &mut weights {
+                *weight /= total;
+            }
+        }
+        (start,weights)
+    }).collect()
+}
@@ -0,0 +1,138 @@
+extern crate std;
+
+use std::collections::HashMap;
+
+//How the levels contours are traced at are spread out.
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum LevelSpacing {
+    //Evenly between the smallest and largest value.
+    Linear,
+    //So about the same number of values fall between each pair of neighboring levels.
+    Quantile,
+}
+
+impl LevelSpacing {
+    pub fn from_name(name: &str) -> Option<LevelSpacing> {
+        match name.to_lowercase().as_str() {
+            "linear" => Some(LevelSpacing::Linear),
+            "quantile" => Some(LevelSpacing::Quantile),
+            _ => None,
+        }
+    }
+}
+
+//Pick up to count levels strictly between the smallest and largest of values. Levels that would be
+//repeated, like the quantiles of a mostly flat image, are only returned once. Returned from lowest
+//to highest.
+pub fn contour_levels(values: &[f32],count: usize,spacing: LevelSpacing) -> Vec<f32> {
+    let mut sorted: Vec<f32> = values.iter().cloned().filter(|value| value.is_finite()).collect();
+    if sorted.is_empty() || count == 0 {
+        return vec![];
+    }
+    sorted.sort_by(|lhs,rhs| lhs.partial_cmp(rhs).unwrap_or(std::cmp::Ordering::Equal));
+    let (minimum,maximum) = (sorted[0],sorted[sorted.len() - 1]);
+
+    let mut levels: Vec<f32> = (1..count + 1).map(|index| {
+        let fraction = index as f32 / (count + 1) as f32;
+        match spacing {
+            LevelSpacing::Linear => minimum + (maximum - minimum) * fraction,
+            LevelSpacing::Quantile => sorted[std::cmp::min((fraction * sorted.len() as f32) as usize,sorted.len() - 1)],
+        }
+    }).filter(|&level| level > minimum && level < maximum).collect();
+    levels.dedup();
+    levels
+}
+
+//Trace the lines where a width x height grid of values crosses level using marching squares. Values
+//are sampled at pixel centers, so the point x,y is at the center of pixel x,y. Lines that form a
+//loop end with their first point. Returns each line as a list of points.
+pub fn isolines(values: &[f32],width: usize,height: usize,level: f32) -> Vec<Vec<(f32,f32)>> {
+    if width < 2 || height < 2 || values.len() < width * height {
+        return vec![];
+    }
+
+    //Each crossing is on an edge between two neighboring pixels. Edges going right from a pixel
+    //are even and edges going down are odd.
+    let value = |x: usize,y: usize| values[y * width + x];
+    let crossing = |edge: usize| -> (f32,f32) {
+        let (x,y) = ((edge / 2) % width,(edge / 2) / width);
+        let (x2,y2) = if edge.is_multiple_of(2) { (x + 1,y) } else { (x,y + 1) };
+        let (value1,value2) = (value(x,y),value(x2,y2));
+        let t = if value1 == value2 { 0.5 } else { (level - value1) / (value2 - value1) };
+        (x as f32 + (x2 - x) as f32 * t,y as f32 + (y2 - y) as f32 * t)
+    };
+
+    //Find the piece of line in every square of four pixels.
+    let mut segments: Vec<(usize,usize)> = vec![];
+    for y in 0..height - 1 {
+        for x in 0..width - 1 {
+            let (top_left,top_right) = (value(x,y),value(x + 1,y));
+            let (bottom_left,bottom_right) = (value(x,y + 1),value(x + 1,y + 1));
+            let case = ((top_left > level) as usize) << 3 | ((top_right > level) as usize) << 2 | ((bottom_right > level) as usize) << 1 | (bottom_left > level) as usize;
+
+            let top = (y * width + x) * 2;
+            let left = top + 1;
+            let bottom = ((y + 1) * width + x) * 2;
+            let right = (y * width + x + 1) * 2 + 1;
+
+            //Opposite corners on the same side of the level are joined through the middle if the
+            //average of all four is too.
+            let center_above = (top_left + top_right + bottom_left + bottom_right) / 4.0 > level;
+            match case {
+                1 | 14 => segments.push((left,bottom)),
+                2 | 13 => segments.push((bottom,right)),
+                3 | 12 => segments.push((left,right)),
+                4 | 11 => segments.push((top,right)),
+                6 | 9 => segments.push((top,bottom)),
+                7 | 8 => segments.push((left,top)),
+                5 if center_above => segments.extend_from_slice(&[(left,top),(bottom,right)]),
+                10 if !center_above => segments.extend_from_slice(&[(left,top),(bottom,right)]),
+                5 | 10 => segments.extend_from_slice(&[(top,right),(left,bottom)]),
+                _ => (),
+            }
+        }
+    }
+
+    //Every crossing is shared by at most two pieces. Chain them together into lines.
+    let mut pieces: HashMap<usize,Vec<usize>> = HashMap::new();
+    for (index,&(first,second)) in segments.iter().enumerate() {
+        pieces.entry(first).or_default().push(index);
+        pieces.entry(second).or_default().push(index);
+    }
+    let mut used = vec![false; segments.len()];
+    let follow = |used: &mut Vec<bool>,start: usize,mut edge: usize| -> Vec<usize> {
+        let mut edges = vec![];
+        let mut current = start;
+        loop {
+            let (first,second) = segments[current];
+            edge = if first == edge { second } else { first };
+            edges.push(edge);
+            match pieces[&edge].iter().cloned().find(|&piece| !used[piece]) {
+                Some(piece) => {
+                    used[piece] = true;
+                    current = piece;
+                },
+                None => return edges,
+            }
+        }
+    };
+
+    let mut lines = vec![];
+    for index in 0..segments.len() {
+        if used[index] {
+            continue;
+        }
+        used[index] = true;
+
+        //Walk forward from the second crossing and then backward from the first. Loops come all
+        //the way around on the first walk.
+        let (first,_) = segments[index];
+        let forward = follow(&mut used,index,first);
+        let mut edges = follow(&mut used,index,forward[0]);
+        edges.reverse();
+        edges.extend_from_slice(&forward);
+        lines.push(edges.iter().map(|&edge| crossing(edge)).collect());
+    }
+
+    lines
+}
@@ -0,0 +1,62 @@
+extern crate std;
+
+use std::fmt;
+use std::io;
+
+use image::ImageError;
+
+//Everything that can go wrong while loading, rendering, or saving an image.
+#[derive(Debug)]
+pub enum Error {
+    //Reading or writing a file or stream failed.
+    Io(io::Error),
+    //The input image could not be decoded.
+    Decode(ImageError),
+    //The image format isn't recognized or can't do what was asked of it.
+    UnsupportedFormat(String),
+    //A setting is out of range or doesn't make sense with the other settings.
+    InvalidParameter(String),
+    //The image has no pixels.
+    EmptyImage,
+}
+
+pub type Result<T> = std::result::Result<T,Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self,f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Io(ref e) => write!(f,"{}",e),
+            Error::Decode(ref e) => write!(f,"Could not decode image: {}",e),
+            Error::UnsupportedFormat(ref message) => write!(f,"{}",message),
+            Error::InvalidParameter(ref message) => write!(f,"{}",message),
+            Error::EmptyImage => write!(f,"Image has no pixels"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match *self {
+            Error::Io(ref e) => Some(e),
+            Error::Decode(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Error {
+        Error::Io(e)
+    }
+}
+
+//The image crate reports I/O and format problems through ImageError too so they're split back out.
+impl From<ImageError> for Error {
+    fn from(e: ImageError) -> Error {
+        match e {
+            ImageError::IoError(e) => Error::Io(e),
+            ImageError::UnsupportedError(message) => Error::UnsupportedFormat(message),
+            e => Error::Decode(e),
+        }
+    }
+}
@@ -0,0 +1,362 @@
+extern crate std;
+
+use std::fs::File;
+use std::io::{self,Read,Write};
+use std::path::Path;
+
+use image::{self,ColorType,DecodingResult,ImageDecoder,ImageError,ImageFormat,ImageResult,RgbImage};
+use image::bmp::BMPEncoder;
+use image::gif::{Encoder as GIFEncoder,Frame};
+use image::jpeg::JPEGEncoder;
+use image::png::{PNGDecoder,PNGEncoder};
+use image::ppm::PPMEncoder;
+use image::tiff::TIFFDecoder;
+
+use canvas::Canvas;
+use error::{Error,Result};
+use metadata::{self,Metadata};
+
+//Image formats that can be written. Reading is handled by the image crate which also supports
+//WebP, ICO, and HDR.
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum OutputFormat {
+    Png,
+    Jpeg,
+    Bmp,
+    Gif,
+    Pnm,
+    Tga,
+    Tiff,
+}
+
+impl OutputFormat {
+    //Look up a format by file extension or name. Case is ignored.
+    pub fn from_name(name: &str) -> Option<OutputFormat> {
+        match name.to_lowercase().as_str() {
+            "png" => Some(OutputFormat::Png),
+            "jpg" | "jpeg" => Some(OutputFormat::Jpeg),
+            "bmp" => Some(OutputFormat::Bmp),
+            "gif" => Some(OutputFormat::Gif),
+            "pnm" | "ppm" => Some(OutputFormat::Pnm),
+            "tga" => Some(OutputFormat::Tga),
+            "tif" | "tiff" => Some(OutputFormat::Tiff),
+            _ => None,
+        }
+    }
+
+    pub fn from_path(path: &Path) -> Option<OutputFormat> {
+        path.extension().and_then(|extension| Self::from_name(&extension.to_string_lossy()))
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            OutputFormat::Png => "PNG",
+            OutputFormat::Jpeg => "JPEG",
+            OutputFormat::Bmp => "BMP",
+            OutputFormat::Gif => "GIF",
+            OutputFormat::Pnm => "PNM",
+            OutputFormat::Tga => "TGA",
+            OutputFormat::Tiff => "TIFF",
+        }
+    }
+
+    pub fn supports_16_bit(self) -> bool {
+        matches!(self,OutputFormat::Png | OutputFormat::Pnm | OutputFormat::Tiff)
+    }
+
+    pub fn supports_alpha(self) -> bool {
+        matches!(self,OutputFormat::Png | OutputFormat::Gif | OutputFormat::Tga | OutputFormat::Tiff)
+    }
+
+    pub fn supports_metadata(self) -> bool {
+        matches!(self,OutputFormat::Png | OutputFormat::Jpeg | OutputFormat::Tiff)
+    }
+}
+
+//Open an image and convert it to 8-bit RGB. The format is picked by the file extension or, if that
+//doesn't work, by looking at the start of the file. Unlike image::open, 16-bit PNG and TIFF images
+//are supported.
+pub fn open_rgb(path: &Path) -> Result<RgbImage> {
+    let mut bytes = vec![];
+    File::open(path)?.read_to_end(&mut bytes)?;
+    load_rgb(&bytes,input_format(path,&bytes)?)
+}
+
+//Work out the format of an image file from its extension or, failing that, its contents.
+pub fn input_format(path: &Path,bytes: &[u8]) -> Result<ImageFormat> {
+    let extension = path.extension().map(|extension| extension.to_string_lossy().to_lowercase());
+    match extension.as_deref() {
+        Some("jpg") | Some("jpeg") => Ok(ImageFormat::JPEG),
+        Some("png") => Ok(ImageFormat::PNG),
+        Some("gif") => Ok(ImageFormat::GIF),
+        Some("webp") => Ok(ImageFormat::WEBP),
+        Some("tif") | Some("tiff") => Ok(ImageFormat::TIFF),
+        Some("tga") => Ok(ImageFormat::TGA),
+        Some("bmp") => Ok(ImageFormat::BMP),
+        Some("ico") => Ok(ImageFormat::ICO),
+        Some("hdr") => Ok(ImageFormat::HDR),
+        Some("pnm") | Some("ppm") => Ok(ImageFormat::PPM),
+        _ => image::guess_format(bytes).map_err(|_| Error::UnsupportedFormat("Unrecognized image format".to_string())),
+    }
+}
+
+//Decode an image in memory and convert it to 8-bit RGB. Images without any pixels are rejected.
+pub fn load_rgb(bytes: &[u8],format: ImageFormat) -> Result<RgbImage> {
+    let image = match image::load_from_memory_with_format(bytes,format) {
+        Ok(image) => image.to_rgb(),
+        //The image crate can decode 16-bit PNG and TIFF images but cannot turn them into a
+        //DynamicImage so it's done here instead.
+        Err(ImageError::UnsupportedColor(color_type)) if format == ImageFormat::PNG => decode_16_bit_rgb(PNGDecoder::new(io::Cursor::new(bytes)),color_type)?,
+        Err(ImageError::UnsupportedColor(color_type)) if format == ImageFormat::TIFF => decode_16_bit_rgb(TIFFDecoder::new(io::Cursor::new(bytes))?,color_type)?,
+        Err(e) => return Err(e.into()),
+    };
+
+    if image.width() == 0 || image.height() == 0 {
+        return Err(Error::EmptyImage);
+    }
+    Ok(image)
+}
+
+fn decode_16_bit_rgb<D: ImageDecoder>(mut decoder: D,color_type: ColorType) -> ImageResult<RgbImage> {
+    let channels = match color_type {
+        ColorType::Gray(16) => 1,
+        ColorType::GrayA(16) => 2,
+        ColorType::RGB(16) => 3,
+        ColorType::RGBA(16) => 4,
+        _ => return Err(ImageError::UnsupportedColor(color_type)),
+    };
+
+    let (width,height) = decoder.dimensions()?;
+    let samples: Vec<u16> = match decoder.read_image()? {
+        //PNG hands back big-endian bytes.
+        DecodingResult::U8(bytes) => bytes.chunks(2).map(|pair| ((pair[0] as u16) << 8) | pair.get(1).cloned().unwrap_or(0) as u16).collect(),
+        DecodingResult::U16(samples) => samples,
+    };
+
+    let pixel_count = width as usize * height as usize;
+    if samples.len() < pixel_count * channels {
+        return Err(ImageError::NotEnoughData);
+    }
+
+    //Alpha is dropped the same way DynamicImage::to_rgb does.
+    let to_u8 = |value: u16| ((value as u32 + 128) / 257) as u8;
+    let mut pixels = Vec::with_capacity(pixel_count * 3);
+    for pixel in samples.chunks(channels).take(pixel_count) {
+        if channels < 3 {
+            let value = to_u8(pixel[0]);
+            pixels.extend_from_slice(&[value,value,value]);
+        }
+        else {
+            pixels.extend_from_slice(&[to_u8(pixel[0]),to_u8(pixel[1]),to_u8(pixel[2])]);
+        }
+    }
+
+    RgbImage::from_raw(width,height,pixels).ok_or(ImageError::DimensionError)
+}
+
+fn to_big_endian(samples: &[u16]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(samples.len() * 2);
+    for value in samples {
+        bytes.push((value >> 8) as u8);
+        bytes.push((value & 0xFF) as u8);
+    }
+    bytes
+}
+
+//Encode the canvas and write it out. bit_depth must be 8 or 16 and transparent keeps the alpha
+//channel. quality is only used by JPEG and goes from 1 to 100. metadata is stored in PNG, JPEG, and
+//TIFF files and ignored otherwise.
+pub fn write_canvas<W: Write>(canvas: &dyn Canvas,writer: &mut W,format: OutputFormat,bit_depth: u8,transparent: bool,quality: u8,metadata: &Metadata) -> Result<()> {
+    if bit_depth != 8 && bit_depth != 16 {
+        return Err(Error::InvalidParameter(format!("Bit depth must be 8 or 16, not {}",bit_depth)));
+    }
+    if !(1..=100).contains(&quality) {
+        return Err(Error::InvalidParameter(format!("Quality must be from 1 to 100, not {}",quality)));
+    }
+    if bit_depth == 16 && !format.supports_16_bit() {
+        return Err(Error::UnsupportedFormat(format!("16-bit output is not supported for {}",format.name())));
+    }
+    if transparent && !format.supports_alpha() {
+        return Err(Error::UnsupportedFormat(format!("Transparent output is not supported for {}",format.name())));
+    }
+    if canvas.width() == 0 || canvas.height() == 0 {
+        return Err(Error::EmptyImage);
+    }
+    if canvas.width() > u32::MAX as usize || canvas.height() > u32::MAX as usize {
+        return Err(Error::UnsupportedFormat(format!("{} images cannot be larger than {}x{}",format.name(),u32::MAX,u32::MAX)));
+    }
+
+    let (width,height) = (canvas.width() as u32,canvas.height() as u32);
+    let color_type = match (transparent,bit_depth) {
+        (true,16) => ColorType::RGBA(16),
+        (true,_) => ColorType::RGBA(8),
+        (false,16) => ColorType::RGB(16),
+        (false,_) => ColorType::RGB(8),
+    };
+
+    match format {
+        OutputFormat::Png => {
+            let bytes = match color_type {
+                ColorType::RGBA(16) => to_big_endian(&canvas.to_rgba16()),
+                ColorType::RGB(16) => to_big_endian(&canvas.to_rgb16()),
+                ColorType::RGBA(8) => canvas.to_rgba8(),
+                _ => canvas.to_rgb8(),
+            };
+            let mut encoded = vec![];
+            PNGEncoder::new(&mut encoded).encode(&bytes,width,height,color_type)?;
+            writer.write_all(&metadata::embed_metadata(encoded,format,metadata))?;
+        },
+        OutputFormat::Jpeg => {
+            let mut encoded = vec![];
+            JPEGEncoder::new_with_quality(&mut encoded,quality).encode(&canvas.to_rgb8(),width,height,color_type)?;
+            writer.write_all(&metadata::embed_metadata(encoded,format,metadata))?;
+        },
+        OutputFormat::Bmp => BMPEncoder::new(writer).encode(&canvas.to_rgb8(),width,height,color_type)?,
+        OutputFormat::Gif => {
+            if width > u16::MAX as u32 || height > u16::MAX as u32 {
+                return Err(Error::UnsupportedFormat("GIF images cannot be larger than 65535x65535".to_string()));
+            }
+
+            let mut pixels = if transparent {
+                canvas.to_rgba8()
+            }
+            else {
+                canvas.to_rgb8().chunks(3).flat_map(|pixel| vec![pixel[0],pixel[1],pixel[2],255]).collect()
+            };
+            let frame = Frame::from_rgba(width as u16,height as u16,&mut pixels);
+            GIFEncoder::new(writer).encode(frame).map_err(|e| io::Error::other(e.to_string()))?;
+        },
+        OutputFormat::Pnm => {
+            let bytes = if bit_depth == 16 {
+                to_big_endian(&canvas.to_rgb16())
+            }
+            else {
+                canvas.to_rgb8()
+            };
+            PPMEncoder::new(writer).encode(&bytes,width,height,color_type)?;
+        },
+        OutputFormat::Tga => write_tga(canvas,writer,transparent)?,
+        OutputFormat::Tiff => write_tiff(canvas,writer,bit_depth,transparent,metadata)?,
+    }
+    Ok(())
+}
+
+//Uncompressed true-color TGA stored top to bottom.
+fn write_tga<W: Write>(canvas: &dyn Canvas,writer: &mut W,transparent: bool) -> Result<()> {
+    let (width,height) = (canvas.width(),canvas.height());
+    if width > u16::MAX as usize || height > u16::MAX as usize {
+        return Err(Error::UnsupportedFormat("TGA images cannot be larger than 65535x65535".to_string()));
+    }
+
+    let (bits_per_pixel,descriptor) = if transparent { (32,0x28) } else { (24,0x20) };
+    let mut header = [0u8; 18];
+    header[2] = 2; //Uncompressed true-color.
+    header[12] = (width & 0xFF) as u8;
+    header[13] = (width >> 8) as u8;
+    header[14] = (height & 0xFF) as u8;
+    header[15] = (height >> 8) as u8;
+    header[16] = bits_per_pixel;
+    header[17] = descriptor; //Alpha bits and top-left origin.
+    writer.write_all(&header)?;
+
+    //TGA stores pixels as BGR(A).
+    let mut bytes = if transparent { canvas.to_rgba8() } else { canvas.to_rgb8() };
+    let channels = if transparent { 4 } else { 3 };
+    for pixel in bytes.chunks_mut(channels) {
+        pixel.swap(0,2);
+    }
+    writer.write_all(&bytes)?;
+    Ok(())
+}
+
+//Uncompressed baseline TIFF with a single strip of interleaved 8 or 16-bit samples.
+fn write_tiff<W: Write>(canvas: &dyn Canvas,writer: &mut W,bit_depth: u8,transparent: bool,metadata: &Metadata) -> Result<()> {
+    let samples_per_pixel: u32 = if transparent { 4 } else { 3 };
+    let bytes_per_sample = (bit_depth / 8) as u64;
+    let data_size = canvas.width() as u64 * canvas.height() as u64 * samples_per_pixel as u64 * bytes_per_sample;
+
+    let short = |value: u32| (value as u16).to_le_bytes().to_vec();
+    let long = |value: u32| value.to_le_bytes().to_vec();
+    let ascii = |text: &String| {
+        let mut bytes = text.as_bytes().to_vec();
+        bytes.push(0);
+        bytes
+    };
+
+    //(tag,type,count,value) where type 2 is ASCII, 3 is SHORT, 4 is LONG, and 7 is UNDEFINED.
+    //Values that don't fit in four bytes are written after the directory. Tags must be sorted.
+    let bits_per_sample: Vec<u8> = (0..samples_per_pixel).flat_map(|_| short(bit_depth as u32)).collect();
+    let mut entries: Vec<(u16,u16,u32,Vec<u8>)> = vec![
+        (256,4,1,long(canvas.width() as u32)), //ImageWidth
+        (257,4,1,long(canvas.height() as u32)), //ImageLength
+        (258,3,samples_per_pixel,bits_per_sample), //BitsPerSample
+        (259,3,1,short(1)), //Compression: none
+        (262,3,1,short(2)), //PhotometricInterpretation: RGB
+        (273,4,1,long(0)), //StripOffsets, filled in below.
+        (277,3,1,short(samples_per_pixel)), //SamplesPerPixel
+        (278,4,1,long(canvas.height() as u32)), //RowsPerStrip
+        (279,4,1,long(data_size as u32)), //StripByteCounts
+        (284,3,1,short(1)), //PlanarConfiguration: interleaved
+    ];
+    if let Some(ref artist) = metadata.artist {
+        entries.push((315,2,artist.len() as u32 + 1,ascii(artist))); //Artist
+    }
+    if transparent {
+        entries.push((338,3,1,short(2))); //ExtraSamples: unassociated alpha
+    }
+    if let Some(ref copyright) = metadata.copyright {
+        entries.push((33432,2,copyright.len() as u32 + 1,ascii(copyright))); //Copyright
+    }
+    if let Some(ref profile) = metadata.icc_profile {
+        entries.push((34675,7,profile.len() as u32,profile.clone())); //ICC profile
+    }
+
+    //Out of line values start on a word boundary.
+    let directory_size = 2 + entries.len() as u64 * 12 + 4;
+    let values_size: u64 = entries.iter().filter(|entry| entry.3.len() > 4).map(|entry| (entry.3.len() as u64 + 1) & !1).sum();
+    let data_offset = 8 + directory_size + values_size;
+    if data_offset + data_size > u32::MAX as u64 {
+        return Err(Error::UnsupportedFormat("Image is too large to save as TIFF".to_string()));
+    }
+    entries[5].3 = long(data_offset as u32);
+
+    let mut bytes = Vec::with_capacity(data_offset as usize);
+    let mut values = vec![];
+    bytes.extend_from_slice(b"II*\0");
+    bytes.extend_from_slice(&8u32.to_le_bytes());
+    bytes.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+    for &(tag,field_type,count,ref value) in &entries {
+        bytes.extend_from_slice(&tag.to_le_bytes());
+        bytes.extend_from_slice(&field_type.to_le_bytes());
+        bytes.extend_from_slice(&count.to_le_bytes());
+        if value.len() <= 4 {
+            bytes.extend_from_slice(value);
+            bytes.extend(std::iter::repeat_n(0,4 - value.len()));
+        }
+        else {
+            let offset = 8 + directory_size as u32 + values.len() as u32;
+            bytes.extend_from_slice(&offset.to_le_bytes());
+            values.extend_from_slice(value);
+            if values.len() % 2 != 0 {
+                values.push(0);
+            }
+        }
+    }
+    bytes.extend_from_slice(&0u32.to_le_bytes()); //No more directories.
+    bytes.extend_from_slice(&values);
+    writer.write_all(&bytes)?;
+
+    match (bit_depth,transparent) {
+        (16,_) => {
+            let samples = if transparent { canvas.to_rgba16() } else { canvas.to_rgb16() };
+            let mut bytes = Vec::with_capacity(samples.len() * 2);
+            for value in samples {
+                bytes.extend_from_slice(&value.to_le_bytes());
+            }
+            writer.write_all(&bytes)?
+        },
+        (_,true) => writer.write_all(&canvas.to_rgba8())?,
+        (_,false) => writer.write_all(&canvas.to_rgb8())?,
+    }
+    Ok(())
+}
@@ -1,36 +1,391 @@
 extern crate std;
 
-pub fn gradient(buffer: &[u8],width: u32,height: u32) -> Vec<f32> {
-    let mut result = Vec::with_capacity(buffer.len());
-    result.resize(buffer.len(),0.0);
+use std::collections::BinaryHeap;
 
-    let value_at = |x: u32,y: u32| -> f32 {
+use error::{Error,Result};
+use utility::{min_f32,max_f32,rgb_to_lab,srgb_to_linear,wrap_angle};
+
+//Make sure a buffer with channels values per pixel covers a width x height image. Returns the number
+//of pixels.
+fn check_dimensions(length: usize,width: u32,height: u32,channels: usize) -> Result<usize> {
+    if width == 0 || height == 0 {
+        return Err(Error::EmptyImage);
+    }
+
+    let pixel_count = (width as usize).checked_mul(height as usize);
+    match pixel_count.and_then(|pixel_count| pixel_count.checked_mul(channels)) {
+        Some(size) if size <= length => Ok(size / channels),
+        _ => Err(Error::InvalidParameter(format!("Buffer is too small for a {}x{} image",width,height))),
+    }
+}
+
+//How the brightness of a pixel is worked out from its color.
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum LuminanceModel {
+    //Rec. 601 weights on the sRGB encoded channels.
+    Rec601,
+    //Rec. 709 weights on the sRGB encoded channels.
+    Rec709,
+    //Rec. 709 weights on linear light, which is how bright the pixel physically is. Dark areas get
+    //squeezed together and light ones spread out compared to the encoded values.
+    Linear,
+}
+
+impl LuminanceModel {
+    pub fn from_name(name: &str) -> Option<LuminanceModel> {
+        match name.to_lowercase().as_str() {
+            "rec601" => Some(LuminanceModel::Rec601),
+            "rec709" => Some(LuminanceModel::Rec709),
+            "linear" => Some(LuminanceModel::Linear),
+            _ => None,
+        }
+    }
+}
+
+//Find the brightness of each pixel in an RGB image from 0.0 to 255.0 using the Rec. 601 weights.
+pub fn luminance(buffer: &[u8],width: u32,height: u32) -> Result<Vec<f32>> {
+    luminance_with(buffer,width,height,LuminanceModel::Rec601)
+}
+
+//Find the brightness of each pixel in an RGB image from 0.0 to 255.0 using model.
+pub fn luminance_with(buffer: &[u8],width: u32,height: u32,model: LuminanceModel) -> Result<Vec<f32>> {
+    let pixel_count = check_dimensions(buffer.len(),width,height,3)?;
+    let mut result = vec![];
+    result.try_reserve_exact(pixel_count).map_err(|_| Error::InvalidParameter(format!("Not enough memory for the luminance of a {}x{} image",width,height)))?;
+
+    for pixel in buffer[..pixel_count * 3].chunks(3) {
+        result.push(match model {
+            LuminanceModel::Rec601 => 0.299 * pixel[0] as f32 + 0.587 * pixel[1] as f32 + 0.114 * pixel[2] as f32,
+            LuminanceModel::Rec709 => 0.2126 * pixel[0] as f32 + 0.7152 * pixel[1] as f32 + 0.0722 * pixel[2] as f32,
+            LuminanceModel::Linear => {
+                let linear = |value: u8| srgb_to_linear(value as f32 / 255.0) * 255.0;
+                0.2126 * linear(pixel[0]) + 0.7152 * linear(pixel[1]) + 0.0722 * linear(pixel[2])
+            },
+        });
+    }
+
+    Ok(result)
+}
+
+//Blur a single channel image using a Gaussian with a standard deviation of sigma pixels. Pixels past
+//the border repeat the closest pixel inside of it.
+pub fn gaussian_blur(values: &[f32],width: u32,height: u32,sigma: f32) -> Result<Vec<f32>> {
+    let pixel_count = check_dimensions(values.len(),width,height,1)?;
+    let mut result = vec![];
+    result.try_reserve_exact(pixel_count).map_err(|_| Error::InvalidParameter(format!("Not enough memory to blur a {}x{} image",width,height)))?;
+    if sigma <= 0.0 || !sigma.is_finite() {
+        result.extend_from_slice(&values[..pixel_count]);
+        return Ok(result);
+    }
+
+    //Half of the kernel since it's symmetric. Three standard deviations covers nearly all of it.
+    let radius = (sigma * 3.0).ceil() as usize;
+    let mut kernel: Vec<f32> = (0..radius + 1).map(|offset| (-((offset * offset) as f32) / (2.0 * sigma * sigma)).exp()).collect();
+    let sum = kernel[0] + 2.0 * kernel[1..].iter().sum::<f32>();
+    for weight in kernel.iter_mut() {
+        *weight /= sum;
+    }
+
+    //Blur across the rows and then down the columns.
+    let (width,height) = (width as usize,height as usize);
+    let mut rows = vec![];
+    rows.try_reserve_exact(pixel_count).map_err(|_| Error::InvalidParameter(format!("Not enough memory to blur a {}x{} image",width,height)))?;
+    for y in 0..height {
+        let row = &values[y * width..(y + 1) * width];
+        for x in 0..width {
+            let mut total = row[x] * kernel[0];
+            for offset in 1..radius + 1 {
+                total += (row[x.saturating_sub(offset)] + row[std::cmp::min(x + offset,width - 1)]) * kernel[offset];
+            }
+            rows.push(total);
+        }
+    }
+    for y in 0..height {
+        for x in 0..width {
+            let mut total = rows[y * width + x] * kernel[0];
+            for offset in 1..radius + 1 {
+                total += (rows[y.saturating_sub(offset) * width + x] + rows[std::cmp::min(y + offset,height - 1) * width + x]) * kernel[offset];
+            }
+            result.push(total);
+        }
+    }
+
+    Ok(result)
+}
+
+//The kernel used to measure how quickly brightness changes from one pixel to the next.
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum GradientOperator {
+    Sobel,
+    //Like Sobel with weights that measure diagonal edges more accurately.
+    Scharr,
+    //Weighs the pixels to either side the same instead of favoring the middle one, which picks up
+    //more noise.
+    Prewitt,
+    //Only compares the pixels directly to either side, which picks up the finest details and the
+    //most noise.
+    CentralDifference,
+}
+
+impl GradientOperator {
+    pub fn from_name(name: &str) -> Option<GradientOperator> {
+        match name.to_lowercase().as_str() {
+            "sobel" => Some(GradientOperator::Sobel),
+            "scharr" => Some(GradientOperator::Scharr),
+            "prewitt" => Some(GradientOperator::Prewitt),
+            "central" => Some(GradientOperator::CentralDifference),
+            _ => None,
+        }
+    }
+
+    //Weights of the corner and middle pixels on either side along with what the result is scaled by
+    //so every operator is as strong as Sobel across a straight edge.
+    fn weights(&self) -> (f32,f32,f32) {
+        match *self {
+            GradientOperator::Sobel => (1.0,2.0,1.0),
+            GradientOperator::Scharr => (3.0,10.0,0.25),
+            GradientOperator::Prewitt => (1.0,1.0,4.0 / 3.0),
+            GradientOperator::CentralDifference => (0.0,1.0,4.0),
+        }
+    }
+}
+
+//How gradient_with and multiscale_gradient_with measure the gradient.
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub struct GradientOptions {
+    pub operator: GradientOperator,
+    pub luminance: LuminanceModel,
+    //Measure the red, green, and blue channels separately and combine them using Di Zenzo's method
+    //instead of measuring luminance. Picks up edges between colors that are equally bright. The
+    //channels are linear light when luminance is LuminanceModel::Linear.
+    pub color: bool,
+}
+
+impl Default for GradientOptions {
+    fn default() -> GradientOptions {
+        GradientOptions {
+            operator: GradientOperator::Sobel,
+            luminance: LuminanceModel::Rec601,
+            color: false,
+        }
+    }
+}
+
+//Find the gradient of an RGB image using the Sobel operator. The result holds the magnitude and
+//angle of each pixel.
+pub fn gradient(buffer: &[u8],width: u32,height: u32) -> Result<Vec<f32>> {
+    gradient_with(buffer,width,height,&GradientOptions::default())
+}
+
+//Like gradient except the operator and what's measured come from options.
+pub fn gradient_with(buffer: &[u8],width: u32,height: u32,options: &GradientOptions) -> Result<Vec<f32>> {
+    multiscale_gradient_with(buffer,width,height,0.0,options)
+}
+
+//Split an RGB image into the single channel images the gradient is measured on.
+fn gradient_channels(buffer: &[u8],width: u32,height: u32,options: &GradientOptions) -> Result<Vec<Vec<f32>>> {
+    if !options.color {
+        return Ok(vec![luminance_with(buffer,width,height,options.luminance)?]);
+    }
+
+    let pixel_count = check_dimensions(buffer.len(),width,height,3)?;
+    let mut channels = vec![];
+    for channel in 0..3 {
+        let mut values = vec![];
+        values.try_reserve_exact(pixel_count).map_err(|_| Error::InvalidParameter(format!("Not enough memory for the gradient of a {}x{} image",width,height)))?;
+        for pixel in buffer[..pixel_count * 3].chunks(3) {
+            values.push(match options.luminance {
+                LuminanceModel::Linear => srgb_to_linear(pixel[channel] as f32 / 255.0) * 255.0,
+                LuminanceModel::Rec601 | LuminanceModel::Rec709 => pixel[channel] as f32,
+            });
+        }
+        channels.push(values);
+    }
+    Ok(channels)
+}
+
+//Apply operator to a single channel image. The result holds the horizontal and vertical derivative
+//of each pixel.
+fn derivatives(values: &[f32],width: u32,height: u32,operator: GradientOperator) -> Result<Vec<(f32,f32)>> {
+    let pixel_count = check_dimensions(values.len(),width,height,1)?;
+    let mut result = vec![];
+    result.try_reserve_exact(pixel_count).map_err(|_| Error::InvalidParameter(format!("Not enough memory for the gradient of a {}x{} image",width,height)))?;
+
+    let (width,height) = (width as usize,height as usize);
+    let value_at = |x: usize,y: usize| -> f32 {
         let x = std::cmp::min(x,width - 1);
         let y = std::cmp::min(y,height - 1);
-
-        let index = ((y * width + x) * 3) as usize;
-        0.299 * buffer[index + 0] as f32 + 0.587 * buffer[index + 1] as f32 + 0.114 * buffer[index + 2] as f32
+        values[y * width + x]
     };
 
+    let (corner,middle,scale) = operator.weights();
     for y in 0..height {
         for x in 0..width {
             let (a,b,c,d,_,f,g,h,i) = (
                 value_at(x.saturating_sub(1),y.saturating_sub(1)),value_at(x,y.saturating_sub(1)),value_at(x + 1,y.saturating_sub(1)),
                 value_at(x.saturating_sub(1),y),value_at(x,y),value_at(x + 1,y),
                 value_at(x.saturating_sub(1),y + 1),value_at(x,y + 1),value_at(x + 1,y + 1));
-            let hsum = a * -1.0 + c + d * -2.0 + f * 2.0 + g * -1.0 + i;
-            let vsum = a * -1.0 + b * -2.0 + c * -1.0 + g + h * 2.0 + i;
+            let hsum = a * -corner + c * corner + d * -middle + f * middle + g * -corner + i * corner;
+            let vsum = a * -corner + b * -middle + c * -corner + g * corner + h * middle + i * corner;
+            result.push((hsum * scale,vsum * scale));
+        }
+    }
+
+    Ok(result)
+}
+
+//Turn the derivatives of each channel at a pixel into a magnitude and angle. A single channel is
+//used as is. Several are combined with Di Zenzo's method, which finds the direction the color
+//changes the most in. That direction only has an orientation so it's flipped to point the same way
+//as the sum of the channels' gradients. The magnitude is scaled so channels that are all the same
+//give the same gradient as one of them alone.
+fn combine_derivatives(derivatives: &[(f32,f32)]) -> (f32,f32) {
+    if let [(hsum,vsum)] = *derivatives {
+        return (hsum.hypot(vsum),vsum.atan2(hsum));
+    }
+
+    let (mut xx,mut yy,mut xy,mut sum_x,mut sum_y) = (0.0,0.0,0.0,0.0,0.0);
+    for &(hsum,vsum) in derivatives {
+        xx += hsum * hsum;
+        yy += vsum * vsum;
+        xy += hsum * vsum;
+        sum_x += hsum;
+        sum_y += vsum;
+    }
+    let mut theta = 0.5 * (2.0 * xy).atan2(xx - yy);
+    let magnitude = (0.5 * (xx + yy + (xx - yy).hypot(2.0 * xy)) / derivatives.len() as f32).sqrt();
+    if theta.cos() * sum_x + theta.sin() * sum_y < 0.0 {
+        theta = wrap_angle(theta + std::f32::consts::PI);
+    }
+    (magnitude,theta)
+}
+
+//One level of a Gaussian pyramid.
+#[derive(Clone,Debug,PartialEq)]
+pub struct PyramidLevel {
+    pub values: Vec<f32>,
+    pub width: u32,
+    pub height: u32,
+}
 
-            let magnitude = hsum.hypot(vsum);
-            let theta = vsum.atan2(hsum);
+//Standard deviation in pixels of the blur applied before each halving so the smaller levels don't
+//alias.
+const PYRAMID_SIGMA: f32 = 1.0;
 
-            let output_index = ((y * width + x) * 2) as usize;
-            result[output_index + 0] = magnitude;
-            result[output_index + 1] = theta;
+//Build a Gaussian pyramid from a single channel image. The first level is the image itself and each
+//level after it is blurred and then halved, keeping every other pixel starting with the first, so
+//pixel x,y of level n sits over pixel x * 2^n,y * 2^n of the image. Odd sizes round up. Stops at
+//level_count levels or once a level is a single pixel, whichever comes first, but always has at
+//least the first level.
+pub fn gaussian_pyramid(values: &[f32],width: u32,height: u32,level_count: usize) -> Result<Vec<PyramidLevel>> {
+    let pixel_count = check_dimensions(values.len(),width,height,1)?;
+    let memory_error = || Error::InvalidParameter(format!("Not enough memory for the pyramid of a {}x{} image",width,height));
+    let mut first = vec![];
+    first.try_reserve_exact(pixel_count).map_err(|_| memory_error())?;
+    first.extend_from_slice(&values[..pixel_count]);
+    let mut levels = vec![PyramidLevel {
+        values: first,
+        width,
+        height,
+    }];
+
+    while levels.len() < level_count {
+        let (blurred,width,height) = {
+            let last = &levels[levels.len() - 1];
+            if last.width == 1 && last.height == 1 {
+                break;
+            }
+            (gaussian_blur(&last.values,last.width,last.height,PYRAMID_SIGMA)?,last.width as usize,last.height as usize)
+        };
+
+        let (next_width,next_height) = (width.div_ceil(2),height.div_ceil(2));
+        let mut next = vec![];
+        next.try_reserve_exact(next_width * next_height).map_err(|_| memory_error())?;
+        for y in 0..next_height {
+            for x in 0..next_width {
+                next.push(blurred[y * 2 * width + x * 2]);
+            }
         }
+        levels.push(PyramidLevel {
+            values: next,
+            width: next_width as u32,
+            height: next_height as u32,
+        });
     }
 
-    result
+    Ok(levels)
+}
+
+//Like gradient except the Sobel operator is applied to a level of the image's Gaussian pyramid so
+//only details at that scale or bigger are picked up. Level 0.0 is the full image, 1.0 is half size,
+//2.0 is quarter size, and so on. Levels in between blend the gradients of the two closest levels.
+//Levels past the smallest one in the pyramid use the smallest. The gradient is measured in pixels
+//of the level and stretched back out so the result still has one magnitude and angle per pixel of
+//the full image.
+pub fn multiscale_gradient(buffer: &[u8],width: u32,height: u32,level: f32) -> Result<Vec<f32>> {
+    multiscale_gradient_with(buffer,width,height,level,&GradientOptions::default())
+}
+
+//Like multiscale_gradient except the operator and what's measured come from options.
+pub fn multiscale_gradient_with(buffer: &[u8],width: u32,height: u32,level: f32,options: &GradientOptions) -> Result<Vec<f32>> {
+    if level.is_nan() {
+        return Err(Error::InvalidParameter("Pyramid level must be a number".to_string()));
+    }
+    let level = if level.is_finite() { max_f32(level,0.0) } else { 64.0 };
+
+    //Measure each channel at the two closest levels.
+    let channels = gradient_channels(buffer,width,height,options)?;
+    let mut measured = vec![];
+    for channel in &channels {
+        let pyramid = gaussian_pyramid(channel,width,height,level.ceil() as usize + 1)?;
+        let deepest = pyramid.len() - 1;
+        let (lower,upper) = (std::cmp::min(level.floor() as usize,deepest),std::cmp::min(level.ceil() as usize,deepest));
+        let blend = if lower == upper { 0.0 } else { level - lower as f32 };
+        let fine = derivatives(&pyramid[lower].values,pyramid[lower].width,pyramid[lower].height,options.operator)?;
+        let coarse = if blend > 0.0 { Some(derivatives(&pyramid[upper].values,pyramid[upper].width,pyramid[upper].height,options.operator)?) } else { None };
+        let sizes = ((pyramid[lower].width as usize,pyramid[lower].height as usize),(pyramid[upper].width as usize,pyramid[upper].height as usize));
+        measured.push((fine,coarse,sizes,(lower,upper),blend));
+    }
+
+    //Bilinearly sample the derivatives of a level at a point of the full image.
+    let sample = |derivatives: &[(f32,f32)],index: usize,(width,height): (usize,usize),x: usize,y: usize| {
+        if index == 0 {
+            return derivatives[y * width + x];
+        }
+        let scale = 2.0f32.powi(index as i32);
+        let (x,y) = (min_f32(x as f32 / scale,(width - 1) as f32),min_f32(y as f32 / scale,(height - 1) as f32));
+        let (left,top) = (x as usize,y as usize);
+        let (right,bottom) = (std::cmp::min(left + 1,width - 1),std::cmp::min(top + 1,height - 1));
+        let (fraction_x,fraction_y) = (x - left as f32,y - top as f32);
+        let lerp = |(x1,y1): (f32,f32),(x2,y2): (f32,f32),t: f32| (x1 + (x2 - x1) * t,y1 + (y2 - y1) * t);
+        let upper_row = lerp(derivatives[top * width + left],derivatives[top * width + right],fraction_x);
+        let lower_row = lerp(derivatives[bottom * width + left],derivatives[bottom * width + right],fraction_x);
+        lerp(upper_row,lower_row,fraction_y)
+    };
+
+    let pixel_count = channels[0].len();
+    let mut result = vec![];
+    result.try_reserve_exact(pixel_count * 2).map_err(|_| Error::InvalidParameter(format!("Not enough memory for the gradient of a {}x{} image",width,height)))?;
+    let mut pixel = Vec::with_capacity(channels.len());
+    for y in 0..height as usize {
+        for x in 0..width as usize {
+            pixel.clear();
+            for &(ref fine,ref coarse,(fine_size,coarse_size),(lower,upper),blend) in &measured {
+                let (mut hsum,mut vsum) = sample(fine,lower,fine_size,x,y);
+                if let Some(ref coarse) = *coarse {
+                    let (coarse_hsum,coarse_vsum) = sample(coarse,upper,coarse_size,x,y);
+                    hsum += (coarse_hsum - hsum) * blend;
+                    vsum += (coarse_vsum - vsum) * blend;
+                }
+                pixel.push((hsum,vsum));
+            }
+            let (magnitude,theta) = combine_derivatives(&pixel);
+            result.push(magnitude);
+            result.push(theta);
+        }
+    }
+
+    Ok(result)
 }
 
 pub fn angle_to_direction(angle: f32) -> u32 {
@@ -42,23 +397,21 @@ pub fn angle_to_direction(angle: f32) -> u32 {
     angle.round() as u32 % 4
 }
 
-pub fn non_maximum_suppression(gradient: &[f32],width: u32,height: u32) -> Vec<u8> {
-    if width == 0 || height == 0 {
-        return vec![];
-    }
-
-    let width = width as usize;
-    let height = height as usize;
-
+//Thin the gradient down to edges one pixel wide. Edge pixels are 255 and everything else is 0. The
+//border is never marked as an edge.
+pub fn non_maximum_suppression(gradient: &[f32],width: u32,height: u32) -> Result<Vec<u8>> {
     const THRESHOLD_HIGH: f32 = 110.0;
     const THRESHOLD_LOW: f32 = THRESHOLD_HIGH / 2.0;
 
-    let result_size = width * height;
-    let mut result = Vec::with_capacity(result_size);
+    let result_size = check_dimensions(gradient.len(),width,height,2)?;
+    let mut result = vec![];
+    result.try_reserve_exact(result_size).map_err(|_| Error::InvalidParameter(format!("Not enough memory for the edges of a {}x{} image",width,height)))?;
     result.resize(result_size,0);
 
-    for y in 1..height - 1 {
-        for x in 1..width - 1 {
+    let width = width as usize;
+    let height = height as usize;
+    for y in 1..height.saturating_sub(1) {
+        for x in 1..width.saturating_sub(1) {
             let base_index = y * width + x;
             let input_index = base_index * 2;
             let output_index = base_index;
@@ -85,5 +438,456 @@ pub fn non_maximum_suppression(gradient: &[f32],width: u32,height: u32) -> Vec<u
         }
     }
 
-    result
+    Ok(result)
+}
+
+//A straight edge found by hough_lines. The ends are measured from pixel centers, so x,y is at the
+//center of pixel x,y, and votes is how many edge pixels lie along it.
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub struct HoughSegment {
+    pub x1: f32,
+    pub y1: f32,
+    pub x2: f32,
+    pub y2: f32,
+    pub votes: u32,
+}
+
+//Lines are looked for at this many angles over half of a turn and edge pixels up to
+//HOUGH_DISTANCE_MAX pixels away from one count as lying along it.
+const HOUGH_ANGLES: usize = 180;
+const HOUGH_DISTANCE_MAX: f32 = 1.0;
+
+//How many peaks that turn out to be too short are passed over for each line asked for before giving
+//up. Busy textures can have lots of them.
+const HOUGH_REJECTIONS_PER_LINE: usize = 8;
+
+//Find up to line_count of the strongest straight lines through the edge pixels (255) of a width x
+//height edge map like the one from non_maximum_suppression using the Hough transform. Each line is
+//cut down to the longest stretch of edge pixels along it without gaps longer than gap_max pixels,
+//and stretches shorter than length_min edge pixels are skipped. Edge pixels are only used by one
+//line. Returned from the most votes to the least.
+pub fn hough_lines(edges: &[u8],width: u32,height: u32,line_count: usize,length_min: usize,gap_max: f32) -> Result<Vec<HoughSegment>> {
+    let pixel_count = check_dimensions(edges.len(),width,height,1)?;
+    let (width,height) = (width as usize,height as usize);
+    let points: Vec<(f32,f32)> = (0..pixel_count).filter(|&index| edges[index] == 255).map(|index| ((index % width) as f32,(index / width) as f32)).collect();
+    let mut used = vec![false; points.len()];
+
+    //Lines are stored by their angle and signed distance from the top left pixel, which is at most
+    //the length of the image's diagonal.
+    let angles: Vec<(f32,f32)> = (0..HOUGH_ANGLES).map(|index| {
+        let angle = index as f32 * std::f32::consts::PI / HOUGH_ANGLES as f32;
+        (angle.cos(),angle.sin())
+    }).collect();
+    let offset = (width as f64).hypot(height as f64).ceil() as usize;
+    let distances = offset * 2 + 1;
+    let cell = |(x,y): (f32,f32),angle: usize| {
+        let (cos,sin) = angles[angle];
+        angle * distances + ((x * cos + y * sin).round() as isize + offset as isize) as usize
+    };
+
+    let mut votes: Vec<u32> = vec![];
+    votes.try_reserve_exact(HOUGH_ANGLES * distances).map_err(|_| Error::InvalidParameter(format!("Not enough memory to find the lines of a {}x{} image",width,height)))?;
+    votes.resize(HOUGH_ANGLES * distances,0);
+    for &point in &points {
+        for angle in 0..HOUGH_ANGLES {
+            votes[cell(point,angle)] += 1;
+        }
+    }
+
+    //Every line with enough votes is a candidate. Votes are taken back as lines are found so a
+    //candidate's count is checked again when it comes up and it's put back if it went down.
+    let length_min = std::cmp::max(length_min,2);
+    let mut candidates: BinaryHeap<(u32,usize)> = votes.iter().cloned().enumerate().filter(|&(_,count)| count as usize >= length_min).map(|(index,count)| (count,index)).collect();
+    let mut segments = vec![];
+    let mut rejections = 0;
+    while segments.len() < line_count && rejections <= line_count * HOUGH_REJECTIONS_PER_LINE {
+        let (count,best) = match candidates.pop() {
+            Some(candidate) => candidate,
+            None => break,
+        };
+        if votes[best] != count {
+            if votes[best] as usize >= length_min {
+                candidates.push((votes[best],best));
+            }
+            continue;
+        }
+        let (cos,sin) = angles[best / distances];
+        let distance = (best % distances) as f32 - offset as f32;
+
+        //Sort the unused edge pixels near the line by how far along it they are and find the longest
+        //stretch without a big gap.
+        let mut along: Vec<(f32,usize)> = points.iter().enumerate()
+            .filter(|&(index,&(x,y))| !used[index] && (x * cos + y * sin - distance).abs() <= HOUGH_DISTANCE_MAX)
+            .map(|(index,&(x,y))| (y * cos - x * sin,index))
+            .collect();
+        along.sort_by(|lhs,rhs| lhs.0.partial_cmp(&rhs.0).unwrap_or(std::cmp::Ordering::Equal));
+        let (mut run_start,mut run_end,mut start) = (0,0,0);
+        for index in 1..along.len() + 1 {
+            if index == along.len() || along[index].0 - along[index - 1].0 > gap_max {
+                if index - start > run_end - run_start {
+                    run_start = start;
+                    run_end = index;
+                }
+                start = index;
+            }
+        }
+        let run = &along[run_start..run_end];
+        if run.len() < length_min {
+            rejections += 1;
+            continue;
+        }
+
+        //Take back the votes of the pixels along the segment so they don't pull other lines toward
+        //this one.
+        for &(_,index) in run {
+            used[index] = true;
+            for angle in 0..HOUGH_ANGLES {
+                votes[cell(points[index],angle)] -= 1;
+            }
+        }
+
+        let point = |along: f32| (distance * cos - along * sin,distance * sin + along * cos);
+        let ((x1,y1),(x2,y2)) = (point(run[0].0),point(run[run.len() - 1].0));
+        segments.push(HoughSegment {
+            x1,
+            y1,
+            x2,
+            y2,
+            votes: run.len() as u32,
+        });
+    }
+
+    segments.sort_by_key(|segment| std::cmp::Reverse(segment.votes));
+    Ok(segments)
+}
+
+//Neighbors of a pixel with the ones sharing a side first so chains step around corners instead of
+//cutting across them.
+const NEIGHBORS: [(isize,isize); 8] = [(1,0),(0,1),(-1,0),(0,-1),(1,1),(-1,1),(-1,-1),(1,-1)];
+
+//Link the edge pixels (255) of a width x height edge map like the one from non_maximum_suppression
+//into chains of touching pixels. Chains start at loose ends where possible and split where edges
+//branch. A branch begins or ends on the pixel it branches from and a chain that loops back on itself
+//ends with its first pixel. Chains of fewer than length_min pixels are left out. Points are pixel
+//centers, so x,y is at the center of pixel x,y. When tolerance is above 0.0 each chain is simplified
+//with douglas_peucker.
+pub fn edge_chains(edges: &[u8],width: u32,height: u32,length_min: usize,tolerance: f32) -> Result<Vec<Vec<(f32,f32)>>> {
+    const UNOWNED: usize = usize::MAX;
+
+    let pixel_count = check_dimensions(edges.len(),width,height,1)?;
+    let (width,height) = (width as usize,height as usize);
+    let mut owner = vec![];
+    owner.try_reserve_exact(pixel_count).map_err(|_| Error::InvalidParameter(format!("Not enough memory to link the edges of a {}x{} image",width,height)))?;
+    owner.resize(pixel_count,UNOWNED);
+
+    let neighbors = |index: usize| {
+        let (x,y) = ((index % width) as isize,(index / width) as isize);
+        NEIGHBORS.iter().filter_map(move |&(offset_x,offset_y)| {
+            let (x,y) = (x + offset_x,y + offset_y);
+            if x < 0 || y < 0 || x >= width as isize || y >= height as isize {
+                return None;
+            }
+            let neighbor = y as usize * width + x as usize;
+            if edges[neighbor] == 255 { Some(neighbor) } else { None }
+        })
+    };
+
+    //Step to untaken neighbors until there are none left. Then reach over to a neighbor on another
+    //chain, or back to the start of this one if close is true, so the chains stay joined up.
+    let walk = |owner: &mut Vec<usize>,chain: &mut Vec<usize>,id: usize,close: bool| {
+        loop {
+            let current = chain[chain.len() - 1];
+            match neighbors(current).find(|&neighbor| owner[neighbor] == UNOWNED) {
+                Some(next) => {
+                    owner[next] = id;
+                    chain.push(next);
+                },
+                None => {
+                    let first = chain[0];
+                    let join = neighbors(current).find(|&neighbor| owner[neighbor] != id)
+                        .or_else(|| neighbors(current).find(|&neighbor| close && neighbor == first && chain.len() > 3));
+                    if let Some(join) = join {
+                        chain.push(join);
+                    }
+                    return;
+                },
+            }
+        }
+    };
+
+    //Loose ends have exactly one neighbor. Anything left over after tracing from them is a loop or
+    //hangs off of a loop.
+    let loose_end = |index: usize| neighbors(index).count() == 1;
+    let starts = (0..pixel_count).filter(|&index| edges[index] == 255 && loose_end(index))
+        .chain((0..pixel_count).filter(|&index| edges[index] == 255));
+
+    let mut chains = vec![];
+    let mut id = 0;
+    for start in starts {
+        if owner[start] != UNOWNED {
+            continue;
+        }
+        owner[start] = id;
+
+        //Walk one way and then the other way from the start.
+        let mut forward = vec![start];
+        walk(&mut owner,&mut forward,id,true);
+        let mut chain = vec![start];
+        if forward[forward.len() - 1] != start {
+            walk(&mut owner,&mut chain,id,false);
+        }
+        chain.reverse();
+        chain.extend_from_slice(&forward[1..]);
+        id += 1;
+
+        if chain.len() < length_min {
+            continue;
+        }
+        let points: Vec<(f32,f32)> = chain.iter().map(|&index| ((index % width) as f32,(index / width) as f32)).collect();
+        chains.push(if tolerance > 0.0 { douglas_peucker(&points,tolerance) } else { points });
+    }
+
+    Ok(chains)
+}
+
+//Simplify a polyline with the Ramer-Douglas-Peucker algorithm. Points are dropped as long as the
+//simplified line stays within tolerance of every one of them. The first and last points are always
+//kept.
+pub fn douglas_peucker(points: &[(f32,f32)],tolerance: f32) -> Vec<(f32,f32)> {
+    if points.len() < 3 {
+        return points.to_vec();
+    }
+
+    //Distance from point to the line through start and end, or to start if they're the same.
+    let distance = |(x,y): (f32,f32),(x1,y1): (f32,f32),(x2,y2): (f32,f32)| {
+        let length = (x2 - x1).hypot(y2 - y1);
+        if length == 0.0 {
+            (x - x1).hypot(y - y1)
+        }
+        else {
+            ((x2 - x1) * (y1 - y) - (x1 - x) * (y2 - y1)).abs() / length
+        }
+    };
+
+    let mut keep = vec![false; points.len()];
+    keep[0] = true;
+    keep[points.len() - 1] = true;
+    let mut ranges = vec![(0,points.len() - 1)];
+    while let Some((first,last)) = ranges.pop() {
+        let farthest = (first + 1..last).map(|index| (index,distance(points[index],points[first],points[last])))
+            .fold((first,0.0),|best,candidate| if candidate.1 > best.1 { candidate } else { best });
+        if farthest.1 > tolerance {
+            keep[farthest.0] = true;
+            ranges.push((first,farthest.0));
+            ranges.push((farthest.0,last));
+        }
+    }
+
+    points.iter().zip(keep.iter()).filter(|&(_,&keep)| keep).map(|(&point,_)| point).collect()
+}
+
+//A division of an image into regions. labels holds the region of each pixel and colors holds the
+//mean color of each region, indexed by label.
+#[derive(Clone,Debug,PartialEq)]
+pub struct Segmentation {
+    pub labels: Vec<u32>,
+    pub colors: Vec<(u8,u8,u8)>,
+}
+
+//How many times pixels are reassigned to their closest center before the centers are settled.
+const SLIC_ITERATIONS: usize = 10;
+
+//Split an RGB image into about region_count compact regions of similar color using SLIC superpixels.
+//Compactness trades color for distance: around 10.0 follows edges in the image closely while higher
+//values make regions closer to squares. Every region is a single 4-connected piece and labels run
+//from 0 up to the number of regions without any gaps.
+pub fn slic(buffer: &[u8],width: u32,height: u32,region_count: usize,compactness: f32) -> Result<Segmentation> {
+    let pixel_count = check_dimensions(buffer.len(),width,height,3)?;
+    if region_count == 0 {
+        return Err(Error::InvalidParameter("Region count must be at least 1".to_string()));
+    }
+    if compactness <= 0.0 || !compactness.is_finite() {
+        return Err(Error::InvalidParameter("Compactness must be greater than 0".to_string()));
+    }
+    let memory_error = || Error::InvalidParameter(format!("Not enough memory to segment a {}x{} image",width,height));
+    let (width,height) = (width as usize,height as usize);
+
+    let mut lab = vec![];
+    lab.try_reserve_exact(pixel_count).map_err(|_| memory_error())?;
+    for pixel in buffer[..pixel_count * 3].chunks(3) {
+        lab.push(rgb_to_lab(pixel[0],pixel[1],pixel[2]));
+    }
+
+    //Spread the centers out on a grid with cells of about step x step pixels and then nudge each one
+    //to the flattest spot nearby so it doesn't start out on an edge.
+    let step = max_f32((pixel_count as f32 / region_count as f32).sqrt(),1.0);
+    let columns = std::cmp::max((width as f32 / step).round() as usize,1);
+    let rows = std::cmp::max((height as f32 / step).round() as usize,1);
+    let roughness = |x: usize,y: usize| {
+        let at = |x: usize,y: usize| lab[std::cmp::min(y,height - 1) * width + std::cmp::min(x,width - 1)].0;
+        let dx = at(x + 1,y) - at(x.saturating_sub(1),y);
+        let dy = at(x,y + 1) - at(x,y.saturating_sub(1));
+        dx * dx + dy * dy
+    };
+    //Lightness, a, b, x, and y.
+    let mut centers: Vec<(f32,f32,f32,f32,f32)> = vec![];
+    for row in 0..rows {
+        for column in 0..columns {
+            let x = ((column as f32 + 0.5) * width as f32 / columns as f32) as usize;
+            let y = ((row as f32 + 0.5) * height as f32 / rows as f32) as usize;
+            let mut best = (x,y);
+            for ny in y.saturating_sub(1)..std::cmp::min(y + 2,height) {
+                for nx in x.saturating_sub(1)..std::cmp::min(x + 2,width) {
+                    if roughness(nx,ny) < roughness(best.0,best.1) {
+                        best = (nx,ny);
+                    }
+                }
+            }
+            let (l,a,b) = lab[best.1 * width + best.0];
+            centers.push((l,a,b,best.0 as f32,best.1 as f32));
+        }
+    }
+
+    //Each center only claims pixels within step of itself, which keeps this linear in the number of
+    //pixels. Pixels no center reaches keep UNASSIGNED and are merged into a neighbor below.
+    const UNASSIGNED: u32 = u32::MAX;
+    let mut labels = vec![];
+    labels.try_reserve_exact(pixel_count).map_err(|_| memory_error())?;
+    labels.resize(pixel_count,UNASSIGNED);
+    let mut distances = vec![];
+    distances.try_reserve_exact(pixel_count).map_err(|_| memory_error())?;
+    distances.resize(pixel_count,f32::INFINITY);
+    let weight = (compactness / step) * (compactness / step);
+    let reach = step.ceil() as isize;
+    for _ in 0..SLIC_ITERATIONS {
+        for distance in distances.iter_mut() {
+            *distance = f32::INFINITY;
+        }
+        for (label,&(l,a,b,cx,cy)) in centers.iter().enumerate() {
+            let (x,y) = (cx.round() as isize,cy.round() as isize);
+            for py in std::cmp::max(y - reach,0)..std::cmp::min(y + reach + 1,height as isize) {
+                for px in std::cmp::max(x - reach,0)..std::cmp::min(x + reach + 1,width as isize) {
+                    let index = py as usize * width + px as usize;
+                    let (pl,pa,pb) = lab[index];
+                    let color = (pl - l) * (pl - l) + (pa - a) * (pa - a) + (pb - b) * (pb - b);
+                    let space = (px as f32 - cx) * (px as f32 - cx) + (py as f32 - cy) * (py as f32 - cy);
+                    let distance = color + space * weight;
+                    if distance < distances[index] {
+                        distances[index] = distance;
+                        labels[index] = label as u32;
+                    }
+                }
+            }
+        }
+
+        //Move each center to the middle of the pixels it claimed. Centers without any stay put.
+        let mut sums = vec![(0.0f64,0.0f64,0.0f64,0.0f64,0.0f64,0u32); centers.len()];
+        for (index,&label) in labels.iter().enumerate() {
+            if label == UNASSIGNED {
+                continue;
+            }
+            let (l,a,b) = lab[index];
+            let sum = &mut sums[label as usize];
+            sum.0 += l as f64;
+            sum.1 += a as f64;
+            sum.2 += b as f64;
+            sum.3 += (index % width) as f64;
+            sum.4 += (index / width) as f64;
+            sum.5 += 1;
+        }
+        for (center,sum) in centers.iter_mut().zip(sums.iter()) {
+            if sum.5 > 0 {
+                let count = sum.5 as f64;
+                *center = ((sum.0 / count) as f32,(sum.1 / count) as f32,(sum.2 / count) as f32,(sum.3 / count) as f32,(sum.4 / count) as f32);
+            }
+        }
+    }
+
+    //Clusters can come out in several pieces. Give each piece its own label, except for small ones
+    //which join the region next to where they start.
+    let size_min = std::cmp::max((step * step / 4.0) as usize,1);
+    let mut regions = vec![];
+    regions.try_reserve_exact(pixel_count).map_err(|_| memory_error())?;
+    regions.resize(pixel_count,UNASSIGNED);
+    let mut region_total = 0u32;
+    let mut piece = vec![];
+    for start in 0..pixel_count {
+        if regions[start] != UNASSIGNED {
+            continue;
+        }
+
+        //Pixels are visited in order so the ones to the left and above are already in a region.
+        let (x,y) = (start % width,start / width);
+        let adjacent = if x > 0 { Some(regions[start - 1]) } else if y > 0 { Some(regions[start - width]) } else { None };
+
+        piece.clear();
+        piece.push(start);
+        regions[start] = region_total;
+        let mut next = 0;
+        while next < piece.len() {
+            let index = piece[next];
+            next += 1;
+            let (x,y) = ((index % width) as isize,(index / width) as isize);
+            for &(offset_x,offset_y) in &NEIGHBORS[..4] {
+                let (x,y) = (x + offset_x,y + offset_y);
+                if x < 0 || y < 0 || x >= width as isize || y >= height as isize {
+                    continue;
+                }
+                let neighbor = y as usize * width + x as usize;
+                if regions[neighbor] == UNASSIGNED && labels[neighbor] == labels[start] {
+                    regions[neighbor] = region_total;
+                    piece.push(neighbor);
+                }
+            }
+        }
+
+        match adjacent {
+            Some(adjacent) if piece.len() < size_min => {
+                for &index in &piece {
+                    regions[index] = adjacent;
+                }
+            },
+            _ => region_total += 1,
+        }
+    }
+
+    let mut sums = vec![(0u64,0u64,0u64,0u64); region_total as usize];
+    for (index,&region) in regions.iter().enumerate() {
+        let sum = &mut sums[region as usize];
+        sum.0 += buffer[index * 3] as u64;
+        sum.1 += buffer[index * 3 + 1] as u64;
+        sum.2 += buffer[index * 3 + 2] as u64;
+        sum.3 += 1;
+    }
+    let colors = sums.iter().map(|&(red,green,blue,count)| {
+        let count = std::cmp::max(count,1);
+        (((red + count / 2) / count) as u8,((green + count / 2) / count) as u8,((blue + count / 2) / count) as u8)
+    }).collect();
+
+    Ok(Segmentation {
+        labels: regions,
+        colors,
+    })
+}
+
+//Mark the pixels of a width x height label map like the one from slic that sit on the border
+//between two regions. A pixel is on the border when the pixel to its right or below it is in a
+//different region. The result is 255 on borders and 0 elsewhere, the same as an edge map from
+//non_maximum_suppression.
+pub fn region_boundaries(labels: &[u32],width: u32,height: u32) -> Result<Vec<u8>> {
+    let pixel_count = check_dimensions(labels.len(),width,height,1)?;
+    let mut result = vec![];
+    result.try_reserve_exact(pixel_count).map_err(|_| Error::InvalidParameter(format!("Not enough memory for the region boundaries of a {}x{} image",width,height)))?;
+
+    let (width,height) = (width as usize,height as usize);
+    for y in 0..height {
+        for x in 0..width {
+            let label = labels[y * width + x];
+            let right = x + 1 < width && labels[y * width + x + 1] != label;
+            let below = y + 1 < height && labels[(y + 1) * width + x] != label;
+            result.push(if right || below { 255 } else { 0 });
+        }
+    }
+
+    Ok(result)
 }
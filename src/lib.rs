@@ -1,3 +1,19 @@
+extern crate image;
+extern crate inflate;
+extern crate rand;
+
+pub mod canvas;
+pub mod contour;
+mod error;
+pub mod formats;
 pub mod imageprocessing;
+pub mod metadata;
 pub mod painter;
+pub mod path;
+pub mod render;
+pub mod stipple;
+pub mod strokelog;
+pub mod triangulation;
 pub mod utility;
+
+pub use error::{Error,Result};
@@ -1,243 +1,396 @@
 extern crate line_splat;
-#[macro_use(arg_enum,value_t,_clap_count_exprs)]
 extern crate clap;
-extern crate image;
-extern crate rand;
 
+use std::fs::{self,File};
+use std::io::{self,BufWriter,IsTerminal,Read,Write};
 use std::path::Path;
 use std::str::FromStr;
-
-use clap::{App,Arg};
-use image::ImageBuffer;
-use rand::{Closed01,Open01,Rand,Rng,ThreadRng};
-use rand::distributions::{IndependentSample,Normal};
-
-use line_splat::imageprocessing::{gradient,angle_to_direction,non_maximum_suppression};
-use line_splat::painter::Painter;
-use line_splat::utility::{clamp_to_u8,min_f32,max_f32,difference_theta,mix,rgb_to_hsl,hsl_to_rgb};
-
-arg_enum! {
-    enum Style {
-        Random,
-        Steered,
-        Energy,
-        EdgeWeb
-    }
+use std::time::{Duration,Instant};
+
+use clap::{App,AppSettings,Arg,ArgMatches,SubCommand};
+
+use line_splat::Error;
+use line_splat::canvas::{Canvas,DownsampleFilter,LinearCanvas,Rgb16Canvas,Rgba16Canvas,RgbCanvas,RgbaCanvas,downsample};
+use line_splat::contour::LevelSpacing;
+use line_splat::formats::{OutputFormat,input_format,load_rgb,write_canvas};
+use line_splat::imageprocessing::{GradientOperator,GradientOptions,LuminanceModel};
+use line_splat::metadata::{Metadata,apply_orientation,read_metadata};
+use line_splat::render::{ColorMode,Detail,Focus,Progress,RenderStatus,Renderer,Source,Style};
+use line_splat::strokelog::{Replayer,StrokeLogHeader,StrokeLogReader,StrokeLogWriter};
+use line_splat::utility::parse_hex_color;
+
+//Path used for INPUT or OUTPUT to read from standard input or write to standard output.
+const STANDARD_STREAM: &str = "-";
+
+//Largest supersample factor and the most memory a supersampled render may use.
+const SUPERSAMPLE_MAX: usize = 16;
+const SUPERSAMPLE_MEMORY_MAX: u64 = 2 << 30;
+
+//How often the progress bar is redrawn on a terminal and how many characters wide it is. When
+//standard error isn't a terminal a line is written every PROGRESS_LOG_STEP percent instead.
+const PROGRESS_REDRAW_INTERVAL: Duration = Duration::from_millis(100);
+const PROGRESS_BAR_WIDTH: usize = 30;
+const PROGRESS_LOG_STEP: u32 = 10;
+
+//Reports drawing progress on standard error.
+struct ProgressReporter {
+    started: Instant,
+    last_redraw: Option<Instant>,
+    last_logged_percent: Option<u32>,
+    terminal: bool,
 }
 
-//Sample a 3x3 region at point and return the average color.
-fn color_at(buffer: &[u8],width: u32,height: u32,x: usize,y: usize) -> (u8,u8,u8) {
-    assert!(x < width as usize);
-    assert!(y < height as usize);
-
-    let min_x = if x == 0 { x } else { x - 1 };
-    let min_y = if y == 0 { y } else { y - 1 };
-    let max_x = if x == (width as usize - 1) { x } else { x + 1 };
-    let max_y = if y == (height as usize - 1) { y } else { y + 1 };
-
-    let mut total = 0.0;
-    let (mut red_sum,mut green_sum,mut blue_sum) = (0.0,0.0,0.0);
-    for y in min_y..(max_y + 1) {
-        for x in min_x..(max_x + 1) {
-            let index = (y * width as usize + x) * 3;
-            red_sum += buffer[index + 0] as f32;
-            green_sum += buffer[index + 1] as f32;
-            blue_sum += buffer[index + 2] as f32;
-            total += 1.0;
+impl ProgressReporter {
+    fn new() -> ProgressReporter {
+        ProgressReporter {
+            started: Instant::now(),
+            last_redraw: None,
+            last_logged_percent: None,
+            terminal: io::stderr().is_terminal(),
         }
     }
 
-    assert!(total != 0.0);
-    ((red_sum / total) as u8,(green_sum / total) as u8,(blue_sum / total) as u8)
-}
-
-fn shift_color(rng: &mut ThreadRng,red: u8,green: u8,blue: u8) -> (u8,u8,u8) {
-    const STD_DEV: f64 = 10.0;
-
-    let (red,green,blue) = (red as f64,green as f64,blue as f64);
-    let (red,green,blue) = (Normal::new(red,STD_DEV).ind_sample(rng),
-                            Normal::new(green,STD_DEV).ind_sample(rng),
-                            Normal::new(blue,STD_DEV).ind_sample(rng));
-
-    (clamp_to_u8(red),clamp_to_u8(green),clamp_to_u8(blue))
-}
-
-fn shift_lightness(rng: &mut ThreadRng,red: u8,green: u8,blue: u8) -> (u8,u8,u8) {
-    const STD_DEV: f64 = 0.03;
-
-    let (h,s,l) = rgb_to_hsl(red,green,blue);
-    let l = Normal::new(l as f64,STD_DEV).ind_sample(rng) as f32;
-    let l = max_f32(min_f32(l,1.0),0.0);
-
-    hsl_to_rgb(h,s,l)
-}
-
-fn random_line(rng: &mut ThreadRng,width: u32,height: u32) -> (usize,usize,usize,usize) {
-    const DISTANCE_MAX: f32 = 128.0;
-
-    let x1 = rng.gen::<usize>() % width as usize;
-    let y1 = rng.gen::<usize>() % height as usize;
-
-    loop {
-        let angle = Closed01::<f32>::rand(rng).0 * std::f32::consts::PI * 2.0;
-        let distance = Open01::<f32>::rand(rng).0 * DISTANCE_MAX;
-
-        let x2 = (x1 as f32 + distance * angle.cos()) as usize;
-        let y2 = (y1 as f32 + distance * angle.sin()) as usize;
+    fn update(&mut self,progress: Progress) {
+        let now = Instant::now();
+        let percent = (progress.fraction() * 100.0) as u32;
+        let done = progress.completed == progress.total;
+        if self.terminal {
+            if !done && self.last_redraw.is_some_and(|last_redraw| now - last_redraw < PROGRESS_REDRAW_INTERVAL) {
+                return;
+            }
+            self.last_redraw = Some(now);
 
-        if x2 < width as usize && y2 < height as usize {
-            return (x1,y1,x2,y2);
+            let filled = (progress.fraction() * PROGRESS_BAR_WIDTH as f32) as usize;
+            let bar = "#".repeat(filled) + &" ".repeat(PROGRESS_BAR_WIDTH - filled);
+            eprint!("\r{:>3}% [{}] {}/{} ETA {:<8}",percent,bar,progress.completed,progress.total,self.eta(progress,now));
+        }
+        else {
+            let step = percent / PROGRESS_LOG_STEP * PROGRESS_LOG_STEP;
+            if self.last_logged_percent.is_some_and(|last_percent| last_percent >= step) {
+                return;
+            }
+            self.last_logged_percent = Some(step);
+            eprintln!("{:>3}% {}/{} ETA {}",step,progress.completed,progress.total,self.eta(progress,now));
         }
     }
-}
-
-fn random_steered_line(rng: &mut ThreadRng,gradient: &[f32],width: u32,height: u32) -> (usize,usize,usize,usize) {
-    const DISTANCE_MAX: f32 = 64.0;
 
-    loop {
-        let x1 = rng.gen::<usize>() % width as usize;
-        let y1 = rng.gen::<usize>() % height as usize;
-
-        let index = (y1 * width as usize + x1) * 2;
-        let angle = gradient[index + 1] + std::f32::consts::PI / 2.0;
-        let distance = Open01::<f32>::rand(rng).0 * DISTANCE_MAX;
+    //Estimate the time left assuming the rest of the work goes as fast as what's been done so far.
+    fn eta(&self,progress: Progress,now: Instant) -> String {
+        if progress.completed == 0 {
+            return "--:--".to_string();
+        }
 
-        let x2 = (x1 as f32 + distance * angle.cos()) as usize;
-        let y2 = (y1 as f32 + distance * angle.sin()) as usize;
+        let elapsed = (now - self.started).as_secs_f64();
+        let remaining = (elapsed * (progress.total - progress.completed) as f64 / progress.completed as f64).round() as u64;
+        let (hours,minutes,seconds) = (remaining / 3600,remaining / 60 % 60,remaining % 60);
+        if hours > 0 {
+            format!("{}:{:02}:{:02}",hours,minutes,seconds)
+        }
+        else {
+            format!("{}:{:02}",minutes,seconds)
+        }
+    }
 
-        if x2 < width as usize && y2 < height as usize {
-            return (x1,y1,x2,y2);
+    //Move past the progress bar so later messages start on their own line.
+    fn finish(&self) {
+        if self.terminal && self.last_redraw.is_some() {
+            eprintln!();
         }
     }
 }
 
-fn random_energy_line(rng: &mut ThreadRng,gradient: &[f32],width: u32,height: u32) -> (usize,usize,usize,usize,usize,usize) {
-    const ENERGY_MIN: f32 = 10.0;
-    const ENERGY_MAX: f32 = 80.0;
-    const ENERGY_DIFF: f32 = ENERGY_MAX - ENERGY_MIN;
-
-    let xc = rng.gen::<usize>() % width as usize;
-    let yc = rng.gen::<usize>() % height as usize;
-    let angle = Closed01::<f32>::rand(rng).0 * std::f32::consts::PI * 2.0;
-
-    let mut fire_ray = |x,y,angle: f32| -> (usize,usize) {
-        let mut energy = Open01::<f32>::rand(rng).0 * ENERGY_DIFF + ENERGY_MIN;
-
-        let xe = (x as f32 + energy * angle.cos()) as i32;
-        let ye = (y as f32 + energy * angle.sin()) as i32;
-
-        let mut last_x = x;
-        let mut last_y = y;
-        Painter::line_foreach(width as usize,height as usize,xc as i32,yc as i32,xe,ye,|x,y| {
-            if energy >= 0.0 {
-                let gradient_index = (y * width as usize + x) * 2;
-                let mut dampening = 1.0 - difference_theta(angle,gradient[gradient_index + 1]) / (std::f32::consts::PI);
-                dampening *= Open01::<f32>::rand(rng).0;
-                energy -= gradient[gradient_index] * dampening;
-                last_x = x;
-                last_y = y;
-            }
-        });
-
-        (last_x,last_y)
-    };
-
-    let (x1,y1) = fire_ray(xc,yc,angle);
-    let (x2,y2) = fire_ray(xc,yc,angle + std::f32::consts::PI);
+//Add what was being done to the message of an I/O error.
+fn io_context(error: Error,context: &str) -> Error {
+    match error {
+        Error::Io(e) => Error::Io(io::Error::new(e.kind(),format!("{}: {}",context,e))),
+        error => error,
+    }
+}
 
-    (xc,yc,x1,y1,x2,y2)
+//Each kind of failure exits with its own code so scripts can tell them apart. Clap exits with 1 when
+//the command line can't be parsed.
+fn exit_code(error: &Error) -> i32 {
+    match *error {
+        Error::InvalidParameter(_) => 2,
+        Error::Io(_) => 3,
+        Error::Decode(_) => 4,
+        Error::UnsupportedFormat(_) => 5,
+        Error::EmptyImage => 6,
+    }
 }
 
-fn edge_web(rng: &mut ThreadRng,source_image_pixels: &[u8],source_image_width: u32,source_image_height: u32,gradient: &[f32],work_image_pixels: &mut [u8]) {
-    const DISTANCE_MIN: f32 = 4.0;
-    const DISTANCE_MAX: f32 = 50.0;
+//Settings shared by drawing and replaying that pick where the output image goes along with its
+//size, precision, and format.
+struct OutputOptions<'a> {
+    path: &'a Path,
+    to_stdout: bool,
+    format: OutputFormat,
+    bit_depth: u8,
+    quality: u8,
+    transparent: bool,
+    linear_blending: bool,
+    width: Option<u32>,
+    height: Option<u32>,
+    scale: Option<f32>,
+    supersample: usize,
+    supersample_filter: DownsampleFilter,
+}
 
-    let edge_pixels = non_maximum_suppression(gradient,source_image_width,source_image_height);
+impl<'a> OutputOptions<'a> {
+    fn args<'b,'c>() -> Vec<Arg<'b,'c>> {
+        vec![
+            Arg::with_name("bit-depth")
+                .short("d")
+                .long("bit-depth")
+                .default_value("8")
+                .help("Bits per channel of the output image. Must be 8 or 16. 16 is only supported for PNG, PNM, and TIFF.")
+                .required(false),
+            Arg::with_name("format")
+                .short("f")
+                .long("format")
+                .takes_value(true)
+                .help("Output image format. Must be png, jpg, bmp, gif, pnm, tga, or tiff. Picked from the output file extension by default.")
+                .required(false),
+            Arg::with_name("quality")
+                .short("q")
+                .long("quality")
+                .default_value("75")
+                .help("JPEG quality from 1 to 100")
+                .required(false),
+            Arg::with_name("transparent")
+                .short("t")
+                .long("transparent")
+                .help("Draw on a transparent background instead of black. Only supported for PNG, GIF, TGA, and TIFF."),
+            Arg::with_name("linear-blending")
+                .long("linear-blending")
+                .help("Blend translucent lines in linear light at floating point precision"),
+            Arg::with_name("width")
+                .long("width")
+                .takes_value(true)
+                .help("Width of the output image in pixels. Keeps the aspect ratio unless --height is also used.")
+                .required(false),
+            Arg::with_name("height")
+                .long("height")
+                .takes_value(true)
+                .help("Height of the output image in pixels. Keeps the aspect ratio unless --width is also used.")
+                .required(false),
+            Arg::with_name("scale")
+                .long("scale")
+                .takes_value(true)
+                .conflicts_with_all(&["width","height"])
+                .help("Size of the output image relative to the input image")
+                .required(false),
+            Arg::with_name("supersample")
+                .long("supersample")
+                .default_value("1")
+                .help("Draw on a canvas this many times larger in each direction and then shrink it to the output size for smoother lines. Must be from 1 to 16. Memory use grows with the square of this value and is limited to about 2 GiB.")
+                .required(false),
+            Arg::with_name("supersample-filter")
+                .long("supersample-filter")
+                .default_value("lanczos")
+                .help("Filter used to shrink a supersampled canvas. Must be lanczos or box.")
+                .required(false),
+            Arg::with_name("OUTPUT")
+                .help("Output image file or - to write to standard output. Writing to standard output requires --format.")
+                .required(true)
+                .index(2),
+        ]
+    }
 
-    let width = source_image_width as usize;
-    let height = source_image_height as usize;
+    fn from_matches(matches: &'a ArgMatches) -> Result<OutputOptions<'a>,Error> {
+        let path = Path::new(matches.value_of("OUTPUT").unwrap());
+        let to_stdout = path == Path::new(STANDARD_STREAM);
 
-    //Find all edge points.
-    let mut edge_points = vec![];
-    for y in 0..height {
-        for x in 0..width {
-            if edge_pixels[y * width + x] == 255 {
-                edge_points.push((x,y));
+        let bit_depth = match matches.value_of("bit-depth").unwrap() {
+            "8" => 8,
+            "16" => 16,
+            _ => {
+                return Err(Error::InvalidParameter("Bit depth must be 8 or 16.".to_string()));
             }
-        }
-    }
+        };
 
-    let lines_per_point = 1;//TODO: Maybe support variable lines again? 1000000 / edge_points.len();
+        let transparent = matches.is_present("transparent");
+        let linear_blending = matches.is_present("linear-blending");
 
-    //Draw a line between points that are between DISTANCE_MIN and DISTANCE_MAX of each other and
-    //have the same relative orientation. Only the closest lines_per_point lines are drawn.
-    //TODO: Use a quadtree to speed up this part.
-    let mut painter = Painter::new();
-    let mut other_points = vec![];
-    for y in 0..edge_points.len() {
-        let first_point = edge_points[y];
+        let supersample = match usize::from_str(matches.value_of("supersample").unwrap()) {
+            Ok(supersample) if (1..=SUPERSAMPLE_MAX).contains(&supersample) => supersample,
+            _ => {
+                return Err(Error::InvalidParameter(format!("Supersample must be an integer from 1 to {}.",SUPERSAMPLE_MAX)));
+            }
+        };
 
-        //Find potential matching points.
-        other_points.clear();
-        for x in y + 1..edge_points.len() {
-            let second_point = edge_points[x];
+        let supersample_filter = match matches.value_of("supersample-filter").unwrap().to_lowercase().as_str() {
+            "lanczos" => DownsampleFilter::Lanczos,
+            "box" => DownsampleFilter::Box,
+            _ => {
+                return Err(Error::InvalidParameter("Supersample filter must be lanczos or box.".to_string()));
+            }
+        };
 
-            let first_direction = angle_to_direction(gradient[first_point.1 * width + first_point.0 + 1]);
-            let second_direction = angle_to_direction(gradient[second_point.1 * width + second_point.0 + 1]);
+        let parse_dimension = |name| match matches.value_of(name).map(u32::from_str) {
+            Some(Ok(dimension)) if dimension > 0 => Ok(Some(dimension)),
+            Some(_) => Err(()),
+            None => Ok(None),
+        };
+        let (width,height) = match (parse_dimension("width"),parse_dimension("height")) {
+            (Ok(width),Ok(height)) => (width,height),
+            _ => {
+                return Err(Error::InvalidParameter("Width and height must be positive integers.".to_string()));
+            }
+        };
 
-            let diff_x = second_point.0 as f32 - first_point.0 as f32;
-            let diff_y = second_point.1 as f32 - first_point.1 as f32;
-            let distance = diff_x.hypot(diff_y);
+        let scale = match matches.value_of("scale").map(f32::from_str) {
+            Some(Ok(scale)) if scale > 0.0 && scale.is_finite() => Some(scale),
+            Some(_) => {
+                return Err(Error::InvalidParameter("Scale must be a positive number.".to_string()));
+            },
+            None => None,
+        };
 
-            if distance >= DISTANCE_MIN && distance <= DISTANCE_MAX && first_direction == second_direction {
-                other_points.push((second_point,diff_x,diff_y,distance));
+        let quality = match u8::from_str(matches.value_of("quality").unwrap()) {
+            Ok(quality) if (1..=100).contains(&quality) => quality,
+            _ => {
+                return Err(Error::InvalidParameter("Quality must be an integer from 1 to 100.".to_string()));
             }
-        }
+        };
 
-        //Sort by distance.
-        other_points.sort_by(|lhs,rhs| lhs.3.partial_cmp(&rhs.3).unwrap_or(std::cmp::Ordering::Equal));
-        if other_points.len() > lines_per_point {
-            other_points.drain(lines_per_point..);
+        //Make sure a supported output format was selected before wasting time generating an image.
+        let format = match matches.value_of("format") {
+            Some(format) => OutputFormat::from_name(format),
+            None if to_stdout => {
+                return Err(Error::InvalidParameter("--format is required when writing to standard output.".to_string()));
+            },
+            None => OutputFormat::from_path(path),
+        };
+        let format = match format {
+            Some(format) => format,
+            None => {
+                return Err(Error::UnsupportedFormat("Unsupported output file format. Must be png, jpg, bmp, gif, pnm, tga, or tiff. Use --format if the extension is missing or different.".to_string()));
+            }
+        };
+        if bit_depth == 16 && !format.supports_16_bit() {
+            return Err(Error::UnsupportedFormat("16-bit output is only supported for PNG, PNM, and TIFF".to_string()));
+        }
+        if transparent && !format.supports_alpha() {
+            return Err(Error::UnsupportedFormat("Transparent output is only supported for PNG, GIF, TGA, and TIFF".to_string()));
         }
 
-        for &(second_point,diff_x,diff_y,_) in &other_points {
-            //Randomly pick a pixel between (inclusive) the two points to sample and use as the
-            //color for the line.
-            let range = Closed01::<f32>::rand(rng).0;
-            let xc = (first_point.0 as f32 + diff_x * range).round() as i32;
-            let yc = (first_point.1 as f32 + diff_y * range).round() as i32;
-            let xc = std::cmp::min(std::cmp::max(0,xc),width as i32 - 1) as usize;
-            let yc = std::cmp::min(std::cmp::max(0,yc),height as i32 - 1) as usize;
+        Ok(OutputOptions {
+            path,
+            to_stdout,
+            format,
+            bit_depth,
+            quality,
+            transparent,
+            linear_blending,
+            width,
+            height,
+            scale,
+            supersample,
+            supersample_filter,
+        })
+    }
 
-            let (red,green,blue) = color_at(&source_image_pixels,source_image_width,source_image_height,xc,yc);
-            painter.set_pen(red,green,blue);
+    //Work out the size of the canvas to draw on for an image that's naturally width by height. A
+    //missing output width or height is picked to keep the aspect ratio. The result includes
+    //supersampling.
+    fn canvas_size(&self,width: u32,height: u32) -> Result<(usize,usize),Error> {
+        let scaled = |length: u32,scale: f64| std::cmp::max(1,(length as f64 * scale).round() as u64);
+        let (canvas_width,canvas_height) = match (self.scale,self.width,self.height) {
+            (Some(scale),_,_) => (scaled(width,scale as f64),scaled(height,scale as f64)),
+            (None,Some(output_width),Some(output_height)) => (output_width as u64,output_height as u64),
+            (None,Some(output_width),None) => (output_width as u64,scaled(height,output_width as f64 / width as f64)),
+            (None,None,Some(output_height)) => (scaled(width,output_height as f64 / height as f64),output_height as u64),
+            (None,None,None) => (width as u64,height as u64),
+        };
 
-            //Draw the line.
-            painter.line(work_image_pixels,source_image_width as usize,source_image_height as usize,first_point.0 as i32,first_point.1 as i32,second_point.0 as i32,second_point.1 as i32);
+        //Supersampling draws everything at a larger size so the canvas and the buffer used to
+        //shrink it need to fit in the memory budget.
+        let supersample = self.supersample as u64;
+        let (canvas_width,canvas_height) = (canvas_width * supersample,canvas_height * supersample);
+        if canvas_width > u32::MAX as u64 || canvas_height > u32::MAX as u64 {
+            return Err(Error::InvalidParameter("Output image is too large.".to_string()));
         }
+        if supersample > 1 {
+            let bytes_per_pixel = if self.linear_blending {
+                16
+            }
+            else if self.transparent && self.bit_depth == 16 {
+                8
+            }
+            else if self.bit_depth == 16 {
+                6
+            }
+            else if self.transparent {
+                4
+            }
+            else {
+                3
+            };
+            let canvas_bytes = canvas_width.saturating_mul(canvas_height).saturating_mul(bytes_per_pixel);
+            let downsample_bytes = (canvas_width / supersample).saturating_mul(canvas_height).saturating_mul(16);
+            if canvas_bytes.saturating_add(downsample_bytes) > SUPERSAMPLE_MEMORY_MAX {
+                return Err(Error::InvalidParameter(format!("Supersampled canvas would need {} MiB which is more than the {} MiB limit. Use a smaller supersample value or output size.",canvas_bytes.saturating_add(downsample_bytes) >> 20,SUPERSAMPLE_MEMORY_MAX >> 20)));
+            }
+        }
+
+        Ok((canvas_width as usize,canvas_height as usize))
     }
 
+    //Create a canvas to draw on. Linear blending and 16-bit output need the extra precision to
+    //keep translucent lines from banding. Transparent output starts with an empty canvas so only
+    //the lines end up visible.
+    fn create_canvas(&self,width: usize,height: usize) -> Result<Box<dyn Canvas>,Error> {
+        Ok(if self.linear_blending {
+            Box::new(LinearCanvas::new(width,height)?)
+        }
+        else if self.transparent && self.bit_depth == 16 {
+            Box::new(Rgba16Canvas::new(width,height)?)
+        }
+        else if self.transparent {
+            Box::new(RgbaCanvas::new(width,height)?)
+        }
+        else if self.bit_depth == 16 {
+            Box::new(Rgb16Canvas::new(width,height)?)
+        }
+        else {
+            Box::new(RgbCanvas::new(width,height)?)
+        })
+    }
 
-    //Debug code for showing the detected edge pixels.
-    /*for y in 0..source_image_height {
-        for x in 0..source_image_width {
-            let index = (y * source_image_width + x) as usize;
+    //Shrink a supersampled canvas down to the output size and write it out.
+    fn save(&self,canvas: Box<dyn Canvas>,metadata: &Metadata) -> Result<(),Error> {
+        let canvas = if self.supersample > 1 {
+            Box::new(downsample(canvas.as_ref(),self.supersample,self.supersample_filter)?)
+        }
+        else {
+            canvas
+        };
 
-            if edge_pixels[index] == 255 {
-                let index = index * 3;
-                work_image_pixels[index + 0] = 0;
-                work_image_pixels[index + 1] = 255;
-                work_image_pixels[index + 2] = 0;
-            }
+        let write = |writer: &mut dyn Write| -> Result<(),Error> {
+            let mut writer = BufWriter::new(writer);
+            write_canvas(canvas.as_ref(),&mut writer,self.format,self.bit_depth,self.transparent,self.quality,metadata)?;
+            writer.flush()?;
+            Ok(())
+        };
+        let result = if self.to_stdout {
+            write(&mut io::stdout().lock())
         }
-    }*/
+        else {
+            File::create(self.path).map_err(Error::from).and_then(|mut file| write(&mut file))
+        };
+        result.map_err(|e| io_context(e,"Could not write output file"))
+    }
 }
 
-fn main() {
+fn run() -> Result<(),Error> {
+    let started = Instant::now();
     let matches = App::new("line-splat")
         .version("0.1")
-        .about("Stylize images by drawing random lines. Supports JPEG and PNG images.")
+        .about("Stylize images by drawing random lines. Supports JPEG, PNG, TIFF, BMP, GIF, PNM, and TGA images.")
         .author("James Bendig")
+        .setting(AppSettings::SubcommandsNegateReqs)
+        .setting(AppSettings::DisableHelpSubcommand)
         .arg(Arg::with_name("line-count")
              .short("l")
              .long("line-count")
@@ -248,24 +401,194 @@ fn main() {
              .short("s")
              .long("style")
              .default_value("random")
-             .help("Style to use. Must be random, steered, energy, or edgeweb.")
+             .help("Style to use. Must be random, steered, energy, edgeweb, stipple, mesh, contour, spiral, squiggle, radial, hough, outline, or regions.")
+             .required(false))
+        .arg(Arg::with_name("color-mode")
+             .short("c")
+             .long("color-mode")
+             .default_value("default")
+             .help("How each line is colored. Must be default, endpointmix, center, randompoint, lineaverage, rgbjitter, lightnessjitter, huejitter, ink, gradient, sampled, or level. The default depends on the style.")
+             .required(false))
+        .arg(Arg::with_name("ink-color")
+             .long("ink-color")
+             .default_value("#000000")
+             .help("Color used by the ink color mode as a hex RGB value such as #1a2b3c")
+             .required(false))
+        .arg(Arg::with_name("pen-width")
+             .short("w")
+             .long("pen-width")
+             .default_value("1")
+             .help("Width of the lines in pixels")
+             .required(false))
+        .arg(Arg::with_name("opacity")
+             .short("o")
+             .long("opacity")
+             .default_value("1")
+             .help("How opaque the lines are from 0 to 1")
+             .required(false))
+        .arg(Arg::with_name("curved")
+             .long("curved")
+             .help("Bend lines to follow the image in the steered and energy styles"))
+        .arg(Arg::with_name("detail")
+             .long("detail")
+             .default_value("full")
+             .help("How fine the edges are that styles follow. Must be full or a size in pixels. A size finds edges as if the input image were shrunk so its longer side is that many pixels, which gives large and small versions of the same image the same look.")
+             .required(false))
+        .arg(Arg::with_name("gradient-operator")
+             .long("gradient-operator")
+             .default_value("sobel")
+             .help("How edges are measured. Must be sobel, scharr, prewitt, or central. Scharr is more accurate on diagonal edges while prewitt and central pick up finer details and more noise.")
+             .required(false))
+        .arg(Arg::with_name("luminance")
+             .long("luminance")
+             .default_value("rec601")
+             .help("How brightness is worked out when measuring edges. Must be rec601, rec709, or linear. Linear measures physical brightness, which favors edges in light areas.")
+             .required(false))
+        .arg(Arg::with_name("color-gradient")
+             .long("color-gradient")
+             .help("Measure edges in each color channel instead of in brightness so edges between different colors that are equally bright are found too"))
+        .arg(Arg::with_name("dot-count")
+             .long("dot-count")
+             .default_value("20000")
+             .help("Number of dots placed by the stipple style")
+             .required(false))
+        .arg(Arg::with_name("tsp")
+             .long("tsp")
+             .help("Join the dots of the stipple style into a single continuous line"))
+        .arg(Arg::with_name("mesh-points")
+             .long("mesh-points")
+             .default_value("4000")
+             .help("Number of points the mesh style connects into triangles")
+             .required(false))
+        .arg(Arg::with_name("edge-weight")
+             .long("edge-weight")
+             .default_value("0.5")
+             .help("Fraction of the mesh style's points from 0 to 1 that are placed on edges in the image. The rest are spread randomly.")
+             .required(false))
+        .arg(Arg::with_name("low-poly")
+             .long("low-poly")
+             .help("Fill the triangles of the mesh style instead of drawing their edges"))
+        .arg(Arg::with_name("contour-levels")
+             .long("contour-levels")
+             .default_value("16")
+             .help("Number of brightness levels the contour style traces")
+             .required(false))
+        .arg(Arg::with_name("level-spacing")
+             .long("level-spacing")
+             .default_value("linear")
+             .help("How the contour style spreads out its levels. Must be linear or quantile. Quantile puts about the same amount of the image between each pair of levels.")
+             .required(false))
+        .arg(Arg::with_name("spacing")
+             .long("spacing")
+             .default_value("6")
+             .help("Distance in pixels between the turns of the spiral style and the lines of the squiggle style. Must be at least 1.")
+             .required(false))
+        .arg(Arg::with_name("amplitude")
+             .long("amplitude")
+             .default_value("3")
+             .help("How far in pixels the spiral and squiggle styles wave to either side in the darkest parts of the image")
+             .required(false))
+        .arg(Arg::with_name("squiggle-angle")
+             .long("squiggle-angle")
+             .default_value("0")
+             .help("Angle of the squiggle style's lines in degrees clockwise from horizontal")
+             .required(false))
+        .arg(Arg::with_name("focus")
+             .long("focus")
+             .default_value("center")
+             .help("Point the lines of the radial style run toward. Must be center, salient, or a position in the input image in pixels written as x,y. Salient picks the busiest part of the image.")
+             .required(false))
+        .arg(Arg::with_name("radial-jitter")
+             .long("radial-jitter")
+             .default_value("3")
+             .help("How far the lines of the radial style randomly turn away from the focus in degrees. This is the standard deviation of the turn.")
+             .required(false))
+        .arg(Arg::with_name("hough-lines")
+             .long("hough-lines")
+             .default_value("200")
+             .help("Most straight lines the hough style draws")
+             .required(false))
+        .arg(Arg::with_name("extend-lines")
+             .long("extend-lines")
+             .help("Extend the lines of the hough style across the whole image"))
+        .arg(Arg::with_name("outline-length")
+             .long("outline-length")
+             .default_value("20")
+             .help("Shortest edge the outline style draws in pixels of the input image")
+             .required(false))
+        .arg(Arg::with_name("regions")
+             .long("regions")
+             .default_value("400")
+             .help("About how many regions of similar color the image is split into for the regions style and --clip-to-regions")
+             .required(false))
+        .arg(Arg::with_name("compactness")
+             .long("compactness")
+             .default_value("10")
+             .help("How much regions favor compact shapes over following the colors of the image. Higher values make regions closer to squares.")
+             .required(false))
+        .arg(Arg::with_name("clip-to-regions")
+             .long("clip-to-regions")
+             .help("Stop the lines of the random, steered, and energy styles where they leave the region they started in so colors don't bleed across the edges of objects"))
+        .arg(Arg::with_name("no-auto-orient")
+             .long("no-auto-orient")
+             .help("Don't rotate the input image to match its EXIF orientation"))
+        .arg(Arg::with_name("keep-metadata")
+             .long("keep-metadata")
+             .help("Copy the color profile, author, and copyright from the input image. Only supported for PNG, JPEG, and TIFF."))
+        .arg(Arg::with_name("quiet")
+             .long("quiet")
+             .help("Don't show drawing progress on standard error"))
+        .arg(Arg::with_name("time-limit")
+             .long("time-limit")
+             .takes_value(true)
+             .help("Stop drawing after this many seconds and save what was drawn so far. The time spent loading the input image counts toward the limit.")
+             .required(false))
+        .arg(Arg::with_name("stroke-log")
+             .long("stroke-log")
+             .takes_value(true)
+             .help("Also write every stroke that's drawn to this file so it can be drawn again with the replay command")
              .required(false))
         .arg(Arg::with_name("INPUT")
-             .help("Input image file")
+             .help("Input image file or - to read from standard input")
              .required(true)
              .index(1))
-        .arg(Arg::with_name("OUTPUT")
-             .help("Output image file")
-             .required(true)
-             .index(2))
+        .args(&OutputOptions::args())
+        .subcommand(SubCommand::with_name("replay")
+                    .about("Draw the strokes from a stroke log again at any size, pen width, or color")
+                    .arg(Arg::with_name("pen-width")
+                         .short("w")
+                         .long("pen-width")
+                         .takes_value(true)
+                         .help("Width of the lines in pixels. Scaled from the logged width to match the output size by default.")
+                         .required(false))
+                    .arg(Arg::with_name("opacity")
+                         .short("o")
+                         .long("opacity")
+                         .takes_value(true)
+                         .help("How opaque the lines are from 0 to 1. The logged opacity is used by default.")
+                         .required(false))
+                    .arg(Arg::with_name("ink-color")
+                         .long("ink-color")
+                         .takes_value(true)
+                         .help("Draw every stroke in this hex RGB color, such as #1a2b3c, instead of the logged colors")
+                         .required(false))
+                    .arg(Arg::with_name("LOG")
+                         .help("Stroke log file written with --stroke-log or - to read from standard input")
+                         .required(true)
+                         .index(1))
+                    .args(&OutputOptions::args()))
         .get_matches();
 
+    if let Some(matches) = matches.subcommand_matches("replay") {
+        return replay(matches);
+    }
+
     //Extract and validate parameters from command line.
     let input_path = Path::new(matches.value_of("INPUT").unwrap());
-    let output_path = Path::new(matches.value_of("OUTPUT").unwrap());
-    if input_path == output_path {
-        eprintln!("Input and output file paths cannot be the same");
-        return;
+    let read_stdin = input_path == Path::new(STANDARD_STREAM);
+    let output = OutputOptions::from_matches(&matches)?;
+    if input_path == output.path && !read_stdin {
+        return Err(Error::InvalidParameter("Input and output file paths cannot be the same".to_string()));
     }
 
     let line_count = matches.value_of("line-count").unwrap();
@@ -273,98 +596,338 @@ fn main() {
         line_count
     }
     else {
-        eprintln!("Line count must be a positive integer.");
-        return;
+        return Err(Error::InvalidParameter("Line count must be a positive integer.".to_string()));
+    };
+
+    let style = match Style::from_name(matches.value_of("style").unwrap()) {
+        Some(style) => style,
+        None => {
+            return Err(Error::InvalidParameter("Style must be random, steered, energy, edgeweb, stipple, mesh, contour, spiral, squiggle, radial, hough, outline, or regions.".to_string()));
+        }
     };
 
-    let style = match value_t!(matches,"style",Style) {
-        Ok(style) => style,
-        Err(e) => {
-            eprintln!("{}       See --help",e);
-            return;
+    let color_mode = match ColorMode::from_name(matches.value_of("color-mode").unwrap()) {
+        Some(color_mode) => color_mode,
+        None => {
+            return Err(Error::InvalidParameter("Color mode must be default, endpointmix, center, randompoint, lineaverage, rgbjitter, lightnessjitter, huejitter, ink, gradient, sampled, or level.".to_string()));
         }
     };
 
-    //Make sure a supported file extension was selected before wasting time generating an image.
-    match output_path.extension() {
-        Some(extension) => {
-            match extension.to_string_lossy().to_lowercase().as_str() {
-                "jpg" | "jpeg" => (),
-                "png" => (),
-                _ => {
-                    eprintln!("Unsupported output file format. Must have a .png or .jpg extension");
-                    return;
-                }
-            }
-        },
+    let pen_width = parse_pen_width(matches.value_of("pen-width").unwrap())?;
+    let opacity = parse_opacity(matches.value_of("opacity").unwrap())?;
+    let ink = parse_ink(matches.value_of("ink-color").unwrap())?;
+
+    let dot_count = match usize::from_str(matches.value_of("dot-count").unwrap()) {
+        Ok(dot_count) => dot_count,
+        Err(_) => {
+            return Err(Error::InvalidParameter("Dot count must be a positive integer.".to_string()));
+        }
+    };
+
+    let mesh_points = match usize::from_str(matches.value_of("mesh-points").unwrap()) {
+        Ok(mesh_points) => mesh_points,
+        Err(_) => {
+            return Err(Error::InvalidParameter("Mesh points must be a positive integer.".to_string()));
+        }
+    };
+
+    let edge_weight = match f32::from_str(matches.value_of("edge-weight").unwrap()) {
+        Ok(edge_weight) if (0.0..=1.0).contains(&edge_weight) => edge_weight,
+        _ => {
+            return Err(Error::InvalidParameter("Edge weight must be a number from 0 to 1.".to_string()));
+        }
+    };
+
+    let contour_levels = match usize::from_str(matches.value_of("contour-levels").unwrap()) {
+        Ok(contour_levels) => contour_levels,
+        Err(_) => {
+            return Err(Error::InvalidParameter("Contour levels must be a positive integer.".to_string()));
+        }
+    };
+
+    let level_spacing = match LevelSpacing::from_name(matches.value_of("level-spacing").unwrap()) {
+        Some(level_spacing) => level_spacing,
         None => {
-            eprintln!("Output file format must have a .png or .jpg extension");
-            return;
+            return Err(Error::InvalidParameter("Level spacing must be linear or quantile.".to_string()));
         }
     };
 
-    //Open source file.
-    let source_image = match image::open(&input_path) {
-        Ok(image) => image.to_rgb(),
-        Err(e) => {
-            eprintln!("Could not open input file: {}",e);
-            return;
+    let spacing = match f32::from_str(matches.value_of("spacing").unwrap()) {
+        Ok(spacing) if spacing >= 1.0 && spacing.is_finite() => spacing,
+        _ => {
+            return Err(Error::InvalidParameter("Spacing must be a number of pixels that's at least 1.".to_string()));
         }
     };
-    let (source_image_width,source_image_height) = source_image.dimensions();
-    let mut source_image_pixels = source_image.into_raw();
 
-    //Generate gradient for source image. It's used by the energy style to determine how far to
-    //shoot the rays. It's also used by the edgeweb style to detect edges in the image.
-    let source_image_gradient = gradient(&mut source_image_pixels,source_image_width,source_image_height);
+    let amplitude = match f32::from_str(matches.value_of("amplitude").unwrap()) {
+        Ok(amplitude) if amplitude >= 0.0 && amplitude.is_finite() => amplitude,
+        _ => {
+            return Err(Error::InvalidParameter("Amplitude must be a number of pixels that isn't negative.".to_string()));
+        }
+    };
 
-    //Create a canvas to write the generated image to.
-    let mut work_image_pixels = Vec::with_capacity(source_image_pixels.capacity());
-    work_image_pixels.resize(source_image_pixels.len(),0);
+    let squiggle_angle = match f32::from_str(matches.value_of("squiggle-angle").unwrap()) {
+        Ok(squiggle_angle) if squiggle_angle.is_finite() => squiggle_angle,
+        _ => {
+            return Err(Error::InvalidParameter("Squiggle angle must be a number of degrees.".to_string()));
+        }
+    };
 
-    //Generate image using the selected style.
-    let mut rng = rand::thread_rng();
-    let mut painter = Painter::new();
-    for _ in 0..line_count {
-        let (x1,y1,x2,y2) = match style {
-            Style::Random => {
-                let (x1,y1,x2,y2) = random_line(&mut rng,source_image_width,source_image_height);
+    let detail = match Detail::from_name(matches.value_of("detail").unwrap()) {
+        Some(detail) => detail,
+        None => {
+            return Err(Error::InvalidParameter("Detail must be full or a size in pixels that's at least 1.".to_string()));
+        }
+    };
 
-                let (red1,green1,blue1) = color_at(&source_image_pixels,source_image_width,source_image_height,x1,y1);
-                let (red2,green2,blue2) = color_at(&source_image_pixels,source_image_width,source_image_height,x2,y2);
-                painter.set_pen(mix(red1,red2),mix(green1,green2),mix(blue1,blue2));
+    let gradient_operator = match GradientOperator::from_name(matches.value_of("gradient-operator").unwrap()) {
+        Some(gradient_operator) => gradient_operator,
+        None => {
+            return Err(Error::InvalidParameter("Gradient operator must be sobel, scharr, prewitt, or central.".to_string()));
+        }
+    };
 
-                (x1,y1,x2,y2)
-            },
-            Style::Steered => {
-                let (x1,y1,x2,y2) = random_steered_line(&mut rng,source_image_gradient.as_slice(),source_image_width,source_image_height);
+    let luminance = match LuminanceModel::from_name(matches.value_of("luminance").unwrap()) {
+        Some(luminance) => luminance,
+        None => {
+            return Err(Error::InvalidParameter("Luminance must be rec601, rec709, or linear.".to_string()));
+        }
+    };
 
-                let (red1,green1,blue1) = color_at(&source_image_pixels,source_image_width,source_image_height,x1,y1);
-                let (red2,green2,blue2) = color_at(&source_image_pixels,source_image_width,source_image_height,x2,y2);
-                painter.set_pen(mix(red1,red2),mix(green1,green2),mix(blue1,blue2));
+    let focus = match Focus::from_name(matches.value_of("focus").unwrap()) {
+        Some(focus) => focus,
+        None => {
+            return Err(Error::InvalidParameter("Focus must be center, salient, or a position written as x,y.".to_string()));
+        }
+    };
 
-                (x1,y1,x2,y2)
-            },
-            Style::Energy => {
-                let (xc,yc,x1,y1,x2,y2) = random_energy_line(&mut rng,source_image_gradient.as_slice(),source_image_width,source_image_height);
-                let (red,green,blue) = color_at(&source_image_pixels,source_image_width,source_image_height,xc,yc);
-                let (red,green,blue) = shift_lightness(&mut rng,red,green,blue);
-                painter.set_pen(red,green,blue);
+    let radial_jitter = match f32::from_str(matches.value_of("radial-jitter").unwrap()) {
+        Ok(radial_jitter) if radial_jitter >= 0.0 && radial_jitter.is_finite() => radial_jitter,
+        _ => {
+            return Err(Error::InvalidParameter("Radial jitter must be a number of degrees that isn't negative.".to_string()));
+        }
+    };
 
-                (x1,y1,x2,y2)
-            },
-            Style::EdgeWeb => {
-                edge_web(&mut rng,source_image_pixels.as_slice(),source_image_width,source_image_height,source_image_gradient.as_slice(),work_image_pixels.as_mut_slice());
-                break;
-            },
-        };
-        painter.line(&mut work_image_pixels,source_image_width as usize,source_image_height as usize,x1 as i32,y1 as i32,x2 as i32,y2 as i32);
+    let hough_lines = match usize::from_str(matches.value_of("hough-lines").unwrap()) {
+        Ok(hough_lines) => hough_lines,
+        Err(_) => {
+            return Err(Error::InvalidParameter("Hough lines must be a positive integer.".to_string()));
+        }
+    };
+
+    let outline_length = match usize::from_str(matches.value_of("outline-length").unwrap()) {
+        Ok(outline_length) => outline_length,
+        Err(_) => {
+            return Err(Error::InvalidParameter("Outline length must be a positive integer.".to_string()));
+        }
+    };
+
+    let region_count = match usize::from_str(matches.value_of("regions").unwrap()) {
+        Ok(region_count) if region_count > 0 => region_count,
+        _ => {
+            return Err(Error::InvalidParameter("Regions must be a positive integer.".to_string()));
+        }
+    };
+
+    let compactness = match f32::from_str(matches.value_of("compactness").unwrap()) {
+        Ok(compactness) if compactness > 0.0 && compactness.is_finite() => compactness,
+        _ => {
+            return Err(Error::InvalidParameter("Compactness must be a positive number.".to_string()));
+        }
+    };
+
+    let curved = matches.is_present("curved");
+    let tsp = matches.is_present("tsp");
+    let low_poly = matches.is_present("low-poly");
+    let extend_lines = matches.is_present("extend-lines");
+    let clip_to_regions = matches.is_present("clip-to-regions");
+    let color_gradient = matches.is_present("color-gradient");
+    let auto_orient = !matches.is_present("no-auto-orient");
+    let keep_metadata = matches.is_present("keep-metadata");
+    let quiet = matches.is_present("quiet");
+
+    let time_limit = match matches.value_of("time-limit").map(f64::from_str) {
+        Some(Ok(time_limit)) if time_limit >= 0.0 && time_limit.is_finite() => Some(Duration::from_secs_f64(time_limit)),
+        Some(_) => {
+            return Err(Error::InvalidParameter("Time limit must be a number of seconds that isn't negative.".to_string()));
+        },
+        None => None,
+    };
+
+    let stroke_log_path = matches.value_of("stroke-log").map(Path::new);
+    if stroke_log_path.is_some_and(|path| path == input_path || path == output.path) {
+        return Err(Error::InvalidParameter("Stroke log path cannot be the same as the input or output file paths".to_string()));
+    }
+
+    if keep_metadata && !output.format.supports_metadata() {
+        return Err(Error::UnsupportedFormat("Keeping metadata is only supported for PNG, JPEG, and TIFF".to_string()));
+    }
+
+    //Open source file and turn it upright. Phone cameras usually store pixels in the sensor's
+    //orientation and leave the rotation to EXIF.
+    //Standard input has no file extension so the format is always picked from the contents.
+    let source_bytes = read_input(input_path).map_err(|e| io_context(e,"Could not open input file"))?;
+    let source_image = load_rgb(&source_bytes,input_format(input_path,&source_bytes)?)?;
+    let source_metadata = read_metadata(&source_bytes);
+    drop(source_bytes);
+    let source_image = match source_metadata.orientation {
+        Some(orientation) if auto_orient => apply_orientation(source_image,orientation),
+        _ => source_image,
+    };
+
+    let (source_image_width,source_image_height) = source_image.dimensions();
+    let (canvas_width,canvas_height) = output.canvas_size(source_image_width,source_image_height)?;
+    let mut source = Source::new(source_image,canvas_width as u32,canvas_height as u32)?;
+    source.set_gradient_options(GradientOptions {
+        operator: gradient_operator,
+        luminance,
+        color: color_gradient,
+    })?;
+    source.set_detail(detail)?;
+    let mut canvas = output.create_canvas(canvas_width,canvas_height)?;
+
+    //Strokes are logged at the output size rather than the supersampled size so replaying the log
+    //doesn't depend on how it was supersampled.
+    let supersample = output.supersample as f32;
+    let mut stroke_log = match stroke_log_path {
+        Some(path) => {
+            let header = StrokeLogHeader {
+                width: (canvas_width / output.supersample) as u32,
+                height: (canvas_height / output.supersample) as u32,
+                pen_width,
+                pen_opacity: opacity,
+            };
+            let file = File::create(path).map_err(|e| io_context(e.into(),"Could not create stroke log file"))?;
+            Some(StrokeLogWriter::new(BufWriter::new(file),&header)?)
+        },
+        None => None,
+    };
+
+    //Generate image using the selected style. Drawing stops early if the time limit runs out but
+    //the image is still saved.
+    let mut renderer = Renderer::new(style);
+    renderer.set_color_mode(color_mode);
+    renderer.set_ink(ink.0,ink.1,ink.2);
+    renderer.set_line_count(line_count);
+    renderer.set_pen_width(pen_width * supersample);
+    renderer.set_pen_opacity(opacity);
+    renderer.set_curved(curved);
+    renderer.set_dot_count(dot_count);
+    renderer.set_tsp(tsp);
+    renderer.set_mesh_points(mesh_points);
+    renderer.set_edge_weight(edge_weight);
+    renderer.set_low_poly(low_poly);
+    renderer.set_contour_levels(contour_levels);
+    renderer.set_level_spacing(level_spacing);
+    renderer.set_spacing(spacing * supersample);
+    renderer.set_amplitude(amplitude * supersample);
+    renderer.set_squiggle_angle(squiggle_angle.to_radians());
+    renderer.set_focus(focus);
+    renderer.set_radial_jitter(radial_jitter.to_radians());
+    renderer.set_hough_lines(hough_lines);
+    renderer.set_extend_lines(extend_lines);
+    renderer.set_outline_length(outline_length);
+    renderer.set_region_count(region_count);
+    renderer.set_compactness(compactness);
+    renderer.set_clip_to_regions(clip_to_regions);
+    let mut reporter = ProgressReporter::new();
+    let status = renderer.render_logged(&source,canvas.as_mut(),|progress| {
+        if !quiet {
+            reporter.update(progress);
+        }
+        time_limit.is_none_or(|time_limit| started.elapsed() < time_limit)
+    },|record| {
+        match stroke_log {
+            Some(ref mut stroke_log) => stroke_log.write(&record.scaled(1.0 / supersample,1.0 / supersample)),
+            None => Ok(()),
+        }
+    }).map_err(|e| io_context(e,"Could not write stroke log file"))?;
+    reporter.finish();
+    if let RenderStatus::Cancelled(progress) = status {
+        if !quiet {
+            eprintln!("Time limit reached after {:.0}% of the drawing.",progress.fraction() * 100.0);
+        }
+    }
+    if let Some(stroke_log) = stroke_log {
+        stroke_log.finish().map_err(|e| io_context(e,"Could not write stroke log file"))?;
     }
 
     //Save the results.
-    let output_image = ImageBuffer::<image::Rgb<u8>,std::vec::Vec<u8>>::from_raw(source_image_width,source_image_height,work_image_pixels).unwrap();
-    if let Err(e) = output_image.save(&output_path) {
-        eprintln!("Could not write output to file: {}",e);
-        return;
+    let output_metadata = if keep_metadata {
+        source_metadata
+    }
+    else {
+        Metadata::default()
+    };
+    output.save(canvas,&output_metadata)
+}
+
+//Draw a stroke log onto a new canvas and save it.
+fn replay(matches: &ArgMatches) -> Result<(),Error> {
+    let log_path = Path::new(matches.value_of("LOG").unwrap());
+    let output = OutputOptions::from_matches(matches)?;
+    if log_path == output.path && log_path != Path::new(STANDARD_STREAM) {
+        return Err(Error::InvalidParameter("Stroke log and output file paths cannot be the same".to_string()));
+    }
+
+    let mut replayer = Replayer::new();
+    if let Some(pen_width) = matches.value_of("pen-width") {
+        replayer.set_pen_width(parse_pen_width(pen_width)? * output.supersample as f32);
+    }
+    if let Some(opacity) = matches.value_of("opacity") {
+        replayer.set_pen_opacity(parse_opacity(opacity)?);
+    }
+    if let Some(ink) = matches.value_of("ink-color") {
+        let (red,green,blue) = parse_ink(ink)?;
+        replayer.set_ink(red,green,blue);
+    }
+
+    let log_bytes = read_input(log_path).map_err(|e| io_context(e,"Could not open stroke log file"))?;
+    let log = StrokeLogReader::new(log_bytes.as_slice())?;
+    let header = *log.header();
+    let (canvas_width,canvas_height) = output.canvas_size(header.width,header.height)?;
+    let mut canvas = output.create_canvas(canvas_width,canvas_height)?;
+    replayer.replay(&header,log,canvas.as_mut())?;
+
+    output.save(canvas,&Metadata::default())
+}
+
+//Read all of a file or standard input.
+fn read_input(path: &Path) -> Result<Vec<u8>,Error> {
+    if path == Path::new(STANDARD_STREAM) {
+        let mut bytes = vec![];
+        io::stdin().read_to_end(&mut bytes)?;
+        Ok(bytes)
+    }
+    else {
+        Ok(fs::read(path)?)
+    }
+}
+
+fn parse_pen_width(pen_width: &str) -> Result<f32,Error> {
+    match f32::from_str(pen_width) {
+        Ok(pen_width) if pen_width > 0.0 && pen_width.is_finite() => Ok(pen_width),
+        _ => Err(Error::InvalidParameter("Pen width must be a positive number.".to_string())),
+    }
+}
+
+fn parse_opacity(opacity: &str) -> Result<f32,Error> {
+    match f32::from_str(opacity) {
+        Ok(opacity) if opacity > 0.0 && opacity <= 1.0 => Ok(opacity),
+        _ => Err(Error::InvalidParameter("Opacity must be a number greater than 0 and at most 1.".to_string())),
+    }
+}
+
+fn parse_ink(ink: &str) -> Result<(u8,u8,u8),Error> {
+    match parse_hex_color(ink) {
+        Some(ink) => Ok(ink),
+        None => Err(Error::InvalidParameter("Ink color must be a hex RGB value such as #1a2b3c.".to_string())),
+    }
+}
+
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("{}",e);
+        std::process::exit(exit_code(&e));
     }
 }
@@ -0,0 +1,373 @@
+extern crate std;
+
+use image::{imageops,RgbImage};
+use inflate;
+
+use formats::OutputFormat;
+
+//TIFF tags that are read from EXIF blocks and TIFF files.
+const TAG_ORIENTATION: u16 = 274;
+const TAG_ARTIST: u16 = 315;
+const TAG_COPYRIGHT: u16 = 33432;
+const TAG_ICC_PROFILE: u16 = 34675;
+
+//The largest payload a JPEG marker segment can hold after its two byte length.
+const JPEG_SEGMENT_MAX: usize = 65533;
+const JPEG_EXIF_HEADER: &[u8] = b"Exif\0\0";
+const JPEG_ICC_HEADER: &[u8] = b"ICC_PROFILE\0";
+
+const PNG_SIGNATURE: &[u8] = &[0x89,b'P',b'N',b'G',0x0D,0x0A,0x1A,0x0A];
+
+//The parts of an image's metadata that line-splat understands. Everything else is dropped.
+#[derive(Clone,Debug,Default,PartialEq)]
+pub struct Metadata {
+    //EXIF orientation from 1 to 8 where 1 means the pixels are already upright.
+    pub orientation: Option<u16>,
+    pub artist: Option<String>,
+    pub copyright: Option<String>,
+    pub icc_profile: Option<Vec<u8>>,
+}
+
+//Pull orientation, author, copyright, and color profile out of a JPEG, PNG, or TIFF file. Other
+//formats and anything that can't be parsed give back empty metadata.
+pub fn read_metadata(bytes: &[u8]) -> Metadata {
+    let mut metadata = Metadata::default();
+    if bytes.starts_with(&[0xFF,0xD8]) {
+        read_jpeg_metadata(bytes,&mut metadata);
+    }
+    else if bytes.starts_with(PNG_SIGNATURE) {
+        read_png_metadata(bytes,&mut metadata);
+    }
+    else if bytes.starts_with(b"II*\0") || bytes.starts_with(b"MM\0*") {
+        read_tiff_metadata(bytes,&mut metadata);
+    }
+    metadata
+}
+
+fn read_jpeg_metadata(bytes: &[u8],metadata: &mut Metadata) {
+    let mut icc_chunks: Vec<(u8,&[u8])> = vec![];
+    let mut offset = 2;
+    while offset + 4 <= bytes.len() && bytes[offset] == 0xFF {
+        let marker = bytes[offset + 1];
+        //Start of scan or end of image. Metadata always comes before the image data.
+        if marker == 0xDA || marker == 0xD9 {
+            break;
+        }
+
+        let length = read_u16(bytes,offset + 2,true).unwrap_or(0) as usize;
+        if length < 2 || offset + 2 + length > bytes.len() {
+            break;
+        }
+        let data = &bytes[offset + 4..offset + 2 + length];
+
+        if marker == 0xE1 && data.starts_with(JPEG_EXIF_HEADER) {
+            read_tiff_metadata(&data[JPEG_EXIF_HEADER.len()..],metadata);
+        }
+        else if marker == 0xE2 && data.starts_with(JPEG_ICC_HEADER) && data.len() > JPEG_ICC_HEADER.len() + 2 {
+            //Profiles are split into numbered chunks when they don't fit in a single segment.
+            let sequence = data[JPEG_ICC_HEADER.len()];
+            icc_chunks.push((sequence,&data[JPEG_ICC_HEADER.len() + 2..]));
+        }
+
+        offset += 2 + length;
+    }
+
+    if !icc_chunks.is_empty() {
+        icc_chunks.sort_by_key(|&(sequence,_)| sequence);
+        metadata.icc_profile = Some(icc_chunks.iter().flat_map(|&(_,chunk)| chunk.iter().cloned()).collect());
+    }
+}
+
+fn read_png_metadata(bytes: &[u8],metadata: &mut Metadata) {
+    let mut offset = PNG_SIGNATURE.len();
+    while let Some(length) = read_u32(bytes,offset,true) {
+        let length = length as usize;
+        let data_start = offset + 8;
+        if data_start + length > bytes.len() {
+            break;
+        }
+        let chunk_type = &bytes[offset + 4..data_start];
+        let data = &bytes[data_start..data_start + length];
+
+        match chunk_type {
+            b"IDAT" | b"IEND" => break,
+            b"eXIf" => read_tiff_metadata(data,metadata),
+            b"iCCP" => {
+                //Profile name, a null, the compression method, and then zlib compressed data.
+                if let Some(name_end) = data.iter().position(|&c| c == 0) {
+                    if let Some(compressed) = data.get(name_end + 2..) {
+                        metadata.icc_profile = inflate::inflate_bytes_zlib(compressed).ok();
+                    }
+                }
+            },
+            b"tEXt" => {
+                if let Some(keyword_end) = data.iter().position(|&c| c == 0) {
+                    //tEXt is Latin-1 which maps directly onto the first 256 code points.
+                    let text: String = data[keyword_end + 1..].iter().map(|&c| c as char).collect();
+                    match &data[..keyword_end] {
+                        b"Author" => metadata.artist = Some(text),
+                        b"Copyright" => metadata.copyright = Some(text),
+                        _ => {},
+                    }
+                }
+            },
+            _ => {},
+        }
+
+        offset = data_start + length + 4;
+    }
+}
+
+//Read the tags from the first directory of a TIFF structure. EXIF blocks use the same layout.
+fn read_tiff_metadata(bytes: &[u8],metadata: &mut Metadata) {
+    let big_endian = match bytes.get(0..2) {
+        Some(b"II") => false,
+        Some(b"MM") => true,
+        _ => return,
+    };
+
+    let directory = match read_u32(bytes,4,big_endian) {
+        Some(offset) => offset as usize,
+        None => return,
+    };
+    let entry_count = read_u16(bytes,directory,big_endian).unwrap_or(0) as usize;
+
+    for index in 0..entry_count {
+        let entry = directory + 2 + index * 12;
+        let (tag,field_type,count) = match (read_u16(bytes,entry,big_endian),read_u16(bytes,entry + 2,big_endian),read_u32(bytes,entry + 4,big_endian)) {
+            (Some(tag),Some(field_type),Some(count)) => (tag,field_type,count as usize),
+            _ => return,
+        };
+
+        //Values of four bytes or less are stored in the entry, everything else is at an offset.
+        let value_bytes = |element_size: usize| -> Option<&[u8]> {
+            let size = count.checked_mul(element_size)?;
+            let start = if size <= 4 {
+                entry + 8
+            }
+            else {
+                read_u32(bytes,entry + 8,big_endian)? as usize
+            };
+            bytes.get(start..start.checked_add(size)?)
+        };
+
+        match (tag,field_type) {
+            //SHORT
+            (TAG_ORIENTATION,3) => {
+                metadata.orientation = read_u16(bytes,entry + 8,big_endian).filter(|orientation| (1..=8).contains(orientation));
+            },
+            //ASCII
+            (TAG_ARTIST,2) => metadata.artist = value_bytes(1).map(ascii_to_string),
+            (TAG_COPYRIGHT,2) => metadata.copyright = value_bytes(1).map(ascii_to_string),
+            //BYTE or UNDEFINED
+            (TAG_ICC_PROFILE,1) | (TAG_ICC_PROFILE,7) => metadata.icc_profile = value_bytes(1).map(|profile| profile.to_vec()),
+            _ => {},
+        }
+    }
+}
+
+fn ascii_to_string(bytes: &[u8]) -> String {
+    let end = bytes.iter().position(|&c| c == 0).unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..end]).into_owned()
+}
+
+fn read_u16(bytes: &[u8],offset: usize,big_endian: bool) -> Option<u16> {
+    let pair = [*bytes.get(offset)?,*bytes.get(offset + 1)?];
+    Some(if big_endian { u16::from_be_bytes(pair) } else { u16::from_le_bytes(pair) })
+}
+
+fn read_u32(bytes: &[u8],offset: usize,big_endian: bool) -> Option<u32> {
+    let quad = [*bytes.get(offset)?,*bytes.get(offset + 1)?,*bytes.get(offset + 2)?,*bytes.get(offset + 3)?];
+    Some(if big_endian { u32::from_be_bytes(quad) } else { u32::from_le_bytes(quad) })
+}
+
+//Rotate and flip the image so it displays upright according to its EXIF orientation.
+pub fn apply_orientation(image: RgbImage,orientation: u16) -> RgbImage {
+    match orientation {
+        2 => imageops::flip_horizontal(&image),
+        3 => imageops::rotate180(&image),
+        4 => imageops::flip_vertical(&image),
+        //Transpose.
+        5 => imageops::flip_horizontal(&imageops::rotate90(&image)),
+        6 => imageops::rotate90(&image),
+        //Transverse.
+        7 => imageops::flip_horizontal(&imageops::rotate270(&image)),
+        8 => imageops::rotate270(&image),
+        _ => image,
+    }
+}
+
+//Build a big-endian EXIF block holding the author and copyright. Orientation is left out because
+//the rendered pixels are always upright. Returns None if there is nothing to store.
+pub fn build_exif(metadata: &Metadata) -> Option<Vec<u8>> {
+    let mut fields = vec![];
+    if let Some(ref artist) = metadata.artist {
+        fields.push((TAG_ARTIST,artist));
+    }
+    if let Some(ref copyright) = metadata.copyright {
+        fields.push((TAG_COPYRIGHT,copyright));
+    }
+    if fields.is_empty() {
+        return None;
+    }
+
+    let directory_size = 2 + fields.len() * 12 + 4;
+    let mut bytes = b"MM\0*".to_vec();
+    bytes.extend_from_slice(&8u32.to_be_bytes());
+    bytes.extend_from_slice(&(fields.len() as u16).to_be_bytes());
+
+    let mut values = vec![];
+    for &(tag,text) in &fields {
+        let mut value = text.as_bytes().to_vec();
+        value.push(0);
+
+        bytes.extend_from_slice(&tag.to_be_bytes());
+        bytes.extend_from_slice(&2u16.to_be_bytes()); //ASCII
+        bytes.extend_from_slice(&(value.len() as u32).to_be_bytes());
+        if value.len() <= 4 {
+            value.resize(4,0);
+            bytes.extend_from_slice(&value);
+        }
+        else {
+            let offset = 8 + directory_size + values.len();
+            bytes.extend_from_slice(&(offset as u32).to_be_bytes());
+            values.extend_from_slice(&value);
+        }
+    }
+    bytes.extend_from_slice(&0u32.to_be_bytes()); //No more directories.
+    bytes.extend_from_slice(&values);
+    Some(bytes)
+}
+
+//Insert metadata into an image that has already been encoded. Only JPEG and PNG are handled here,
+//TIFF output writes its own tags and the other formats have no standard place to put it.
+pub fn embed_metadata(encoded: Vec<u8>,format: OutputFormat,metadata: &Metadata) -> Vec<u8> {
+    match format {
+        OutputFormat::Jpeg => embed_jpeg_metadata(encoded,metadata),
+        OutputFormat::Png => embed_png_metadata(encoded,metadata),
+        _ => encoded,
+    }
+}
+
+fn embed_jpeg_metadata(encoded: Vec<u8>,metadata: &Metadata) -> Vec<u8> {
+    if !encoded.starts_with(&[0xFF,0xD8]) {
+        return encoded;
+    }
+
+    let mut segments = vec![];
+    if let Some(exif) = build_exif(metadata) {
+        let mut data = JPEG_EXIF_HEADER.to_vec();
+        data.extend_from_slice(&exif);
+        if data.len() <= JPEG_SEGMENT_MAX {
+            segments.push((0xE1,data));
+        }
+    }
+    if let Some(ref profile) = metadata.icc_profile {
+        let chunk_size = JPEG_SEGMENT_MAX - JPEG_ICC_HEADER.len() - 2;
+        let chunk_count = profile.len().div_ceil(chunk_size);
+        //Chunks are numbered with a single byte.
+        if chunk_count <= 255 {
+            for (index,chunk) in profile.chunks(chunk_size).enumerate() {
+                let mut data = JPEG_ICC_HEADER.to_vec();
+                data.push((index + 1) as u8);
+                data.push(chunk_count as u8);
+                data.extend_from_slice(chunk);
+                segments.push((0xE2,data));
+            }
+        }
+    }
+
+    //Keep a leading JFIF segment first so readers that expect it still find it.
+    let mut insert_at = 2;
+    if encoded.get(2..4) == Some(&[0xFF,0xE0]) {
+        if let Some(length) = read_u16(&encoded,4,true) {
+            insert_at = std::cmp::min(encoded.len(),4 + length as usize);
+        }
+    }
+
+    let mut bytes = Vec::with_capacity(encoded.len() + segments.iter().map(|(_,data)| data.len() + 4).sum::<usize>());
+    bytes.extend_from_slice(&encoded[..insert_at]);
+    for (marker,data) in segments {
+        bytes.extend_from_slice(&[0xFF,marker]);
+        bytes.extend_from_slice(&((data.len() + 2) as u16).to_be_bytes());
+        bytes.extend_from_slice(&data);
+    }
+    bytes.extend_from_slice(&encoded[insert_at..]);
+    bytes
+}
+
+fn embed_png_metadata(encoded: Vec<u8>,metadata: &Metadata) -> Vec<u8> {
+    //Everything goes right after IHDR which is always the first chunk.
+    let insert_at = PNG_SIGNATURE.len() + 8 + 13 + 4;
+    if !encoded.starts_with(PNG_SIGNATURE) || encoded.len() < insert_at {
+        return encoded;
+    }
+
+    let mut chunks = vec![];
+    if let Some(ref profile) = metadata.icc_profile {
+        let mut data = b"ICC Profile\0\0".to_vec();
+        data.extend_from_slice(&zlib_stored(profile));
+        chunks.push((b"iCCP",data));
+    }
+    if let Some(exif) = build_exif(metadata) {
+        chunks.push((b"eXIf",exif));
+    }
+    for &(keyword,text) in &[("Author",&metadata.artist),("Copyright",&metadata.copyright)] {
+        if let Some(ref text) = *text {
+            let mut data = keyword.as_bytes().to_vec();
+            data.push(0);
+            data.extend(text.chars().map(|c| if (c as u32) < 256 { c as u8 } else { b'?' }));
+            chunks.push((b"tEXt",data));
+        }
+    }
+
+    let mut bytes = Vec::with_capacity(encoded.len());
+    bytes.extend_from_slice(&encoded[..insert_at]);
+    for (chunk_type,data) in chunks {
+        bytes.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        let start = bytes.len();
+        bytes.extend_from_slice(chunk_type);
+        bytes.extend_from_slice(&data);
+        let crc = crc32(&bytes[start..]);
+        bytes.extend_from_slice(&crc.to_be_bytes());
+    }
+    bytes.extend_from_slice(&encoded[insert_at..]);
+    bytes
+}
+
+//Wrap data in a zlib stream without compressing it. ICC profiles are small enough that it's not
+//worth pulling in a compressor.
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut bytes = vec![0x78,0x01];
+    let mut blocks = data.chunks(65535).peekable();
+    if blocks.peek().is_none() {
+        bytes.extend_from_slice(&[1,0,0,0xFF,0xFF]);
+    }
+    while let Some(block) = blocks.next() {
+        let is_final = blocks.peek().is_none();
+        let length = block.len() as u16;
+        bytes.push(if is_final { 1 } else { 0 });
+        bytes.extend_from_slice(&length.to_le_bytes());
+        bytes.extend_from_slice(&(!length).to_le_bytes());
+        bytes.extend_from_slice(block);
+    }
+
+    let (mut a,mut b) = (1u32,0u32);
+    for &byte in data {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    bytes.extend_from_slice(&((b << 16) | a).to_be_bytes());
+    bytes
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = 0xFFFFFFFFu32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB88320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
@@ -1,5 +1,20 @@
 extern crate std;
 
+use canvas::Canvas;
+use path::Path;
+use utility::{min_f32,max_f32};
+
+//Miter joins that would stick out further than this many half pen widths are beveled instead.
+const MITER_LIMIT: f32 = 4.0;
+
+//How thick lines are joined together at the corners of a path.
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum LineJoin {
+    Miter,
+    Round,
+    Bevel,
+}
+
 struct Color {
     red: u8,
     green: u8,
@@ -10,9 +25,14 @@ struct Color {
 //learning Rust. This implementation is ugly and inefficient but it works fine for splatting lines.
 pub struct Painter {
     pen: Color,
+    pen_end: Option<Color>,
+    pen_width: f32,
+    pen_opacity: f32,
+    line_join: LineJoin,
 }
 
 impl Painter {
+    #[allow(clippy::new_without_default)]
     pub fn new() -> Painter {
         Painter {
             pen: Color {
@@ -20,6 +40,10 @@ impl Painter {
                 green: 0,
                 blue: 0,
             },
+            pen_end: None,
+            pen_width: 1.0,
+            pen_opacity: 1.0,
+            line_join: LineJoin::Miter,
         }
     }
 
@@ -27,19 +51,76 @@ impl Painter {
         self.pen.red = red;
         self.pen.green = green;
         self.pen.blue = blue;
+        self.pen_end = None;
+    }
+
+    //Set a pen that fades from the first color at the start of a line to the second color at the
+    //end of the line.
+    pub fn set_pen_gradient(&mut self,red1: u8,green1: u8,blue1: u8,red2: u8,green2: u8,blue2: u8) {
+        self.set_pen(red1,green1,blue1);
+        self.pen_end = Some(Color {
+            red: red2,
+            green: green2,
+            blue: blue2,
+        });
+    }
+
+    //Set how many pixels wide lines are drawn.
+    pub fn set_pen_width(&mut self,pen_width: f32) {
+        self.pen_width = pen_width;
+    }
+
+    //Set how much lines cover what is underneath them from 0.0 (invisible) to 1.0 (opaque).
+    pub fn set_pen_opacity(&mut self,pen_opacity: f32) {
+        self.pen_opacity = max_f32(min_f32(pen_opacity,1.0),0.0);
     }
 
-    fn clip_line_from_outside(width: usize,height: usize,x1: i32,y1: i32,x2: i32,y2: i32) -> Result<(i32, i32, i32, i32), &'static str>  {
+    pub fn set_line_join(&mut self,line_join: LineJoin) {
+        self.line_join = line_join;
+    }
+
+    //Find how far along a line from x1,y1 to x2,y2 the point x,y is, from 0.0 at the start to 1.0
+    //at the end. The point is projected onto the line so clipping and the drawing direction do not
+    //matter.
+    fn segment_position(x1: i32,y1: i32,x2: i32,y2: i32,x: usize,y: usize) -> f32 {
+        let diff_x = x2 as f32 - x1 as f32;
+        let diff_y = y2 as f32 - y1 as f32;
+        let length_squared = diff_x * diff_x + diff_y * diff_y;
+        if length_squared == 0.0 {
+            return 0.0;
+        }
+
+        let t = ((x as f32 - x1 as f32) * diff_x + (y as f32 - y1 as f32) * diff_y) / length_squared;
+        t.clamp(0.0,1.0)
+    }
+
+    //Find the pen color at position t (0.0 to 1.0) along whatever is being drawn.
+    fn pen_at(&self,t: f32) -> (u8,u8,u8) {
+        let pen_end = match self.pen_end {
+            Some(ref pen_end) => pen_end,
+            None => return (self.pen.red,self.pen.green,self.pen.blue),
+        };
+
+        let lerp = |start: u8,end: u8| -> u8 {
+            (start as f32 + (end as f32 - start as f32) * t).round() as u8
+        };
+        (lerp(self.pen.red,pen_end.red),lerp(self.pen.green,pen_end.green),lerp(self.pen.blue,pen_end.blue))
+    }
+
+    //Find where a line starting outside of the box enters and leaves it. Returns None if the line is
+    //axis aligned or misses the box entirely.
+    #[allow(clippy::neg_multiply)]
+    fn clip_line_from_outside(width: usize,height: usize,x1: i32,y1: i32,x2: i32,y2: i32) -> Option<(i32, i32, i32, i32)>  {
         //Shorten the box slightly so the end points end up inside of the box.
         let width = width - 1;
         let height = height - 1;
 
-        //Calculate line's normal.
-        let diff_x = (x2 - x1) as f32;
-        let diff_y = (y2 - y1) as f32;
+        //Calculate line's normal. Done as floats so lines with far away end points can't overflow.
+        let diff_x = x2 as f32 - x1 as f32;
+        let diff_y = y2 as f32 - y1 as f32;
 
         if diff_x == 0.0 || diff_y == 0.0 {
-            return Err("Line is a point");
+            return None;
         }
 
         let length = (diff_x * diff_x + diff_y * diff_y).sqrt();
@@ -50,7 +131,7 @@ impl Painter {
         let inverted_direction = 1.0 / nx;
         let mut t = (
             -1.0 * x1 as f32 * inverted_direction,
-            (width as i32 - x1) as f32 * inverted_direction
+            (width as f32 - x1 as f32) * inverted_direction
         );
         if inverted_direction < 0.0 {
             t = (t.1, t.0);
@@ -58,13 +139,13 @@ impl Painter {
         let t_min_x = t.0.max(0.0);
         let t_max_x = t.1.min(length);
         if t_max_x < t_min_x {
-            return Err("Line does not intersect box");
+            return None;
         }
 
         let inverted_direction = 1.0 / ny;
         let mut t = (
             -1.0 * y1 as f32 * inverted_direction,
-            (height as i32 - y1) as f32 * inverted_direction
+            (height as f32 - y1 as f32) * inverted_direction
         );
         if inverted_direction < 0.0 {
             t = (t.1, t.0);
@@ -72,7 +153,7 @@ impl Painter {
         let t_min_y = t.0.max(0.0);
         let t_max_y = t.1.min(length);
         if t_max_y < t_min_y {
-            return Err("Line does not intersect box");
+            return None;
         }
 
         let result = (
@@ -81,9 +162,10 @@ impl Painter {
             x1 + (nx * t_max_x).round() as i32,
             y1 + (ny * t_max_y).round() as i32
         );
-        Ok(result)
+        Some(result)
     }
 
+    #[allow(clippy::needless_return)]
     fn clip_line_from_inside(width: usize,height: usize,x1: i32,y1: i32,x2: i32,y2: i32) -> (i32, i32)  {
         //Shorten the box slightly so the end points end up inside of the box.
         let width = width - 1;
@@ -106,7 +188,7 @@ impl Painter {
             }
         }
 
-        let slope = (y2 - y1) as f32 / (x2 - x1) as f32;
+        let slope = (y2 as f32 - y1 as f32) / (x2 as f32 - x1 as f32);
         let b = y1 as f32 - slope * x1 as f32;
 
         let top_intersection = ((-b / slope) as i32,0);
@@ -116,8 +198,8 @@ impl Painter {
 
         //Check distance between x1,y1 and each of these. Use the closest positive one.
         let distance_squared = |intersection: (i32,i32)| {
-            let diff_x = (intersection.0 - x1) as f32;
-            let diff_y = (intersection.1 - y1) as f32;
+            let diff_x = intersection.0 as f32 - x1 as f32;
+            let diff_y = intersection.1 as f32 - y1 as f32;
             diff_x * diff_x + diff_y * diff_y
         };
 
@@ -126,8 +208,8 @@ impl Painter {
         let left_intersection_distance = distance_squared(left_intersection);
         let right_intersection_distance = distance_squared(right_intersection);
 
-        let line_down = y2 - y1 > 0;
-        let line_right = x2 - x1 > 0;
+        let line_down = y2 > y1;
+        let line_right = x2 > x1;
 
         if line_down {
             if line_right {
@@ -188,8 +270,8 @@ impl Painter {
                 if !p1_in_box {
                     let intersection = Self::clip_line_from_outside(width,height,x1,y1,x2,y2);
                     match intersection {
-                        Ok(point) => { x1 = point.0; y1 = point.1; },
-                        Err(_) => return,
+                        Some(point) => { x1 = point.0; y1 = point.1; },
+                        None => return,
                     }
                     p1_in_box = true;
                 }
@@ -211,12 +293,14 @@ impl Painter {
             }
 
             //Handle the special case of a point.
-            if y1 == y2 {
-                y2 += 1;
-            }
+            let end_y = if y1 == y2 { y2 as i64 + 1 } else { y2 as i64 };
 
-            //Handle special case of a vertical line.
-            for y in y1..y2 {
+            //Handle special case of a vertical line. Clipping works in floating point so rounding
+            //can leave the ends just outside of the image.
+            if x1 < 0 || x1 >= width as i32 {
+                return;
+            }
+            for y in std::cmp::max(0,y1 as i64)..std::cmp::min(end_y,height as i64) {
                 func(x1 as usize,y as usize);
             }
             return;
@@ -235,17 +319,24 @@ impl Painter {
         let mut error = 0.0;
         let delta_error = (delta_y / delta_x).abs();
 
+        //Clipping works in floating point so rounding can push the ends of the line just outside
+        //of the image. Stop drawing as soon as that happens.
+        if x1 < 0 || y1 < 0 || y1 >= height as i32 {
+            return;
+        }
+
         let y_increment: i32 = if y2 > y1 { 1 } else { -1 };
         let mut y = y1 as usize;
         let (x1,x2) = (x1 as usize,x2 as usize);
         for x in x1..x2 {
-            assert!(x < width);
-            assert!(y < height);
+            if x >= width || y >= height {
+                break;
+            }
 
             func(x,y);
 
             error += delta_error;
-            while error >= 0.5 {
+            while error >= 0.5 && y < height {
                 func(x,y);
                 if y_increment != -1 || y > 0 {
                     y = (y as i32 + y_increment) as usize;
@@ -255,13 +346,348 @@ impl Painter {
         }
     }
 
-    pub fn line(&self,buffer: &mut [u8],width: usize,height: usize,x1: i32,y1: i32,x2: i32,y2: i32) {
+    //Lines that are thick or translucent go through the path code so that no pixel is drawn
+    //twice.
+    fn needs_path(&self) -> bool {
+        self.pen_width > 1.0 || self.pen_opacity < 1.0
+    }
+
+    pub fn line<C: Canvas + ?Sized>(&self,canvas: &mut C,x1: i32,y1: i32,x2: i32,y2: i32) {
+        if self.needs_path() {
+            self.polyline(canvas,&[(x1 as f32,y1 as f32),(x2 as f32,y2 as f32)]);
+            return;
+        }
+
+        let (width,height) = (canvas.width(),canvas.height());
         Self::line_foreach(width,height,x1,y1,x2,y2,|x,y| {
-            let index: usize = (y * width + x) * 3;
-            buffer[index + 0] = self.pen.red;
-            buffer[index + 1] = self.pen.green;
-            buffer[index + 2] = self.pen.blue;
+            let (red,green,blue) = self.pen_at(Self::segment_position(x1,y1,x2,y2,x,y));
+            canvas.blend(x,y,red,green,blue,self.pen_opacity);
         });
     }
- }
 
+    //Draw a line where every pixel's color is chosen by func. Useful for sampling a source image
+    //along the whole line instead of using a single pen color.
+    pub fn line_sampled<C: Canvas + ?Sized,F>(&self,canvas: &mut C,x1: i32,y1: i32,x2: i32,y2: i32,mut func: F)
+        where F: FnMut(usize,usize) -> (u8,u8,u8) {
+        if self.needs_path() {
+            let path = Path::polyline(&[(x1 as f32,y1 as f32),(x2 as f32,y2 as f32)]).unwrap();
+            self.path_sampled(canvas,&path,func);
+            return;
+        }
+
+        let (width,height) = (canvas.width(),canvas.height());
+        Self::line_foreach(width,height,x1,y1,x2,y2,|x,y| {
+            let (red,green,blue) = func(x,y);
+            canvas.blend(x,y,red,green,blue,self.pen_opacity);
+        });
+    }
+
+    //Call func for every pixel whose center is inside of the polygon. Uses the even-odd rule so
+    //the polygon can be concave or self-intersecting. Centers that land exactly on the bottom or
+    //right edge are left out so polygons that share an edge never both cover the same pixel.
+    pub fn polygon_foreach<F>(width: usize,height: usize,points: &[(f32,f32)],mut func: F)
+        where F: FnMut(usize,usize) {
+        if width == 0 || height == 0 || points.len() < 3 {
+            return;
+        }
+
+        let min_y = points.iter().fold(f32::INFINITY,|min,point| min_f32(min,point.1));
+        let max_y = points.iter().fold(f32::NEG_INFINITY,|max,point| max_f32(max,point.1));
+        if !min_y.is_finite() || !max_y.is_finite() || max_y < 0.0 || min_y >= height as f32 {
+            return;
+        }
+        let first_row = max_f32(0.0,(min_y - 0.5).ceil()) as usize;
+        let last_row = std::cmp::min((max_y - 0.5).floor() as usize,height - 1);
+
+        let mut crossings = vec![];
+        for y in first_row..(last_row + 1) {
+            let center_y = y as f32 + 0.5;
+
+            //Find where each edge crosses the center of this row.
+            crossings.clear();
+            for index in 0..points.len() {
+                let (x1,y1) = points[index];
+                let (x2,y2) = points[(index + 1) % points.len()];
+                //Always work from the top end so a shared edge crosses at exactly the same spot.
+                let ((x1,y1),(x2,y2)) = if y1 <= y2 { ((x1,y1),(x2,y2)) } else { ((x2,y2),(x1,y1)) };
+                if (y1 <= center_y) != (y2 <= center_y) {
+                    crossings.push(x1 + (center_y - y1) / (y2 - y1) * (x2 - x1));
+                }
+            }
+            crossings.sort_by(|lhs,rhs| lhs.partial_cmp(rhs).unwrap_or(std::cmp::Ordering::Equal));
+
+            for pair in crossings.chunks(2) {
+                if pair.len() != 2 || pair[1] < 0.0 || pair[0] >= width as f32 {
+                    continue;
+                }
+
+                let first_column = max_f32(0.0,(pair[0] - 0.5).ceil()) as usize;
+                let last_column = (pair[1] - 0.5).ceil() - 1.0;
+                if last_column < 0.0 {
+                    continue;
+                }
+                let last_column = std::cmp::min(last_column as usize,width - 1);
+                for x in first_column..(last_column + 1) {
+                    func(x,y);
+                }
+            }
+        }
+    }
+
+    //Call func for every pixel whose center is within radius of cx,cy.
+    pub fn disc_foreach<F>(width: usize,height: usize,cx: f32,cy: f32,radius: f32,mut func: F)
+        where F: FnMut(usize,usize) {
+        if width == 0 || height == 0 || radius < 0.0 || cx + radius < 0.0 || cy + radius < 0.0 {
+            return;
+        }
+
+        let first_row = max_f32(0.0,(cy - radius - 0.5).ceil()) as usize;
+        let last_row = std::cmp::min(max_f32(0.0,(cy + radius - 0.5).floor()) as usize,height - 1);
+        let first_column = max_f32(0.0,(cx - radius - 0.5).ceil()) as usize;
+        let last_column = std::cmp::min(max_f32(0.0,(cx + radius - 0.5).floor()) as usize,width - 1);
+        for y in first_row..(last_row + 1) {
+            for x in first_column..(last_column + 1) {
+                let diff_x = x as f32 + 0.5 - cx;
+                let diff_y = y as f32 + 0.5 - cy;
+                if diff_x * diff_x + diff_y * diff_y <= radius * radius {
+                    func(x,y);
+                }
+            }
+        }
+    }
+
+    //Find every pixel covered by a path along with how far along the path (0.0 to 1.0) the pixel
+    //is. Each pixel is only returned once even where segments overlap.
+    fn path_pixels(width: usize,height: usize,path: &Path,pen_width: f32,line_join: LineJoin) -> Vec<(usize,f32)> {
+        let mut pixels = vec![];
+        if width == 0 || height == 0 {
+            return pixels;
+        }
+
+        let points = path.flatten();
+        let segment_length = |index: usize| -> f32 {
+            let (x1,y1) = points[index];
+            let (x2,y2) = points[index + 1];
+            (x2 - x1).hypot(y2 - y1)
+        };
+        let total_length = (0..points.len() - 1).fold(0.0,|sum,index| sum + segment_length(index));
+        let position = |traveled: f32| -> f32 {
+            if total_length == 0.0 { 0.0 } else { traveled / total_length }
+        };
+
+        if pen_width <= 1.0 {
+            if points.len() == 1 {
+                let (x,y) = (points[0].0.round() as i32,points[0].1.round() as i32);
+                Self::line_foreach(width,height,x,y,x,y,|x,y| pixels.push((y * width + x,0.0)));
+            }
+
+            let mut traveled = 0.0;
+            for index in 0..points.len() - 1 {
+                let (x1,y1) = (points[index].0.round() as i32,points[index].1.round() as i32);
+                let (x2,y2) = (points[index + 1].0.round() as i32,points[index + 1].1.round() as i32);
+                let length = segment_length(index);
+                Self::line_foreach(width,height,x1,y1,x2,y2,|x,y| {
+                    pixels.push((y * width + x,position(traveled + Self::segment_position(x1,y1,x2,y2,x,y) * length)));
+                });
+                traveled += length;
+            }
+        }
+        else {
+            let half_width = pen_width * 0.5;
+            if points.len() == 1 {
+                let (x,y) = points[0];
+                Self::disc_foreach(width,height,x,y,half_width,|x,y| pixels.push((y * width + x,0.0)));
+            }
+
+            //Unit normal of each segment.
+            let normals: Vec<(f32,f32)> = (0..points.len() - 1).map(|index| {
+                let (x1,y1) = points[index];
+                let (x2,y2) = points[index + 1];
+                let length = segment_length(index);
+                (-(y2 - y1) / length,(x2 - x1) / length)
+            }).collect();
+
+            let mut traveled = 0.0;
+            for index in 0..points.len() - 1 {
+                let (x1,y1) = points[index];
+                let (x2,y2) = points[index + 1];
+                let (nx,ny) = (normals[index].0 * half_width,normals[index].1 * half_width);
+                let length = segment_length(index);
+
+                let quad = [(x1 + nx,y1 + ny),(x2 + nx,y2 + ny),(x2 - nx,y2 - ny),(x1 - nx,y1 - ny)];
+                Self::polygon_foreach(width,height,&quad,|x,y| {
+                    let t = ((x as f32 + 0.5 - x1) * (x2 - x1) + (y as f32 + 0.5 - y1) * (y2 - y1)) / (length * length);
+                    pixels.push((y * width + x,position(traveled + t.clamp(0.0,1.0) * length)));
+                });
+                traveled += length;
+            }
+
+            //Fill in the gaps where segments meet. A closed path also joins the last segment to the
+            //first.
+            let mut joins: Vec<(usize,usize,usize,f32)> = vec![];
+            let mut traveled = 0.0;
+            for index in 1..normals.len() {
+                traveled += segment_length(index - 1);
+                joins.push((index,index - 1,index,position(traveled)));
+            }
+            if path.is_closed() && normals.len() > 1 {
+                joins.push((0,normals.len() - 1,0,0.0));
+            }
+
+            for (vertex,previous,next,t) in joins {
+                let (vx,vy) = points[vertex];
+                let (previous_x,previous_y) = normals[previous];
+                let (next_x,next_y) = normals[next];
+
+                //Joins only need to cover the outside of the turn since the inside is already
+                //covered by the segments.
+                let cross = previous_y * next_x - previous_x * next_y;
+                let side = if cross > 0.0 { half_width } else { -half_width };
+                let outer_previous = (vx + previous_x * side,vy + previous_y * side);
+                let outer_next = (vx + next_x * side,vy + next_y * side);
+
+                let push = |x: usize,y: usize| pixels.push((y * width + x,t));
+                match line_join {
+                    LineJoin::Round => Self::disc_foreach(width,height,vx,vy,half_width,push),
+                    LineJoin::Bevel => Self::polygon_foreach(width,height,&[(vx,vy),outer_previous,outer_next],push),
+                    LineJoin::Miter => {
+                        let (bisector_x,bisector_y) = (previous_x + next_x,previous_y + next_y);
+                        let bisector_length = bisector_x.hypot(bisector_y);
+                        let cos_half_angle = bisector_length * 0.5;
+                        if bisector_length == 0.0 || 1.0 / cos_half_angle > MITER_LIMIT {
+                            Self::polygon_foreach(width,height,&[(vx,vy),outer_previous,outer_next],push);
+                        }
+                        else {
+                            let miter_length = side / cos_half_angle;
+                            let miter = (vx + bisector_x / bisector_length * miter_length,vy + bisector_y / bisector_length * miter_length);
+                            Self::polygon_foreach(width,height,&[(vx,vy),outer_previous,miter,outer_next],push);
+                        }
+                    },
+                }
+            }
+        }
+
+        //Keep the first visit to every pixel.
+        pixels.sort_by_key(|&(index,_)| index);
+        pixels.dedup_by_key(|&mut (index,_)| index);
+        pixels
+    }
+
+    //Call func once for every pixel covered by the path when drawn with a pen pen_width pixels wide.
+    pub fn path_foreach<F>(width: usize,height: usize,path: &Path,pen_width: f32,line_join: LineJoin,mut func: F)
+        where F: FnMut(usize,usize) {
+        for (index,_) in Self::path_pixels(width,height,path,pen_width,line_join) {
+            func(index % width,index / width);
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn quadratic_bezier_foreach<F>(width: usize,height: usize,x1: f32,y1: f32,cx: f32,cy: f32,x2: f32,y2: f32,func: F)
+        where F: FnMut(usize,usize) {
+        let mut path = Path::new(x1,y1);
+        path.quadratic_to(cx,cy,x2,y2);
+        Self::path_foreach(width,height,&path,1.0,LineJoin::Miter,func);
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn cubic_bezier_foreach<F>(width: usize,height: usize,x1: f32,y1: f32,cx1: f32,cy1: f32,cx2: f32,cy2: f32,x2: f32,y2: f32,func: F)
+        where F: FnMut(usize,usize) {
+        let mut path = Path::new(x1,y1);
+        path.cubic_to(cx1,cy1,cx2,cy2,x2,y2);
+        Self::path_foreach(width,height,&path,1.0,LineJoin::Miter,func);
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn arc_foreach<F>(width: usize,height: usize,cx: f32,cy: f32,radius: f32,start_angle: f32,end_angle: f32,func: F)
+        where F: FnMut(usize,usize) {
+        let path = Self::arc_path(cx,cy,radius,start_angle,end_angle);
+        Self::path_foreach(width,height,&path,1.0,LineJoin::Miter,func);
+    }
+
+    fn arc_path(cx: f32,cy: f32,radius: f32,start_angle: f32,end_angle: f32) -> Path {
+        let mut path = Path::new(cx + radius * start_angle.cos(),cy + radius * start_angle.sin());
+        path.arc(cx,cy,radius,start_angle,end_angle);
+        path
+    }
+
+    //Draw a path using the pen's color, width, and join. Every pixel is drawn at most once.
+    pub fn path<C: Canvas + ?Sized>(&self,canvas: &mut C,path: &Path) {
+        let width = canvas.width();
+        for (index,t) in Self::path_pixels(width,canvas.height(),path,self.pen_width,self.line_join) {
+            let (red,green,blue) = self.pen_at(t);
+            canvas.blend(index % width,index / width,red,green,blue,self.pen_opacity);
+        }
+    }
+
+    //Same as line_sampled but for a path.
+    pub fn path_sampled<C: Canvas + ?Sized,F>(&self,canvas: &mut C,path: &Path,mut func: F)
+        where F: FnMut(usize,usize) -> (u8,u8,u8) {
+        let width = canvas.width();
+        for (index,_) in Self::path_pixels(width,canvas.height(),path,self.pen_width,self.line_join) {
+            let (x,y) = (index % width,index / width);
+            let (red,green,blue) = func(x,y);
+            canvas.blend(x,y,red,green,blue,self.pen_opacity);
+        }
+    }
+
+    pub fn polyline<C: Canvas + ?Sized>(&self,canvas: &mut C,points: &[(f32,f32)]) {
+        if let Some(path) = Path::polyline(points) {
+            self.path(canvas,&path);
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn quadratic_bezier<C: Canvas + ?Sized>(&self,canvas: &mut C,x1: f32,y1: f32,cx: f32,cy: f32,x2: f32,y2: f32) {
+        let mut path = Path::new(x1,y1);
+        path.quadratic_to(cx,cy,x2,y2);
+        self.path(canvas,&path);
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn cubic_bezier<C: Canvas + ?Sized>(&self,canvas: &mut C,x1: f32,y1: f32,cx1: f32,cy1: f32,cx2: f32,cy2: f32,x2: f32,y2: f32) {
+        let mut path = Path::new(x1,y1);
+        path.cubic_to(cx1,cy1,cx2,cy2,x2,y2);
+        self.path(canvas,&path);
+    }
+
+    pub fn arc<C: Canvas + ?Sized>(&self,canvas: &mut C,cx: f32,cy: f32,radius: f32,start_angle: f32,end_angle: f32) {
+        self.path(canvas,&Self::arc_path(cx,cy,radius,start_angle,end_angle));
+    }
+
+    //Draw a filled circle as wide as the pen centered at cx,cy.
+    pub fn dot<C: Canvas + ?Sized>(&self,canvas: &mut C,cx: f32,cy: f32) {
+        let (red,green,blue) = self.pen_at(0.0);
+        let (width,height) = (canvas.width(),canvas.height());
+        Self::disc_foreach(width,height,cx,cy,self.pen_width / 2.0,|x,y| {
+            canvas.blend(x,y,red,green,blue,self.pen_opacity);
+        });
+    }
+
+    //Fill a polygon with the pen's color. The pen width doesn't matter since there's no outline.
+    pub fn polygon<C: Canvas + ?Sized>(&self,canvas: &mut C,points: &[(f32,f32)]) {
+        let (red,green,blue) = self.pen_at(0.0);
+        let (width,height) = (canvas.width(),canvas.height());
+        Self::polygon_foreach(width,height,points,|x,y| {
+            canvas.blend(x,y,red,green,blue,self.pen_opacity);
+        });
+    }
+
+    //Same as line_sampled but for filling a polygon.
+    pub fn polygon_sampled<C: Canvas + ?Sized,F>(&self,canvas: &mut C,points: &[(f32,f32)],mut func: F)
+        where F: FnMut(usize,usize) -> (u8,u8,u8) {
+        let (width,height) = (canvas.width(),canvas.height());
+        Self::polygon_foreach(width,height,points,|x,y| {
+            let (red,green,blue) = func(x,y);
+            canvas.blend(x,y,red,green,blue,self.pen_opacity);
+        });
+    }
+
+    //Same as line_sampled but for a quadratic Bézier curve.
+    #[allow(clippy::too_many_arguments)]
+    pub fn quadratic_bezier_sampled<C: Canvas + ?Sized,F>(&self,canvas: &mut C,x1: f32,y1: f32,cx: f32,cy: f32,x2: f32,y2: f32,func: F)
+        where F: FnMut(usize,usize) -> (u8,u8,u8) {
+        let mut path = Path::new(x1,y1);
+        path.quadratic_to(cx,cy,x2,y2);
+        self.path_sampled(canvas,&path,func);
+    }
+ }
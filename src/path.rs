@@ -0,0 +1,207 @@
+extern crate std;
+
+use utility::max_f32;
+
+//Maximum distance in pixels a flattened curve is allowed to stray from the real curve.
+const FLATTEN_TOLERANCE: f32 = 0.25;
+
+//Upper limit on how many line segments a single curve is broken into. Keeps absurd control points
+//from allocating huge amounts of memory.
+const MAX_CURVE_SEGMENTS: usize = 4096;
+
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum PathSegment {
+    //End point.
+    LineTo(f32,f32),
+    //Control point followed by end point.
+    QuadraticTo(f32,f32,f32,f32),
+    //Two control points followed by end point.
+    CubicTo(f32,f32,f32,f32,f32,f32),
+    //Center, radius, start angle, and end angle. Angles are in radians and a negative sweep goes
+    //counter-clockwise. A straight line connects the current point to the start of the arc.
+    Arc(f32,f32,f32,f32,f32),
+}
+
+//A connected series of lines and curves that is drawn as a single stroke. The segments are kept
+//as-is so curves are only broken into lines when they are rasterized.
+#[derive(Clone,Debug,PartialEq)]
+pub struct Path {
+    start: (f32,f32),
+    segments: Vec<PathSegment>,
+    closed: bool,
+}
+
+impl Path {
+    pub fn new(x: f32,y: f32) -> Path {
+        Path {
+            start: (x,y),
+            segments: vec![],
+            closed: false,
+        }
+    }
+
+    //Create a path with straight lines connecting each of the points. If the last point repeats the
+    //first after at least two others, the path is closed instead so the corner there is joined too.
+    //Returns None if there are no points.
+    pub fn polyline(points: &[(f32,f32)]) -> Option<Path> {
+        let (&(x,y),rest) = points.split_first()?;
+        let closed = points.len() > 3 && points[0] == points[points.len() - 1];
+        let rest = if closed { &rest[..rest.len() - 1] } else { rest };
+        let mut path = Path::new(x,y);
+        for &(x,y) in rest {
+            path.line_to(x,y);
+        }
+        if closed {
+            path.close();
+        }
+        Some(path)
+    }
+
+    //Create a smooth path that rounds off the corners of a polyline. It runs through the midpoint of
+    //each line and bends toward the points in between, only touching the first and last points.
+    //Closed polylines, where the last point repeats the first, make closed paths without any ends.
+    //Returns None if there are no points.
+    pub fn smooth(points: &[(f32,f32)]) -> Option<Path> {
+        let closed = points.len() > 3 && points[0] == points[points.len() - 1];
+        if points.len() < 3 {
+            return Path::polyline(points);
+        }
+
+        let midpoint = |(x1,y1): (f32,f32),(x2,y2): (f32,f32)| ((x1 + x2) / 2.0,(y1 + y2) / 2.0);
+        let last = points.len() - 1;
+        let (x,y) = if closed { midpoint(points[0],points[1]) } else { points[0] };
+        let mut path = Path::new(x,y);
+        if !closed {
+            let (x,y) = midpoint(points[0],points[1]);
+            path.line_to(x,y);
+        }
+        for index in 1..last {
+            let (x,y) = midpoint(points[index],points[index + 1]);
+            path.quadratic_to(points[index].0,points[index].1,x,y);
+        }
+        if closed {
+            //The last point is the first one again. Bend around it back to where the path started.
+            path.quadratic_to(points[0].0,points[0].1,x,y);
+            path.close();
+        }
+        else {
+            path.line_to(points[last].0,points[last].1);
+        }
+        Some(path)
+    }
+
+    pub fn line_to(&mut self,x: f32,y: f32) {
+        self.segments.push(PathSegment::LineTo(x,y));
+    }
+
+    pub fn quadratic_to(&mut self,cx: f32,cy: f32,x: f32,y: f32) {
+        self.segments.push(PathSegment::QuadraticTo(cx,cy,x,y));
+    }
+
+    pub fn cubic_to(&mut self,cx1: f32,cy1: f32,cx2: f32,cy2: f32,x: f32,y: f32) {
+        self.segments.push(PathSegment::CubicTo(cx1,cy1,cx2,cy2,x,y));
+    }
+
+    pub fn arc(&mut self,cx: f32,cy: f32,radius: f32,start_angle: f32,end_angle: f32) {
+        self.segments.push(PathSegment::Arc(cx,cy,radius,start_angle,end_angle));
+    }
+
+    //Connect the end of the path back to the start.
+    pub fn close(&mut self) {
+        self.closed = true;
+    }
+
+    pub fn start(&self) -> (f32,f32) {
+        self.start
+    }
+
+    pub fn segments(&self) -> &[PathSegment] {
+        &self.segments
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+
+    //Break the path into straight lines. Consecutive duplicate points are removed and a closed
+    //path ends with its starting point.
+    pub fn flatten(&self) -> Vec<(f32,f32)> {
+        let mut points = vec![self.start];
+        for segment in &self.segments {
+            let (x1,y1) = points[points.len() - 1];
+            match *segment {
+                PathSegment::LineTo(x,y) => push_point(&mut points,(x,y)),
+                PathSegment::QuadraticTo(cx,cy,x2,y2) => {
+                    let second_difference = (x1 - 2.0 * cx + x2).hypot(y1 - 2.0 * cy + y2);
+                    let segment_count = (second_difference / (4.0 * FLATTEN_TOLERANCE)).sqrt();
+                    flatten_curve(&mut points,segment_count,|t| {
+                        let u = 1.0 - t;
+                        (u * u * x1 + 2.0 * u * t * cx + t * t * x2,
+                         u * u * y1 + 2.0 * u * t * cy + t * t * y2)
+                    });
+                },
+                PathSegment::CubicTo(cx1,cy1,cx2,cy2,x2,y2) => {
+                    let second_difference = max_f32(
+                        (x1 - 2.0 * cx1 + cx2).hypot(y1 - 2.0 * cy1 + cy2),
+                        (cx1 - 2.0 * cx2 + x2).hypot(cy1 - 2.0 * cy2 + y2));
+                    let segment_count = (3.0 * second_difference / (4.0 * FLATTEN_TOLERANCE)).sqrt();
+                    flatten_curve(&mut points,segment_count,|t| {
+                        let u = 1.0 - t;
+                        (u * u * u * x1 + 3.0 * u * u * t * cx1 + 3.0 * u * t * t * cx2 + t * t * t * x2,
+                         u * u * u * y1 + 3.0 * u * u * t * cy1 + 3.0 * u * t * t * cy2 + t * t * t * y2)
+                    });
+                },
+                PathSegment::Arc(cx,cy,radius,start_angle,end_angle) => {
+                    let sweep = end_angle - start_angle;
+                    let radius = radius.abs();
+
+                    //The error between a chord and its arc is r * (1 - cos(theta / 2)).
+                    let segment_count = if radius <= FLATTEN_TOLERANCE {
+                        1.0
+                    }
+                    else {
+                        let max_angle = 2.0 * (1.0 - FLATTEN_TOLERANCE / radius).acos();
+                        sweep.abs() / max_angle
+                    };
+
+                    push_point(&mut points,(cx + radius * start_angle.cos(),cy + radius * start_angle.sin()));
+                    flatten_curve(&mut points,segment_count,|t| {
+                        let angle = start_angle + sweep * t;
+                        (cx + radius * angle.cos(),cy + radius * angle.sin())
+                    });
+                },
+            }
+        }
+
+        if self.closed {
+            let start = self.start;
+            push_point(&mut points,start);
+        }
+
+        points
+    }
+}
+
+fn push_point(points: &mut Vec<(f32,f32)>,point: (f32,f32)) {
+    if points.last() != Some(&point) {
+        points.push(point);
+    }
+}
+
+//Add points along a curve close enough together that drawing straight lines between them is
+//indistinguishable from the curve. The number of segments is picked using Wang's formula so that
+//the error is at most FLATTEN_TOLERANCE pixels. The curve's starting point is assumed to already be
+//in points.
+fn flatten_curve<F>(points: &mut Vec<(f32,f32)>,segment_count: f32,point_at: F)
+    where F: Fn(f32) -> (f32,f32) {
+    let segment_count = if segment_count.is_finite() {
+        (segment_count.ceil() as usize).clamp(1,MAX_CURVE_SEGMENTS)
+    }
+    else {
+        MAX_CURVE_SEGMENTS
+    };
+
+    for index in 1..(segment_count + 1) {
+        push_point(points,point_at(index as f32 / segment_count as f32));
+    }
+}
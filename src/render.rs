@@ -0,0 +1,1841 @@
+extern crate std;
+
+use std::ops::Rem;
+
+use image::RgbImage;
+use rand::{self,Closed01,Open01,Rand,Rng,ThreadRng};
+use rand::distributions::{IndependentSample,Normal};
+
+use canvas::Canvas;
+use contour::{LevelSpacing,contour_levels,isolines};
+use error::{Error,Result};
+use imageprocessing::{GradientOptions,gradient,angle_to_direction,edge_chains,gaussian_blur,hough_lines,luminance,multiscale_gradient_with,non_maximum_suppression,region_boundaries,slic};
+use painter::{LineJoin,Painter};
+use path::Path;
+use stipple::{stipple,tsp_path};
+use strokelog::{StrokeColor,StrokeRecord,StrokeShape};
+use triangulation::triangulate;
+use utility::{clamp_to_u8,min_f32,max_f32,difference_theta,mix,rgb_to_hsl,hsl_to_rgb,wrap_angle};
+
+//How many times a style tries to find a line that fits inside of the canvas before settling for one
+//that's cut off at the edge. Only very thin canvases should ever run out of attempts.
+const LINE_ATTEMPTS_MAX: usize = 100;
+
+//How many lines are drawn between progress reports. The edgeweb style does far more work per edge
+//point than the other styles do per line so it reports more often.
+const PROGRESS_INTERVAL: u64 = 1024;
+const EDGE_PROGRESS_INTERVAL: u64 = 16;
+
+//How many times the stipple style relaxes its dots and the largest density map it relaxes them
+//over. Bigger sources are shrunk to fit so the relaxation takes about the same time for any size.
+const STIPPLE_ITERATIONS: usize = 30;
+const STIPPLE_DENSITY_PIXELS_MAX: usize = 1 << 20;
+
+//How much the contour style smooths the source before tracing it, as a fraction of the source's
+//longer side, and the shortest contour in source pixels worth drawing.
+const CONTOUR_SMOOTHING: f32 = 0.004;
+const CONTOUR_LENGTH_MIN: f32 = 3.0;
+
+//How much the source's gradient is smoothed, as a fraction of the source's longer side, before
+//picking the most salient point as the radial style's focus.
+const SALIENCE_SMOOTHING: f32 = 0.05;
+
+//The shortest line the hough style draws as a fraction of the source's longer side and the longest
+//gap in source pixels it jumps over along a line. Edges are thinned to continuous lines so anything
+//with bigger gaps is usually texture.
+const HOUGH_LENGTH_MIN: f32 = 0.015;
+const HOUGH_GAP_MAX: f32 = 3.0;
+
+//How far in source pixels the outline and regions styles let their simplified edges stray from the
+//edge pixels before they're smoothed.
+const OUTLINE_TOLERANCE: f32 = 1.0;
+
+//Shortest stretch of border in source pixels the regions style draws. Borders are split into short
+//pieces where three regions meet so only stray pixels are left out.
+const REGION_LENGTH_MIN: usize = 2;
+
+//How far apart in canvas pixels strokes are checked while clipping them to a region.
+const CLIP_STEP: f32 = 0.5;
+
+//Wavelengths of the spiral style's wobble in the lightest and darkest parts of the image and of the
+//squiggle style's wave, all relative to the spacing between turns or lines. Waves are followed with
+//at least WOBBLE_STEPS points per wavelength and no more than WOBBLE_STEP_MAX canvas pixels apart.
+const SPIRAL_WAVELENGTH_LIGHT: f32 = 2.0;
+const SPIRAL_WAVELENGTH_DARK: f32 = 0.5;
+const SQUIGGLE_WAVELENGTH: f32 = 1.0;
+const WOBBLE_STEPS: f32 = 12.0;
+const WOBBLE_STEP_MAX: f32 = 1.0;
+
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum Style {
+    Random,
+    Steered,
+    Energy,
+    EdgeWeb,
+    Stipple,
+    Mesh,
+    Contour,
+    Spiral,
+    Squiggle,
+    Radial,
+    Hough,
+    Outline,
+    Regions,
+}
+
+impl Style {
+    pub fn from_name(name: &str) -> Option<Style> {
+        match name.to_lowercase().as_str() {
+            "random" => Some(Style::Random),
+            "steered" => Some(Style::Steered),
+            "energy" => Some(Style::Energy),
+            "edgeweb" => Some(Style::EdgeWeb),
+            "stipple" => Some(Style::Stipple),
+            "mesh" => Some(Style::Mesh),
+            "contour" => Some(Style::Contour),
+            "spiral" => Some(Style::Spiral),
+            "squiggle" => Some(Style::Squiggle),
+            "radial" => Some(Style::Radial),
+            "hough" => Some(Style::Hough),
+            "outline" => Some(Style::Outline),
+            "regions" => Some(Style::Regions),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum ColorMode {
+    //Use the style's preferred color mode.
+    Default,
+    EndpointMix,
+    Center,
+    RandomPoint,
+    LineAverage,
+    RgbJitter,
+    LightnessJitter,
+    HueJitter,
+    Ink,
+    Gradient,
+    Sampled,
+    //Gray matching the luminance of what's being drawn.
+    Level,
+}
+
+impl ColorMode {
+    pub fn from_name(name: &str) -> Option<ColorMode> {
+        match name.to_lowercase().as_str() {
+            "default" => Some(ColorMode::Default),
+            "endpointmix" => Some(ColorMode::EndpointMix),
+            "center" => Some(ColorMode::Center),
+            "randompoint" => Some(ColorMode::RandomPoint),
+            "lineaverage" => Some(ColorMode::LineAverage),
+            "rgbjitter" => Some(ColorMode::RgbJitter),
+            "lightnessjitter" => Some(ColorMode::LightnessJitter),
+            "huejitter" => Some(ColorMode::HueJitter),
+            "ink" => Some(ColorMode::Ink),
+            "gradient" => Some(ColorMode::Gradient),
+            "sampled" => Some(ColorMode::Sampled),
+            "level" => Some(ColorMode::Level),
+            _ => None,
+        }
+    }
+}
+
+//Where the radial style's lines point.
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum Focus {
+    Center,
+    //A point measured in source pixels from the top left corner of the source.
+    Point(f32,f32),
+    //The middle of the busiest part of the source where the smoothed gradient is strongest.
+    Salient,
+}
+
+impl Focus {
+    //Besides center and salient, a point can be given as x,y.
+    pub fn from_name(name: &str) -> Option<Focus> {
+        match name.to_lowercase().as_str() {
+            "center" => Some(Focus::Center),
+            "salient" => Some(Focus::Salient),
+            name => {
+                let mut parts = name.split(',').map(|part| part.trim().parse::<f32>());
+                match (parts.next(),parts.next(),parts.next()) {
+                    (Some(Ok(x)),Some(Ok(y)),None) if x.is_finite() && y.is_finite() => Some(Focus::Point(x,y)),
+                    _ => None,
+                }
+            },
+        }
+    }
+}
+
+//How fine the details in the source are that the gradient picks up.
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum Detail {
+    //Measure the gradient at the source's full resolution.
+    Full,
+    //Measure the gradient as if the source were shrunk so its longer side is this many pixels. The
+    //same size gives sources of different resolutions the same look. Sources that are already
+    //smaller use their full resolution.
+    Size(f32),
+}
+
+impl Detail {
+    //Besides full, a size can be given as a number of pixels.
+    pub fn from_name(name: &str) -> Option<Detail> {
+        match name.to_lowercase().as_str() {
+            "full" => Some(Detail::Full),
+            name => match name.trim().parse::<f32>() {
+                Ok(size) if size >= 1.0 && size.is_finite() => Some(Detail::Size(size)),
+                _ => None,
+            },
+        }
+    }
+}
+
+//How far along a render is. Most styles count lines while edgeweb counts the edge points it has
+//finished matching up.
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub struct Progress {
+    pub completed: u64,
+    pub total: u64,
+}
+
+impl Progress {
+    //Fraction of the work that's done from 0.0 to 1.0.
+    pub fn fraction(&self) -> f32 {
+        if self.total == 0 {
+            return 1.0;
+        }
+        self.completed as f32 / self.total as f32
+    }
+}
+
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum RenderStatus {
+    //Every line was drawn.
+    Finished,
+    //The progress callback asked to stop. Whatever was drawn up to that point is left on the
+    //canvas.
+    Cancelled(Progress),
+}
+
+enum StrokeKind {
+    Line,
+    //Quadratic curve bending toward the control point.
+    Curve(f32,f32),
+    //Dot as wide as the pen centered on the first point.
+    Dot,
+    //Filled triangle with this as its third corner. The corners to fill between are measured from
+    //the top left of the canvas instead of from pixel centers.
+    Triangle(usize,usize,[(f32,f32); 3]),
+    //Connected lines through points measured from the top left of the canvas along with the
+    //luminance they follow, if they follow one.
+    Polyline(Vec<(f32,f32)>,Option<f32>),
+}
+
+//A line generated by one of the styles along with the point it was grown from. The color modes
+//sample the source image using these points.
+struct Stroke {
+    x1: usize,
+    y1: usize,
+    x2: usize,
+    y2: usize,
+    xc: usize,
+    yc: usize,
+    kind: StrokeKind,
+}
+
+impl Stroke {
+    fn new(x1: usize,y1: usize,x2: usize,y2: usize) -> Stroke {
+        Stroke {
+            x1,
+            y1,
+            x2,
+            y2,
+            xc: (x1 + x2) / 2,
+            yc: (y1 + y2) / 2,
+            kind: StrokeKind::Line,
+        }
+    }
+
+    //Create a dot as wide as the pen centered on the pixel at x,y.
+    fn dot(x: usize,y: usize) -> Stroke {
+        Stroke {
+            kind: StrokeKind::Dot,
+            ..Stroke::new(x,y,x,y)
+        }
+    }
+
+    //Create a curved stroke that bends so it passes through xc,yc halfway along.
+    fn curve_through(x1: usize,y1: usize,xc: usize,yc: usize,x2: usize,y2: usize) -> Stroke {
+        let control = (2.0 * xc as f32 - (x1 + x2) as f32 * 0.5,
+                       2.0 * yc as f32 - (y1 + y2) as f32 * 0.5);
+        Stroke {
+            x1,
+            y1,
+            x2,
+            y2,
+            xc,
+            yc,
+            kind: StrokeKind::Curve(control.0,control.1),
+        }
+    }
+
+    //Create a filled triangle between the centers of three pixels on a width by height canvas. Its
+    //center is the centroid. Corners along the border of the canvas are pushed out onto it so
+    //triangles that reach the border fill it completely.
+    fn triangle((x1,y1): (usize,usize),(x2,y2): (usize,usize),(x3,y3): (usize,usize),width: usize,height: usize) -> Stroke {
+        let place = |value: usize,size: usize| {
+            if value == 0 {
+                0.0
+            }
+            else if value + 1 >= size {
+                size as f32
+            }
+            else {
+                value as f32 + 0.5
+            }
+        };
+        let corners = [(place(x1,width),place(y1,height)),(place(x2,width),place(y2,height)),(place(x3,width),place(y3,height))];
+        Stroke {
+            x1,
+            y1,
+            x2,
+            y2,
+            xc: (x1 + x2 + x3) / 3,
+            yc: (y1 + y2 + y3) / 3,
+            kind: StrokeKind::Triangle(x3,y3,corners),
+        }
+    }
+
+    //Create a polyline through points measured from the top left of a width by height canvas that
+    //follows the luminance level, or the luminance underneath it if level is None.
+    fn polyline(points: Vec<(f32,f32)>,level: Option<f32>,width: usize,height: usize) -> Stroke {
+        let pixel = |(x,y): (f32,f32)| (std::cmp::min(max_f32(x,0.0) as usize,width - 1),std::cmp::min(max_f32(y,0.0) as usize,height - 1));
+        let (x1,y1) = pixel(points[0]);
+        let (x2,y2) = pixel(points[points.len() - 1]);
+        let (xc,yc) = pixel(points[points.len() / 2]);
+        Stroke {
+            x1,
+            y1,
+            x2,
+            y2,
+            xc,
+            yc,
+            kind: StrokeKind::Polyline(points,level),
+        }
+    }
+
+    //Find the point at t (0.0 to 1.0) along the stroke. Triangles are walked around their edges.
+    fn point_at(&self,t: f32) -> (f32,f32) {
+        let (x1,y1,x2,y2) = (self.x1 as f32,self.y1 as f32,self.x2 as f32,self.y2 as f32);
+        let lerp = |(x1,y1): (f32,f32),(x2,y2): (f32,f32),t: f32| (x1 + (x2 - x1) * t,y1 + (y2 - y1) * t);
+        match self.kind {
+            StrokeKind::Curve(cx,cy) => {
+                let u = 1.0 - t;
+                (u * u * x1 + 2.0 * u * t * cx + t * t * x2,
+                 u * u * y1 + 2.0 * u * t * cy + t * t * y2)
+            },
+            StrokeKind::Triangle(x3,y3,_) => {
+                let corners = [(x1,y1),(x2,y2),(x3 as f32,y3 as f32),(x1,y1)];
+                let side = std::cmp::min((t * 3.0) as usize,2);
+                lerp(corners[side],corners[side + 1],t * 3.0 - side as f32)
+            },
+            StrokeKind::Polyline(ref points,_) => {
+                let position = t * (points.len() - 1) as f32;
+                let index = std::cmp::min(position as usize,points.len().saturating_sub(2));
+                let next = std::cmp::min(index + 1,points.len() - 1);
+                let (x,y) = lerp(points[index],points[next],position - index as f32);
+                (x - 0.5,y - 0.5)
+            },
+            StrokeKind::Line | StrokeKind::Dot => lerp((x1,y1),(x2,y2),t),
+        }
+    }
+
+    //Shorten a line or curve to the part around t = anchor (0.0 to 1.0) where inside holds for every
+    //point, walking out both ways from the anchor. The center moves to the anchor if it's cut off.
+    //Other kinds of strokes are left alone.
+    fn clip<F>(&mut self,anchor: f32,inside: F)
+        where F: Fn(f32,f32) -> bool {
+        let (x1,y1,x2,y2) = (self.x1 as f32,self.y1 as f32,self.x2 as f32,self.y2 as f32);
+        let length = match self.kind {
+            StrokeKind::Line => (x2 - x1).hypot(y2 - y1),
+            StrokeKind::Curve(cx,cy) => (cx - x1).hypot(cy - y1) + (x2 - cx).hypot(y2 - cy),
+            StrokeKind::Dot | StrokeKind::Triangle(..) | StrokeKind::Polyline(..) => return,
+        };
+        let step = CLIP_STEP / max_f32(length,1.0);
+        let inside_at = |t: f32| {
+            let (x,y) = self.point_at(t);
+            inside(x,y)
+        };
+
+        let mut start = anchor;
+        while start > 0.0 && inside_at(max_f32(start - step,0.0)) {
+            start = max_f32(start - step,0.0);
+        }
+        let mut end = anchor;
+        while end < 1.0 && inside_at(min_f32(end + step,1.0)) {
+            end = min_f32(end + step,1.0);
+        }
+        if start == 0.0 && end == 1.0 {
+            return;
+        }
+
+        let pixel = |(x,y): (f32,f32)| (max_f32(x.round(),0.0) as usize,max_f32(y.round(),0.0) as usize);
+        if !inside(self.xc as f32,self.yc as f32) {
+            let (xc,yc) = pixel(self.point_at(anchor));
+            self.xc = xc;
+            self.yc = yc;
+        }
+        let ((new_x1,new_y1),(new_x2,new_y2)) = (pixel(self.point_at(start)),pixel(self.point_at(end)));
+        if let StrokeKind::Curve(cx,cy) = self.kind {
+            //The piece of a quadratic Bezier between start and end is another quadratic Bezier.
+            let (a,b) = (start,end);
+            let (w1,wc,w2) = ((1.0 - a) * (1.0 - b),(1.0 - a) * b + a * (1.0 - b),a * b);
+            self.kind = StrokeKind::Curve(w1 * x1 + wc * cx + w2 * x2,w1 * y1 + wc * cy + w2 * y2);
+        }
+        self.x1 = new_x1;
+        self.y1 = new_y1;
+        self.x2 = new_x2;
+        self.y2 = new_y2;
+    }
+
+    fn record(&self,color: StrokeColor,style: Style,layer: u16) -> StrokeRecord {
+        let (x1,y1,x2,y2) = (self.x1 as f32,self.y1 as f32,self.x2 as f32,self.y2 as f32);
+        let shape = match self.kind {
+            StrokeKind::Line => StrokeShape::Line { x1,y1,x2,y2 },
+            StrokeKind::Curve(cx,cy) => StrokeShape::Curve { x1,y1,cx,cy,x2,y2 },
+            StrokeKind::Dot => StrokeShape::Dot { x: x1,y: y1 },
+            StrokeKind::Triangle(_,_,[(x1,y1),(x2,y2),(x3,y3)]) => StrokeShape::Triangle { x1,y1,x2,y2,x3,y3 },
+            StrokeKind::Polyline(ref points,_) => StrokeShape::Polyline { points: points.clone() },
+        };
+        StrokeRecord {
+            shape,
+            color,
+            style,
+            layer,
+        }
+    }
+
+    fn foreach<F>(&self,width: u32,height: u32,func: F)
+        where F: FnMut(usize,usize) {
+        let (width,height) = (width as usize,height as usize);
+        match self.kind {
+            StrokeKind::Curve(cx,cy) => Painter::quadratic_bezier_foreach(width,height,self.x1 as f32,self.y1 as f32,cx,cy,self.x2 as f32,self.y2 as f32,func),
+            StrokeKind::Triangle(_,_,corners) => Painter::polygon_foreach(width,height,&corners,func),
+            StrokeKind::Polyline(ref points,_) => {
+                if let Some(path) = Path::polyline(points) {
+                    Painter::path_foreach(width,height,&path,1.0,LineJoin::Miter,func);
+                }
+            },
+            StrokeKind::Line | StrokeKind::Dot => Painter::line_foreach(width,height,self.x1 as i32,self.y1 as i32,self.x2 as i32,self.y2 as i32,func),
+        }
+    }
+}
+
+//The source image and its gradient. Styles work in canvas coordinates which are mapped onto the
+//source when sampling so the canvas can be larger or smaller than the source.
+pub struct Source {
+    pixels: Vec<u8>,
+    width: u32,
+    height: u32,
+    gradient: Vec<f32>,
+    canvas_width: u32,
+    canvas_height: u32,
+    //Level of the source's Gaussian pyramid the gradient is measured at and how it's measured.
+    level: f32,
+    gradient_options: GradientOptions,
+}
+
+impl Source {
+    //Prepare image for drawing onto a canvas_width by canvas_height canvas.
+    pub fn new(image: RgbImage,canvas_width: u32,canvas_height: u32) -> Result<Source> {
+        let (width,height) = image.dimensions();
+        let pixels = image.into_raw();
+
+        //Generate gradient for source image. It's used by the energy style to determine how far to
+        //shoot the rays. It's also used by the edgeweb style to detect edges in the image.
+        let gradient = gradient(&pixels,width,height)?;
+
+        Ok(Source {
+            pixels,
+            width,
+            height,
+            gradient,
+            canvas_width,
+            canvas_height,
+            level: 0.0,
+            gradient_options: GradientOptions::default(),
+        })
+    }
+
+    //Measure the gradient at a different level of detail. Styles that follow or stop at edges in the
+    //source all use this gradient, and the lengths they pick grow along with how much the source is
+    //shrunk so strokes cover the same part of the image at any resolution.
+    pub fn set_detail(&mut self,detail: Detail) -> Result<()> {
+        self.level = match detail {
+            Detail::Full => 0.0,
+            Detail::Size(size) => max_f32((std::cmp::max(self.width,self.height) as f32 / size).log2(),0.0),
+        };
+        self.gradient = multiscale_gradient_with(&self.pixels,self.width,self.height,self.level,&self.gradient_options)?;
+        Ok(())
+    }
+
+    //Measure the gradient with a different operator or from something other than Rec. 601 luminance.
+    pub fn set_gradient_options(&mut self,options: GradientOptions) -> Result<()> {
+        self.gradient_options = options;
+        self.gradient = multiscale_gradient_with(&self.pixels,self.width,self.height,self.level,&self.gradient_options)?;
+        Ok(())
+    }
+
+    //How many times smaller the source is shrunk to before measuring the gradient.
+    fn shrink(&self) -> f32 {
+        self.level.exp2()
+    }
+
+    //How much bigger the canvas is than the source.
+    fn canvas_scale(&self) -> f32 {
+        let scale_x = self.canvas_width as f32 / self.width as f32;
+        let scale_y = self.canvas_height as f32 / self.height as f32;
+        (scale_x * scale_y).sqrt()
+    }
+
+    //Distances picked by the styles are multiplied by this so the look doesn't change with the
+    //output size or, when the detail is set, with the source's size.
+    fn length_scale(&self) -> f32 {
+        self.canvas_scale() * self.shrink()
+    }
+
+    //Map a canvas coordinate to the matching continuous source coordinate. Pixel centers line up.
+    fn to_source(&self,x: f32,y: f32) -> (f32,f32) {
+        let x = (x + 0.5) * self.width as f32 / self.canvas_width as f32 - 0.5;
+        let y = (y + 0.5) * self.height as f32 / self.canvas_height as f32 - 0.5;
+        (max_f32(min_f32(x,self.width as f32 - 1.0),0.0),max_f32(min_f32(y,self.height as f32 - 1.0),0.0))
+    }
+
+    //Map the center of a source pixel to the canvas pixel underneath it.
+    fn to_canvas(&self,(x,y): (usize,usize)) -> (usize,usize) {
+        let x = ((x as f32 + 0.5) * self.canvas_width as f32 / self.width as f32) as usize;
+        let y = ((y as f32 + 0.5) * self.canvas_height as f32 / self.height as f32) as usize;
+        (std::cmp::min(x,self.canvas_width as usize - 1),std::cmp::min(y,self.canvas_height as usize - 1))
+    }
+
+    //Get the gradient magnitude and angle of the source pixel under a canvas pixel.
+    fn gradient_at(&self,x: usize,y: usize) -> (f32,f32) {
+        let (x,y) = self.to_source(x as f32,y as f32);
+        let index = (y.round() as usize * self.width as usize + x.round() as usize) * 2;
+        (self.gradient[index],self.gradient[index + 1])
+    }
+
+    //Find the region in labels, a label map at the source's resolution, under the canvas point x,y.
+    //Points off of the canvas aren't in any region.
+    fn region_at(&self,labels: &[u32],x: f32,y: f32) -> Option<u32> {
+        if !(x >= -0.5 && y >= -0.5 && x < self.canvas_width as f32 - 0.5 && y < self.canvas_height as f32 - 0.5) {
+            return None;
+        }
+        let (x,y) = self.to_source(x,y);
+        Some(labels[y.round() as usize * self.width as usize + x.round() as usize])
+    }
+
+    //Bilinearly interpolate the source color at a continuous source coordinate.
+    fn interpolate(&self,x: f32,y: f32) -> (f32,f32,f32) {
+        let width = self.width as usize;
+        let (x0,y0) = (x.floor() as usize,y.floor() as usize);
+        let x1 = std::cmp::min(x0 + 1,width - 1);
+        let y1 = std::cmp::min(y0 + 1,self.height as usize - 1);
+        let (tx,ty) = (x - x0 as f32,y - y0 as f32);
+
+        let value = |x,y,channel| self.pixels[(y * width + x) * 3 + channel] as f32;
+        let channel = |channel| {
+            let top = value(x0,y0,channel) * (1.0 - tx) + value(x1,y0,channel) * tx;
+            let bottom = value(x0,y1,channel) * (1.0 - tx) + value(x1,y1,channel) * tx;
+            top * (1.0 - ty) + bottom * ty
+        };
+        (channel(0),channel(1),channel(2))
+    }
+
+    //Get the source color under a canvas pixel.
+    fn pixel_at(&self,x: usize,y: usize) -> (u8,u8,u8) {
+        let (x,y) = self.to_source(x as f32,y as f32);
+        let (red,green,blue) = self.interpolate(x,y);
+        (red.round() as u8,green.round() as u8,blue.round() as u8)
+    }
+
+    //Sample a 3x3 region of the source under a canvas pixel and return the average color. Pixels
+    //off of the canvas are moved onto its edge by to_source so the center sample is always used.
+    fn color_at(&self,x: usize,y: usize) -> (u8,u8,u8) {
+        let (x,y) = self.to_source(x as f32,y as f32);
+        let (max_x,max_y) = (self.width as f32 - 1.0,self.height as f32 - 1.0);
+
+        let mut total = 0.0;
+        let (mut red_sum,mut green_sum,mut blue_sum) = (0.0,0.0,0.0);
+        for offset_y in -1..2 {
+            for offset_x in -1..2 {
+                let (sample_x,sample_y) = (x + offset_x as f32,y + offset_y as f32);
+                if sample_x < 0.0 || sample_y < 0.0 || sample_x > max_x || sample_y > max_y {
+                    continue;
+                }
+
+                let (red,green,blue) = self.interpolate(sample_x,sample_y);
+                red_sum += red;
+                green_sum += green;
+                blue_sum += blue;
+                total += 1.0;
+            }
+        }
+
+        ((red_sum / total) as u8,(green_sum / total) as u8,(blue_sum / total) as u8)
+    }
+}
+
+fn shift_color(rng: &mut ThreadRng,red: u8,green: u8,blue: u8) -> (u8,u8,u8) {
+    const STD_DEV: f64 = 10.0;
+
+    let (red,green,blue) = (red as f64,green as f64,blue as f64);
+    let (red,green,blue) = (Normal::new(red,STD_DEV).ind_sample(rng),
+                            Normal::new(green,STD_DEV).ind_sample(rng),
+                            Normal::new(blue,STD_DEV).ind_sample(rng));
+
+    (clamp_to_u8(red),clamp_to_u8(green),clamp_to_u8(blue))
+}
+
+fn shift_lightness(rng: &mut ThreadRng,red: u8,green: u8,blue: u8) -> (u8,u8,u8) {
+    const STD_DEV: f64 = 0.03;
+
+    let (h,s,l) = rgb_to_hsl(red,green,blue);
+    let l = Normal::new(l as f64,STD_DEV).ind_sample(rng) as f32;
+    let l = max_f32(min_f32(l,1.0),0.0);
+
+    hsl_to_rgb(h,s,l)
+}
+
+fn shift_hue(rng: &mut ThreadRng,red: u8,green: u8,blue: u8) -> (u8,u8,u8) {
+    const STD_DEV: f64 = 8.0;
+
+    let (h,s,l) = rgb_to_hsl(red,green,blue);
+    let mut h = (Normal::new(h as f64,STD_DEV).ind_sample(rng) as f32).rem(360.0);
+    if h < 0.0 {
+        h += 360.0;
+    }
+
+    hsl_to_rgb(h,s,l)
+}
+
+//Average the source color of every pixel the stroke passes through.
+fn line_average_color(source: &Source,stroke: &Stroke) -> (u8,u8,u8) {
+    let mut total = 0.0;
+    let (mut red_sum,mut green_sum,mut blue_sum) = (0.0,0.0,0.0);
+    stroke.foreach(source.canvas_width,source.canvas_height,|x,y| {
+        let (red,green,blue) = source.pixel_at(x,y);
+        red_sum += red as f32;
+        green_sum += green as f32;
+        blue_sum += blue as f32;
+        total += 1.0;
+    });
+
+    if total == 0.0 {
+        return source.color_at(stroke.xc,stroke.yc);
+    }
+    ((red_sum / total) as u8,(green_sum / total) as u8,(blue_sum / total) as u8)
+}
+
+//Pick the pen color for a stroke using the selected color mode.
+fn stroke_color(rng: &mut ThreadRng,color_mode: ColorMode,ink: (u8,u8,u8),source: &Source,stroke: &Stroke) -> (u8,u8,u8) {
+    match color_mode {
+        ColorMode::Default | ColorMode::EndpointMix | ColorMode::Gradient | ColorMode::Sampled => {
+            let (red1,green1,blue1) = source.color_at(stroke.x1,stroke.y1);
+            let (red2,green2,blue2) = source.color_at(stroke.x2,stroke.y2);
+            (mix(red1,red2),mix(green1,green2),mix(blue1,blue2))
+        },
+        ColorMode::Center => source.color_at(stroke.xc,stroke.yc),
+        ColorMode::RandomPoint => {
+            //Randomly pick a pixel between (inclusive) the two points to sample.
+            let (x,y) = stroke.point_at(Closed01::<f32>::rand(rng).0);
+            let (x,y) = (x.round() as i32,y.round() as i32);
+            let x = std::cmp::min(std::cmp::max(0,x),source.canvas_width as i32 - 1) as usize;
+            let y = std::cmp::min(std::cmp::max(0,y),source.canvas_height as i32 - 1) as usize;
+            source.color_at(x,y)
+        },
+        ColorMode::LineAverage => line_average_color(source,stroke),
+        ColorMode::RgbJitter => {
+            let (red,green,blue) = source.color_at(stroke.xc,stroke.yc);
+            shift_color(rng,red,green,blue)
+        },
+        ColorMode::LightnessJitter => {
+            let (red,green,blue) = source.color_at(stroke.xc,stroke.yc);
+            shift_lightness(rng,red,green,blue)
+        },
+        ColorMode::HueJitter => {
+            let (red,green,blue) = source.color_at(stroke.xc,stroke.yc);
+            shift_hue(rng,red,green,blue)
+        },
+        ColorMode::Level => {
+            //Polylines can know the luminance they follow. Anything else uses the Rec. 601
+            //luminance of the source under its center.
+            let level = match stroke.kind {
+                StrokeKind::Polyline(_,Some(level)) => level,
+                _ => {
+                    let (red,green,blue) = source.color_at(stroke.xc,stroke.yc);
+                    0.299 * red as f32 + 0.587 * green as f32 + 0.114 * blue as f32
+                },
+            };
+            let level = clamp_to_u8(level as f64);
+            (level,level,level)
+        },
+        ColorMode::Ink => ink,
+    }
+}
+
+//Color a stroke using the selected color mode and draw it to the work image. Returns the color
+//that was used so it can be logged.
+fn draw_stroke(painter: &mut Painter,rng: &mut ThreadRng,color_mode: ColorMode,ink: (u8,u8,u8),source: &Source,canvas: &mut dyn Canvas,stroke: &Stroke) -> StrokeColor {
+    let sample = |x,y| source.color_at(x,y);
+    let color = match color_mode {
+        ColorMode::Gradient | ColorMode::Sampled => {
+            let (red1,green1,blue1) = sample(stroke.x1,stroke.y1);
+            let (red2,green2,blue2) = sample(stroke.x2,stroke.y2);
+            painter.set_pen_gradient(red1,green1,blue1,red2,green2,blue2);
+            StrokeColor::Gradient((red1,green1,blue1),(red2,green2,blue2))
+        },
+        _ => {
+            let (red,green,blue) = stroke_color(rng,color_mode,ink,source,stroke);
+            painter.set_pen(red,green,blue);
+            StrokeColor::Solid(red,green,blue)
+        },
+    };
+
+    match (&stroke.kind,color_mode) {
+        (StrokeKind::Dot,_) => {
+            painter.dot(canvas,stroke.x1 as f32 + 0.5,stroke.y1 as f32 + 0.5);
+        },
+        (StrokeKind::Triangle(_,_,corners),ColorMode::Sampled) => {
+            painter.polygon_sampled(canvas,corners,sample);
+        },
+        (StrokeKind::Triangle(_,_,corners),_) => {
+            painter.polygon(canvas,corners);
+        },
+        (StrokeKind::Polyline(points,_),ColorMode::Sampled) => {
+            if let Some(path) = Path::polyline(points) {
+                painter.path_sampled(canvas,&path,sample);
+            }
+        },
+        (StrokeKind::Polyline(points,None),ColorMode::Level) => {
+            if let Some(path) = Path::polyline(points) {
+                painter.path_sampled(canvas,&path,|x,y| {
+                    let (red,green,blue) = sample(x,y);
+                    let level = clamp_to_u8(0.299 * red as f64 + 0.587 * green as f64 + 0.114 * blue as f64);
+                    (level,level,level)
+                });
+            }
+        },
+        (StrokeKind::Polyline(points,_),_) => {
+            painter.polyline(canvas,points);
+        },
+        (&StrokeKind::Curve(cx,cy),ColorMode::Sampled) => {
+            painter.quadratic_bezier_sampled(canvas,stroke.x1 as f32,stroke.y1 as f32,cx,cy,stroke.x2 as f32,stroke.y2 as f32,sample);
+        },
+        (&StrokeKind::Curve(cx,cy),_) => {
+            painter.quadratic_bezier(canvas,stroke.x1 as f32,stroke.y1 as f32,cx,cy,stroke.x2 as f32,stroke.y2 as f32);
+        },
+        (StrokeKind::Line,ColorMode::Sampled) => {
+            painter.line_sampled(canvas,stroke.x1 as i32,stroke.y1 as i32,stroke.x2 as i32,stroke.y2 as i32,sample);
+        },
+        (StrokeKind::Line,_) => {
+            painter.line(canvas,stroke.x1 as i32,stroke.y1 as i32,stroke.x2 as i32,stroke.y2 as i32);
+        },
+    }
+
+    color
+}
+
+fn random_line(rng: &mut ThreadRng,source: &Source) -> (usize,usize,usize,usize) {
+    const DISTANCE_MAX: f32 = 128.0;
+
+    let (width,height) = (source.canvas_width,source.canvas_height);
+
+    let x1 = rng.gen::<usize>() % width as usize;
+    let y1 = rng.gen::<usize>() % height as usize;
+
+    for attempt in 1.. {
+        let angle = Closed01::<f32>::rand(rng).0 * std::f32::consts::PI * 2.0;
+        let distance = Open01::<f32>::rand(rng).0 * DISTANCE_MAX * source.length_scale();
+
+        let x2 = (x1 as f32 + distance * angle.cos()) as usize;
+        let y2 = (y1 as f32 + distance * angle.sin()) as usize;
+
+        if (x2 < width as usize && y2 < height as usize) || attempt >= LINE_ATTEMPTS_MAX {
+            return (x1,y1,std::cmp::min(x2,width as usize - 1),std::cmp::min(y2,height as usize - 1));
+        }
+    }
+    unreachable!()
+}
+
+fn random_steered_line(rng: &mut ThreadRng,source: &Source) -> (usize,usize,usize,usize) {
+    const DISTANCE_MAX: f32 = 64.0;
+
+    let (width,height) = (source.canvas_width,source.canvas_height);
+
+    for attempt in 1.. {
+        let x1 = rng.gen::<usize>() % width as usize;
+        let y1 = rng.gen::<usize>() % height as usize;
+
+        let angle = source.gradient_at(x1,y1).1 + std::f32::consts::PI / 2.0;
+        let distance = Open01::<f32>::rand(rng).0 * DISTANCE_MAX * source.length_scale();
+
+        let x2 = (x1 as f32 + distance * angle.cos()) as usize;
+        let y2 = (y1 as f32 + distance * angle.sin()) as usize;
+
+        if (x2 < width as usize && y2 < height as usize) || attempt >= LINE_ATTEMPTS_MAX {
+            return (x1,y1,std::cmp::min(x2,width as usize - 1),std::cmp::min(y2,height as usize - 1));
+        }
+    }
+    unreachable!()
+}
+
+//Shoot a ray from the pixel at x,y toward angle (radians) with a random amount of energy. Crossing
+//strong gradients that run across the ray uses up its energy faster. Returns the last pixel reached
+//before the energy ran out.
+fn fire_ray(rng: &mut ThreadRng,source: &Source,x: usize,y: usize,angle: f32) -> (usize,usize) {
+    const ENERGY_MIN: f32 = 10.0;
+    const ENERGY_MAX: f32 = 80.0;
+    const ENERGY_DIFF: f32 = ENERGY_MAX - ENERGY_MIN;
+
+    let (width,height) = (source.canvas_width,source.canvas_height);
+    let mut energy = (Open01::<f32>::rand(rng).0 * ENERGY_DIFF + ENERGY_MIN) * source.length_scale();
+
+    let xe = (x as f32 + energy * angle.cos()) as i32;
+    let ye = (y as f32 + energy * angle.sin()) as i32;
+
+    let mut last_x = x;
+    let mut last_y = y;
+    Painter::line_foreach(width as usize,height as usize,x as i32,y as i32,xe,ye,|x,y| {
+        if energy >= 0.0 {
+            let (magnitude,theta) = source.gradient_at(x,y);
+            let mut dampening = 1.0 - difference_theta(angle,theta) / (std::f32::consts::PI);
+            dampening *= Open01::<f32>::rand(rng).0;
+            energy -= magnitude * dampening;
+            last_x = x;
+            last_y = y;
+        }
+    });
+
+    (last_x,last_y)
+}
+
+fn random_energy_line(rng: &mut ThreadRng,source: &Source) -> (usize,usize,usize,usize,usize,usize) {
+    let (width,height) = (source.canvas_width,source.canvas_height);
+
+    let xc = rng.gen::<usize>() % width as usize;
+    let yc = rng.gen::<usize>() % height as usize;
+    let angle = Closed01::<f32>::rand(rng).0 * std::f32::consts::PI * 2.0;
+
+    let (x1,y1) = fire_ray(rng,source,xc,yc,angle);
+    let (x2,y2) = fire_ray(rng,source,xc,yc,angle + std::f32::consts::PI);
+
+    (xc,yc,x1,y1,x2,y2)
+}
+
+//Like random_energy_line except the rays are fired along the line through the focal point give or
+//take a normally distributed jitter with a standard deviation of jitter radians.
+fn random_radial_line(rng: &mut ThreadRng,source: &Source,(focus_x,focus_y): (f32,f32),jitter: f32) -> Stroke {
+    let xc = rng.gen::<usize>() % source.canvas_width as usize;
+    let yc = rng.gen::<usize>() % source.canvas_height as usize;
+    let mut angle = (yc as f32 + 0.5 - focus_y).atan2(xc as f32 + 0.5 - focus_x);
+    if jitter > 0.0 {
+        angle += Normal::new(0.0,jitter as f64).ind_sample(rng) as f32;
+    }
+
+    let (x1,y1) = fire_ray(rng,source,xc,yc,wrap_angle(angle));
+    let (x2,y2) = fire_ray(rng,source,xc,yc,wrap_angle(angle + std::f32::consts::PI));
+    Stroke {
+        x1,
+        y1,
+        x2,
+        y2,
+        xc,
+        yc,
+        kind: StrokeKind::Line,
+    }
+}
+
+//Find how much to turn from heading (radians) so it lines up with the orientation field at x,y.
+//Lines flow perpendicular to the gradient and have no preferred direction, so the smallest turn
+//that lines up either way is used.
+fn flow_turn(source: &Source,x: usize,y: usize,heading: f32) -> f32 {
+    let flow = source.gradient_at(x,y).1 + std::f32::consts::PI / 2.0;
+
+    let turn = wrap_angle(flow - heading);
+    if turn > std::f32::consts::PI / 2.0 {
+        turn - std::f32::consts::PI
+    }
+    else if turn < -std::f32::consts::PI / 2.0 {
+        turn + std::f32::consts::PI
+    }
+    else {
+        turn
+    }
+}
+
+//Walk one pixel at a time from x,y while turning to follow the orientation field. bend is how much
+//of the turn toward the field is applied each step, 1.0 follows the field exactly. keep_going is
+//called with each new pixel and can end the walk early by returning false. Returns every pixel
+//visited including the starting one.
+fn trace_flow<F>(source: &Source,x: usize,y: usize,heading: f32,bend: f32,max_steps: usize,mut keep_going: F) -> Vec<(usize,usize)>
+    where F: FnMut(usize,usize,f32) -> bool {
+    let (width,height) = (source.canvas_width,source.canvas_height);
+    let mut path = vec![(x,y)];
+    let (mut position_x,mut position_y) = (x as f32 + 0.5,y as f32 + 0.5);
+    let mut heading = heading;
+    for _ in 0..max_steps {
+        let next_x = position_x + heading.cos();
+        let next_y = position_y + heading.sin();
+        if next_x < 0.0 || next_y < 0.0 || next_x >= width as f32 || next_y >= height as f32 {
+            break;
+        }
+
+        let (pixel_x,pixel_y) = (next_x as usize,next_y as usize);
+        if !keep_going(pixel_x,pixel_y,heading) {
+            break;
+        }
+
+        path.push((pixel_x,pixel_y));
+        position_x = next_x;
+        position_y = next_y;
+        heading += flow_turn(source,pixel_x,pixel_y,heading) * bend;
+    }
+
+    path
+}
+
+//Like random_steered_line except the line bends to follow the orientation field along its whole
+//length instead of only using the direction at the starting point.
+fn random_steered_curve(rng: &mut ThreadRng,source: &Source) -> Stroke {
+    const DISTANCE_MAX: f32 = 64.0;
+    const BEND: f32 = 0.5;
+
+    let x1 = rng.gen::<usize>() % source.canvas_width as usize;
+    let y1 = rng.gen::<usize>() % source.canvas_height as usize;
+
+    let heading = source.gradient_at(x1,y1).1 + std::f32::consts::PI / 2.0;
+    let distance = Open01::<f32>::rand(rng).0 * DISTANCE_MAX * source.length_scale();
+
+    let path = trace_flow(source,x1,y1,heading,BEND,distance as usize,|_,_,_| true);
+    let (xc,yc) = path[path.len() / 2];
+    let (x2,y2) = path[path.len() - 1];
+    Stroke::curve_through(x1,y1,xc,yc,x2,y2)
+}
+
+//Like random_energy_line except the rays bend to follow the orientation field as they travel.
+fn random_energy_curve(rng: &mut ThreadRng,source: &Source) -> Stroke {
+    const ENERGY_MIN: f32 = 10.0;
+    const ENERGY_MAX: f32 = 80.0;
+    const ENERGY_DIFF: f32 = ENERGY_MAX - ENERGY_MIN;
+    const BEND: f32 = 0.25;
+
+    let length_scale = source.length_scale();
+    let xc = rng.gen::<usize>() % source.canvas_width as usize;
+    let yc = rng.gen::<usize>() % source.canvas_height as usize;
+    let angle = Closed01::<f32>::rand(rng).0 * std::f32::consts::PI * 2.0;
+
+    let mut fire_ray = |angle: f32| -> (usize,usize) {
+        let mut energy = (Open01::<f32>::rand(rng).0 * ENERGY_DIFF + ENERGY_MIN) * length_scale;
+        let path = trace_flow(source,xc,yc,angle,BEND,(ENERGY_MAX * length_scale) as usize,|x,y,heading| {
+            if energy < 0.0 {
+                return false;
+            }
+
+            let (magnitude,theta) = source.gradient_at(x,y);
+            let mut dampening = 1.0 - difference_theta(wrap_angle(heading),theta) / (std::f32::consts::PI);
+            dampening *= Open01::<f32>::rand(rng).0;
+            energy -= magnitude * dampening;
+            true
+        });
+        path[path.len() - 1]
+    };
+
+    let (x1,y1) = fire_ray(angle);
+    let (x2,y2) = fire_ray(angle + std::f32::consts::PI);
+
+    Stroke::curve_through(x1,y1,xc,yc,x2,y2)
+}
+
+//Edges are found and matched up at the source's resolution and then mapped onto the canvas.
+fn edge_web<F,D>(rng: &mut ThreadRng,source: &Source,progress: &mut F,draw: &mut D) -> Result<RenderStatus>
+    where F: FnMut(Progress) -> bool,
+          D: FnMut(&mut ThreadRng,&Stroke) -> Result<()> {
+    const DISTANCE_MIN: f32 = 4.0;
+    const DISTANCE_MAX: f32 = 50.0;
+
+    let gradient = source.gradient.as_slice();
+    let edge_pixels = non_maximum_suppression(gradient,source.width,source.height)?;
+
+    let width = source.width as usize;
+    let height = source.height as usize;
+
+    //Find all edge points. When the gradient is measured on a shrunk source only the first point in
+    //each block of pixels that shrinks down to one is kept so the web is as dense as it would be at
+    //that size.
+    let block = std::cmp::max(source.shrink().round() as usize,1);
+    let blocks_across = width.div_ceil(block);
+    let mut blocks_taken = vec![false; blocks_across * height.div_ceil(block)];
+    let mut edge_points = vec![];
+    for y in 0..height {
+        for x in 0..width {
+            let block_index = (y / block) * blocks_across + x / block;
+            if edge_pixels[y * width + x] == 255 && !blocks_taken[block_index] {
+                blocks_taken[block_index] = true;
+                edge_points.push((x,y));
+            }
+        }
+    }
+    let (distance_min,distance_max) = (DISTANCE_MIN * source.shrink(),DISTANCE_MAX * source.shrink());
+
+    let lines_per_point = 1;//TODO: Maybe support variable lines again? 1000000 / edge_points.len();
+
+    //Draw a line between points that are between DISTANCE_MIN and DISTANCE_MAX of each other and
+    //have the same relative orientation. Only the closest lines_per_point lines are drawn.
+    //TODO: Use a quadtree to speed up this part.
+    let mut other_points = vec![];
+    let total = edge_points.len() as u64;
+    for y in 0..edge_points.len() {
+        let completed = y as u64;
+        if completed.is_multiple_of(EDGE_PROGRESS_INTERVAL) && !progress(Progress { completed,total }) {
+            return Ok(RenderStatus::Cancelled(Progress { completed,total }));
+        }
+
+        let first_point = edge_points[y];
+        let direction_at = |(x,y): (usize,usize)| angle_to_direction(gradient[(y * width + x) * 2 + 1]);
+        let first_direction = direction_at(first_point);
+
+        //Find potential matching points.
+        other_points.clear();
+        for &second_point in &edge_points[y + 1..] {
+            let second_direction = direction_at(second_point);
+
+            let diff_x = second_point.0 as f32 - first_point.0 as f32;
+            let diff_y = second_point.1 as f32 - first_point.1 as f32;
+            let distance = diff_x.hypot(diff_y);
+
+            if distance >= distance_min && distance <= distance_max && first_direction == second_direction {
+                other_points.push((second_point,distance));
+            }
+        }
+
+        //Sort by distance.
+        other_points.sort_by(|lhs,rhs| lhs.1.partial_cmp(&rhs.1).unwrap_or(std::cmp::Ordering::Equal));
+        if other_points.len() > lines_per_point {
+            other_points.drain(lines_per_point..);
+        }
+
+        for &(second_point,_) in &other_points {
+            let ((x1,y1),(x2,y2)) = (source.to_canvas(first_point),source.to_canvas(second_point));
+            draw(rng,&Stroke::new(x1,y1,x2,y2))?;
+        }
+    }
+
+    progress(Progress { completed: total,total });
+    Ok(RenderStatus::Finished)
+}
+
+//Dots are placed and relaxed over the source's darkness and then mapped onto the canvas. If the
+//render is cancelled while the dots are being relaxed, they're drawn wherever they ended up.
+fn stipple_dots<F,D>(rng: &mut ThreadRng,source: &Source,dot_count: usize,tsp: bool,progress: &mut F,draw: &mut D) -> Result<RenderStatus>
+    where F: FnMut(Progress) -> bool,
+          D: FnMut(&mut ThreadRng,&Stroke) -> Result<()> {
+    let luminance = luminance(&source.pixels,source.width,source.height)?;
+
+    //Shrink big sources by averaging blocks of pixels.
+    let (source_width,source_height) = (source.width as usize,source.height as usize);
+    let shrink = ((source_width * source_height) as f32 / STIPPLE_DENSITY_PIXELS_MAX as f32).sqrt().ceil().max(1.0) as usize;
+    let (width,height) = (source_width.div_ceil(shrink),source_height.div_ceil(shrink));
+    let mut density = vec![0.0; width * height];
+    let mut counts = vec![0.0; width * height];
+    for y in 0..source_height {
+        for x in 0..source_width {
+            let index = (y / shrink) * width + x / shrink;
+            density[index] += 1.0 - luminance[y * source_width + x] / 255.0;
+            counts[index] += 1.0;
+        }
+    }
+    for (value,count) in density.iter_mut().zip(counts.iter()) {
+        *value /= *count;
+    }
+
+    //Relaxing counts for one unit of work per dot per iteration and drawing for one more.
+    let total = (dot_count * (STIPPLE_ITERATIONS + 1)) as u64;
+    let mut cancelled = None;
+    let points = stipple(&density,width,height,dot_count,STIPPLE_ITERATIONS,rng,|iteration| {
+        let completed = (iteration * dot_count) as u64;
+        if !progress(Progress { completed,total }) {
+            cancelled = Some(Progress { completed,total });
+            return false;
+        }
+        true
+    })?;
+
+    let to_canvas = |(x,y): (f32,f32)| {
+        let x = (x * source.canvas_width as f32 / width as f32) as usize;
+        let y = (y * source.canvas_height as f32 / height as f32) as usize;
+        (std::cmp::min(x,source.canvas_width as usize - 1),std::cmp::min(y,source.canvas_height as usize - 1))
+    };
+    let points: Vec<(usize,usize)> = points.into_iter().map(to_canvas).collect();
+    let base = (dot_count * STIPPLE_ITERATIONS) as u64;
+    if tsp {
+        let path = tsp_path(&points.iter().map(|&(x,y)| (x as f32,y as f32)).collect::<Vec<_>>());
+        for (index,pair) in path.windows(2).enumerate() {
+            let ((x1,y1),(x2,y2)) = (points[pair[0]],points[pair[1]]);
+            draw(rng,&Stroke::new(x1,y1,x2,y2))?;
+            if cancelled.is_none() && (index as u64).is_multiple_of(PROGRESS_INTERVAL) && !progress(Progress { completed: base + index as u64,total }) {
+                return Ok(RenderStatus::Cancelled(Progress { completed: base + index as u64,total }));
+            }
+        }
+    }
+    else {
+        for (index,&(x,y)) in points.iter().enumerate() {
+            draw(rng,&Stroke::dot(x,y))?;
+            if cancelled.is_none() && (index as u64).is_multiple_of(PROGRESS_INTERVAL) && !progress(Progress { completed: base + index as u64,total }) {
+                return Ok(RenderStatus::Cancelled(Progress { completed: base + index as u64,total }));
+            }
+        }
+    }
+
+    if let Some(cancelled) = cancelled {
+        return Ok(RenderStatus::Cancelled(cancelled));
+    }
+    progress(Progress { completed: total,total });
+    Ok(RenderStatus::Finished)
+}
+
+//Points are picked at the source's resolution, some on edges and the rest anywhere, and then mapped
+//onto the canvas and triangulated. The canvas corners are always included so the triangles cover
+//all of it.
+fn mesh<F,D>(rng: &mut ThreadRng,source: &Source,point_count: usize,edge_weight: f32,low_poly: bool,progress: &mut F,draw: &mut D) -> Result<RenderStatus>
+    where F: FnMut(Progress) -> bool,
+          D: FnMut(&mut ThreadRng,&Stroke) -> Result<()> {
+    let edge_pixels = non_maximum_suppression(&source.gradient,source.width,source.height)?;
+    let width = source.width as usize;
+    let mut edge_points: Vec<usize> = (0..edge_pixels.len()).filter(|&index| edge_pixels[index] == 255).collect();
+
+    //Randomly pick edge points without picking any twice.
+    let edge_count = std::cmp::min((point_count as f32 * edge_weight).round() as usize,edge_points.len());
+    for index in 0..edge_count {
+        let other = rng.gen_range(index,edge_points.len());
+        edge_points.swap(index,other);
+    }
+    let mut points: Vec<(usize,usize)> = edge_points[..edge_count].iter().map(|&index| source.to_canvas((index % width,index / width))).collect();
+    for _ in edge_count..point_count {
+        let x = rng.gen::<usize>() % width;
+        let y = rng.gen::<usize>() % source.height as usize;
+        points.push(source.to_canvas((x,y)));
+    }
+    let (right,bottom) = (source.canvas_width as usize - 1,source.canvas_height as usize - 1);
+    points.extend_from_slice(&[(0,0),(right,0),(0,bottom),(right,bottom)]);
+    points.sort();
+    points.dedup();
+
+    let triangles = triangulate(&points.iter().map(|&(x,y)| (x as f32,y as f32)).collect::<Vec<_>>());
+    let strokes: Vec<Stroke> = if low_poly {
+        triangles.iter().map(|&[a,b,c]| Stroke::triangle(points[a],points[b],points[c],right + 1,bottom + 1)).collect()
+    }
+    else {
+        //Neighboring triangles share edges so only draw each one once.
+        let mut edges: Vec<(usize,usize)> = triangles.iter().flat_map(|&[a,b,c]| vec![(a,b),(b,c),(c,a)]).map(|(a,b)| (std::cmp::min(a,b),std::cmp::max(a,b))).collect();
+        edges.sort();
+        edges.dedup();
+        edges.iter().map(|&(a,b)| Stroke::new(points[a].0,points[a].1,points[b].0,points[b].1)).collect()
+    };
+
+    let total = strokes.len() as u64;
+    for (completed,stroke) in strokes.iter().enumerate() {
+        let completed = completed as u64;
+        if completed.is_multiple_of(PROGRESS_INTERVAL) && !progress(Progress { completed,total }) {
+            return Ok(RenderStatus::Cancelled(Progress { completed,total }));
+        }
+        draw(rng,stroke)?;
+    }
+
+    progress(Progress { completed: total,total });
+    Ok(RenderStatus::Finished)
+}
+
+//Contours are traced over the source's smoothed luminance and then mapped onto the canvas. Each
+//level counts as one unit of progress.
+fn contours<F,D>(rng: &mut ThreadRng,source: &Source,level_count: usize,spacing: LevelSpacing,progress: &mut F,draw: &mut D) -> Result<RenderStatus>
+    where F: FnMut(Progress) -> bool,
+          D: FnMut(&mut ThreadRng,&Stroke) -> Result<()> {
+    let (width,height) = (source.width as usize,source.height as usize);
+    let luminance = luminance(&source.pixels,source.width,source.height)?;
+    let sigma = max_f32(1.0,std::cmp::max(width,height) as f32 * CONTOUR_SMOOTHING);
+    let smoothed = gaussian_blur(&luminance,source.width,source.height,sigma)?;
+    let levels = contour_levels(&smoothed,level_count,spacing);
+
+    let scale_x = source.canvas_width as f32 / width as f32;
+    let scale_y = source.canvas_height as f32 / height as f32;
+    let total = levels.len() as u64;
+    for (completed,&level) in levels.iter().enumerate() {
+        let completed = completed as u64;
+        if !progress(Progress { completed,total }) {
+            return Ok(RenderStatus::Cancelled(Progress { completed,total }));
+        }
+
+        for line in isolines(&smoothed,width,height,level) {
+            let length: f32 = line.windows(2).map(|pair| (pair[1].0 - pair[0].0).hypot(pair[1].1 - pair[0].1)).sum();
+            if length < CONTOUR_LENGTH_MIN {
+                continue;
+            }
+
+            let points = line.iter().map(|&(x,y)| ((x + 0.5) * scale_x,(y + 0.5) * scale_y)).collect();
+            draw(rng,&Stroke::polyline(points,Some(level),source.canvas_width as usize,source.canvas_height as usize))?;
+        }
+    }
+
+    progress(Progress { completed: total,total });
+    Ok(RenderStatus::Finished)
+}
+
+//Straight lines are found at the source's resolution and then mapped onto the canvas. Extended lines
+//run all the way across the canvas instead of stopping at the ends of the edge they were found on.
+fn hough<F,D>(rng: &mut ThreadRng,source: &Source,line_count: usize,extend: bool,progress: &mut F,draw: &mut D) -> Result<RenderStatus>
+    where F: FnMut(Progress) -> bool,
+          D: FnMut(&mut ThreadRng,&Stroke) -> Result<()> {
+    let edges = non_maximum_suppression(&source.gradient,source.width,source.height)?;
+    let longer = std::cmp::max(source.width,source.height) as f32;
+    let segments = hough_lines(&edges,source.width,source.height,line_count,(longer * HOUGH_LENGTH_MIN) as usize,HOUGH_GAP_MAX)?;
+
+    let (canvas_width,canvas_height) = (source.canvas_width as usize,source.canvas_height as usize);
+    let scale_x = canvas_width as f32 / source.width as f32;
+    let scale_y = canvas_height as f32 / source.height as f32;
+    let pixel = |(x,y): (f32,f32)| (std::cmp::min(max_f32(x,0.0) as usize,canvas_width - 1),std::cmp::min(max_f32(y,0.0) as usize,canvas_height - 1));
+    let total = segments.len() as u64;
+    for (completed,segment) in segments.iter().enumerate() {
+        let completed = completed as u64;
+        if !progress(Progress { completed,total }) {
+            return Ok(RenderStatus::Cancelled(Progress { completed,total }));
+        }
+
+        let mut start = ((segment.x1 + 0.5) * scale_x,(segment.y1 + 0.5) * scale_y);
+        let mut end = ((segment.x2 + 0.5) * scale_x,(segment.y2 + 0.5) * scale_y);
+        let length = (end.0 - start.0).hypot(end.1 - start.1);
+        if extend && length > 0.0 {
+            let direction = ((end.0 - start.0) / length,(end.1 - start.1) / length);
+            if let Some((t1,t2)) = line_through_canvas(source,start,direction) {
+                end = (start.0 + direction.0 * t2,start.1 + direction.1 * t2);
+                start = (start.0 + direction.0 * t1,start.1 + direction.1 * t1);
+            }
+        }
+
+        let ((x1,y1),(x2,y2)) = (pixel(start),pixel(end));
+        draw(rng,&Stroke::new(x1,y1,x2,y2))?;
+    }
+
+    progress(Progress { completed: total,total });
+    Ok(RenderStatus::Finished)
+}
+
+//Edges are linked into chains at the source's resolution, simplified, and then mapped onto the
+//canvas where they're drawn as smooth curves. Chains shorter than length_min source pixels are
+//skipped.
+fn outline<F,D>(rng: &mut ThreadRng,source: &Source,length_min: usize,progress: &mut F,draw: &mut D) -> Result<RenderStatus>
+    where F: FnMut(Progress) -> bool,
+          D: FnMut(&mut ThreadRng,&Stroke) -> Result<()> {
+    let edges = non_maximum_suppression(&source.gradient,source.width,source.height)?;
+    smooth_chains(rng,source,&edges,length_min,progress,draw)
+}
+
+//The source is split into superpixels and the borders between them are drawn the same way as the
+//outline style draws edges.
+fn regions<F,D>(rng: &mut ThreadRng,source: &Source,region_count: usize,compactness: f32,progress: &mut F,draw: &mut D) -> Result<RenderStatus>
+    where F: FnMut(Progress) -> bool,
+          D: FnMut(&mut ThreadRng,&Stroke) -> Result<()> {
+    let segmentation = slic(&source.pixels,source.width,source.height,region_count,compactness)?;
+    let boundaries = region_boundaries(&segmentation.labels,source.width,source.height)?;
+    smooth_chains(rng,source,&boundaries,REGION_LENGTH_MIN,progress,draw)
+}
+
+//Link the pixels of an edge map at the source's resolution into chains and draw each one as a
+//smooth curve on the canvas.
+fn smooth_chains<F,D>(rng: &mut ThreadRng,source: &Source,edges: &[u8],length_min: usize,progress: &mut F,draw: &mut D) -> Result<RenderStatus>
+    where F: FnMut(Progress) -> bool,
+          D: FnMut(&mut ThreadRng,&Stroke) -> Result<()> {
+    let chains = edge_chains(edges,source.width,source.height,length_min,OUTLINE_TOLERANCE)?;
+
+    let (canvas_width,canvas_height) = (source.canvas_width as usize,source.canvas_height as usize);
+    let scale_x = canvas_width as f32 / source.width as f32;
+    let scale_y = canvas_height as f32 / source.height as f32;
+    let total = chains.len() as u64;
+    for (completed,chain) in chains.iter().enumerate() {
+        let completed = completed as u64;
+        if completed.is_multiple_of(PROGRESS_INTERVAL) && !progress(Progress { completed,total }) {
+            return Ok(RenderStatus::Cancelled(Progress { completed,total }));
+        }
+
+        let points: Vec<(f32,f32)> = chain.iter().map(|&(x,y)| ((x + 0.5) * scale_x,(y + 0.5) * scale_y)).collect();
+        let points = match Path::smooth(&points) {
+            Some(path) => path.flatten(),
+            None => continue,
+        };
+
+        //Shade by the source under the middle of the chain.
+        let (x,y) = chain[chain.len() / 2];
+        let (red,green,blue) = source.interpolate(x,y);
+        let level = 0.299 * red + 0.587 * green + 0.114 * blue;
+        draw(rng,&Stroke::polyline(points,Some(level),canvas_width,canvas_height))?;
+    }
+
+    progress(Progress { completed: total,total });
+    Ok(RenderStatus::Finished)
+}
+
+//Follows a path point by point while swinging out to either side of it. How far it swings, and
+//how tightly it waves if the wavelengths differ, grows with the darkness of the smoothed source
+//underneath. Parts of the path off of the canvas are left out which splits it into separate runs.
+struct Wobble<'a> {
+    source: &'a Source,
+    darkness: Vec<f32>,
+    amplitude: f32,
+    wavelengths: (f32,f32),
+    phase: f32,
+    last: Option<(f32,f32)>,
+    run: Vec<(f32,f32)>,
+    runs: Vec<Vec<(f32,f32)>>,
+}
+
+impl<'a> Wobble<'a> {
+    //amplitude is how far to swing in canvas pixels where the source is black. wavelengths are the
+    //length of one wave where the source is white and where it's black. The source is smoothed over
+    //about spacing canvas pixels so the wobble follows the tone of a whole turn or line instead of
+    //every little detail.
+    fn new(source: &'a Source,spacing: f32,amplitude: f32,wavelengths: (f32,f32)) -> Result<Wobble<'a>> {
+        let luminance = luminance(&source.pixels,source.width,source.height)?;
+        let sigma = spacing / 2.0 / source.canvas_scale();
+        let smoothed = gaussian_blur(&luminance,source.width,source.height,sigma)?;
+        Ok(Wobble {
+            source,
+            darkness: smoothed.iter().map(|value| 1.0 - value / 255.0).collect(),
+            amplitude,
+            wavelengths,
+            phase: 0.0,
+            last: None,
+            run: vec![],
+            runs: vec![],
+        })
+    }
+
+    //Darkness of the smoothed source from 0.0 (white) to 1.0 (black) under a point measured from
+    //the top left of the canvas.
+    fn darkness_at(&self,x: f32,y: f32) -> f32 {
+        let (x,y) = self.source.to_source(x - 0.5,y - 0.5);
+        let width = self.source.width as usize;
+        let (x0,y0) = (x.floor() as usize,y.floor() as usize);
+        let x1 = std::cmp::min(x0 + 1,width - 1);
+        let y1 = std::cmp::min(y0 + 1,self.source.height as usize - 1);
+        let (tx,ty) = (x - x0 as f32,y - y0 as f32);
+
+        let value = |x,y| self.darkness[y * width + x];
+        let top = value(x0,y0) * (1.0 - tx) + value(x1,y0) * tx;
+        let bottom = value(x0,y1) * (1.0 - tx) + value(x1,y1) * tx;
+        top * (1.0 - ty) + bottom * ty
+    }
+
+    //Visit the next point x,y along the path where normal points out to its side. Returns how far
+    //along the path to place the next point so the wave stays smooth.
+    fn visit(&mut self,(x,y): (f32,f32),(normal_x,normal_y): (f32,f32)) -> f32 {
+        const BORDER: f32 = 0.01;
+
+        let (width,height) = (self.source.canvas_width as f32,self.source.canvas_height as f32);
+        if x < -BORDER || y < -BORDER || x > width + BORDER || y > height + BORDER {
+            self.end_run();
+            self.last = None;
+            return WOBBLE_STEP_MAX;
+        }
+
+        let darkness = self.darkness_at(x,y);
+        let wavelength = self.wavelengths.0 + (self.wavelengths.1 - self.wavelengths.0) * darkness;
+        if let Some((last_x,last_y)) = self.last {
+            self.phase += (x - last_x).hypot(y - last_y) / wavelength * std::f32::consts::PI * 2.0;
+        }
+        self.last = Some((x,y));
+
+        //Points swinging off of the canvas are pulled back onto its edge.
+        let offset = self.amplitude * darkness * self.phase.sin();
+        let x = max_f32(min_f32(x + normal_x * offset,width),0.0);
+        let y = max_f32(min_f32(y + normal_y * offset,height),0.0);
+        self.run.push((x,y));
+        min_f32(WOBBLE_STEP_MAX,wavelength / WOBBLE_STEPS)
+    }
+
+    fn end_run(&mut self) {
+        if self.run.len() >= 2 {
+            self.runs.push(std::mem::take(&mut self.run));
+        }
+        self.run.clear();
+    }
+
+    //Draw every run as one polyline so its pixels are only drawn once and its corners are joined.
+    //Each run counts as one unit of progress.
+    fn draw<F,D>(mut self,rng: &mut ThreadRng,progress: &mut F,draw: &mut D) -> Result<RenderStatus>
+        where F: FnMut(Progress) -> bool,
+              D: FnMut(&mut ThreadRng,&Stroke) -> Result<()> {
+        self.end_run();
+
+        let (width,height) = (self.source.canvas_width as usize,self.source.canvas_height as usize);
+        let total = self.runs.len() as u64;
+        for (completed,run) in self.runs.into_iter().enumerate() {
+            let completed = completed as u64;
+            if !progress(Progress { completed,total }) {
+                return Ok(RenderStatus::Cancelled(Progress { completed,total }));
+            }
+            draw(rng,&Stroke::polyline(run,None,width,height))?;
+        }
+
+        progress(Progress { completed: total,total });
+        Ok(RenderStatus::Finished)
+    }
+}
+
+fn check_wobble(spacing: f32,amplitude: f32) -> Result<()> {
+    if !(spacing >= 1.0 && spacing.is_finite()) {
+        return Err(Error::InvalidParameter(format!("Spacing must be at least 1 pixel but is {}",spacing)));
+    }
+    if !(amplitude >= 0.0 && amplitude.is_finite()) {
+        return Err(Error::InvalidParameter(format!("Amplitude cannot be negative but is {}",amplitude)));
+    }
+    Ok(())
+}
+
+//One Archimedean spiral from the center of the canvas out past its corners with spacing canvas
+//pixels between turns.
+fn spiral<F,D>(rng: &mut ThreadRng,source: &Source,spacing: f32,amplitude: f32,progress: &mut F,draw: &mut D) -> Result<RenderStatus>
+    where F: FnMut(Progress) -> bool,
+          D: FnMut(&mut ThreadRng,&Stroke) -> Result<()> {
+    check_wobble(spacing,amplitude)?;
+    let mut wobble = Wobble::new(source,spacing,amplitude,(spacing * SPIRAL_WAVELENGTH_LIGHT,spacing * SPIRAL_WAVELENGTH_DARK))?;
+
+    //The angle is kept in double precision because it grows too large for single precision steps
+    //to move it on big canvases.
+    let (center_x,center_y) = (source.canvas_width as f64 / 2.0,source.canvas_height as f64 / 2.0);
+    let radius_max = center_x.hypot(center_y);
+    let spacing = spacing as f64;
+    let mut angle: f64 = 0.0;
+    loop {
+        let radius = spacing * angle / (std::f64::consts::PI * 2.0);
+        if radius > radius_max {
+            break;
+        }
+
+        let (sin,cos) = angle.sin_cos();
+        let step = wobble.visit(((center_x + radius * cos) as f32,(center_y + radius * sin) as f32),(cos as f32,sin as f32));
+        angle += step as f64 / radius.max(spacing);
+    }
+
+    wobble.draw(rng,progress,draw)
+}
+
+//Find where the line through origin heading in direction enters and leaves the canvas. Returns how
+//many directions along the line from origin each one is, or None if the line misses the canvas.
+fn line_through_canvas(source: &Source,(origin_x,origin_y): (f32,f32),(direction_x,direction_y): (f32,f32)) -> Option<(f32,f32)> {
+    let (mut start,mut end) = (f32::NEG_INFINITY,f32::INFINITY);
+    for &(origin,direction,size) in &[(origin_x,direction_x,source.canvas_width as f32),(origin_y,direction_y,source.canvas_height as f32)] {
+        if direction.abs() < 1.0e-6 {
+            if origin < 0.0 || origin > size {
+                return None;
+            }
+            continue;
+        }
+        let (t1,t2) = (-origin / direction,(size - origin) / direction);
+        start = max_f32(start,min_f32(t1,t2));
+        end = min_f32(end,max_f32(t1,t2));
+    }
+    if start >= end {
+        return None;
+    }
+    Some((start,end))
+}
+
+//Parallel lines spacing canvas pixels apart at angle radians clockwise from horizontal. Every other
+//line runs backward and each one is joined to the next so they make up one path.
+fn squiggle<F,D>(rng: &mut ThreadRng,source: &Source,spacing: f32,amplitude: f32,angle: f32,progress: &mut F,draw: &mut D) -> Result<RenderStatus>
+    where F: FnMut(Progress) -> bool,
+          D: FnMut(&mut ThreadRng,&Stroke) -> Result<()> {
+    check_wobble(spacing,amplitude)?;
+    let mut wobble = Wobble::new(source,spacing,amplitude,(spacing * SQUIGGLE_WAVELENGTH,spacing * SQUIGGLE_WAVELENGTH))?;
+
+    //Spread the lines evenly across the canvas as seen along the normal.
+    let (width,height) = (source.canvas_width as f32,source.canvas_height as f32);
+    let (direction_x,direction_y) = (angle.cos(),angle.sin());
+    let normal = (-direction_y,direction_x);
+    let offsets: Vec<f32> = [(0.0,0.0),(width,0.0),(0.0,height),(width,height)].iter().map(|&(x,y)| x * normal.0 + y * normal.1).collect();
+    let low = offsets.iter().cloned().fold(f32::INFINITY,min_f32);
+    let high = offsets.iter().cloned().fold(f32::NEG_INFINITY,max_f32);
+    let line_count = max_f32(((high - low) / spacing).ceil(),1.0) as usize;
+    let first = low + (high - low - (line_count - 1) as f32 * spacing) / 2.0;
+
+    for line in 0..line_count {
+        let offset = first + line as f32 * spacing;
+        let (origin_x,origin_y) = (normal.0 * offset,normal.1 * offset);
+
+        let (start,end) = match line_through_canvas(source,(origin_x,origin_y),(direction_x,direction_y)) {
+            Some(range) => range,
+            None => continue,
+        };
+
+        let length = end - start;
+        let mut distance = 0.0;
+        loop {
+            let t = if line.is_multiple_of(2) { start + distance } else { end - distance };
+            let step = wobble.visit((origin_x + direction_x * t,origin_y + direction_y * t),normal);
+            if distance >= length {
+                break;
+            }
+            distance = min_f32(distance + step,length);
+        }
+    }
+
+    wobble.draw(rng,progress,draw)
+}
+
+//Find where focus is on the canvas measured from its top left corner.
+fn focal_point(source: &Source,focus: Focus) -> Result<(f32,f32)> {
+    let (canvas_width,canvas_height) = (source.canvas_width as f32,source.canvas_height as f32);
+    match focus {
+        Focus::Center => Ok((canvas_width / 2.0,canvas_height / 2.0)),
+        Focus::Point(x,y) => Ok((x * canvas_width / source.width as f32,y * canvas_height / source.height as f32)),
+        Focus::Salient => {
+            let magnitudes: Vec<f32> = source.gradient.iter().step_by(2).cloned().collect();
+            let sigma = max_f32(1.0,std::cmp::max(source.width,source.height) as f32 * SALIENCE_SMOOTHING);
+            let smoothed = gaussian_blur(&magnitudes,source.width,source.height,sigma)?;
+            let mut busiest = 0;
+            for (index,&value) in smoothed.iter().enumerate() {
+                if value > smoothed[busiest] {
+                    busiest = index;
+                }
+            }
+
+            let width = source.width as usize;
+            let (x,y) = source.to_canvas((busiest % width,busiest / width));
+            Ok((x as f32 + 0.5,y as f32 + 0.5))
+        },
+    }
+}
+
+//Draws a style onto a canvas. Settings start out matching the command line defaults.
+pub struct Renderer {
+    style: Style,
+    color_mode: ColorMode,
+    ink: (u8,u8,u8),
+    line_count: u64,
+    pen_width: f32,
+    pen_opacity: f32,
+    curved: bool,
+    dot_count: usize,
+    tsp: bool,
+    mesh_points: usize,
+    edge_weight: f32,
+    low_poly: bool,
+    contour_levels: usize,
+    level_spacing: LevelSpacing,
+    spacing: f32,
+    amplitude: f32,
+    squiggle_angle: f32,
+    focus: Focus,
+    radial_jitter: f32,
+    hough_lines: usize,
+    extend_lines: bool,
+    outline_length: usize,
+    region_count: usize,
+    compactness: f32,
+    clip_to_regions: bool,
+    layer: u16,
+}
+
+impl Renderer {
+    pub fn new(style: Style) -> Renderer {
+        Renderer {
+            style,
+            color_mode: ColorMode::Default,
+            ink: (0,0,0),
+            line_count: 1000000,
+            pen_width: 1.0,
+            pen_opacity: 1.0,
+            curved: false,
+            dot_count: 20000,
+            tsp: false,
+            mesh_points: 4000,
+            edge_weight: 0.5,
+            low_poly: false,
+            contour_levels: 16,
+            level_spacing: LevelSpacing::Linear,
+            spacing: 6.0,
+            amplitude: 3.0,
+            squiggle_angle: 0.0,
+            focus: Focus::Center,
+            radial_jitter: 3.0f32.to_radians(),
+            hough_lines: 200,
+            extend_lines: false,
+            outline_length: 20,
+            region_count: 400,
+            compactness: 10.0,
+            clip_to_regions: false,
+            layer: 0,
+        }
+    }
+
+    pub fn set_color_mode(&mut self,color_mode: ColorMode) {
+        self.color_mode = color_mode;
+    }
+
+    //Color used by the ink color mode.
+    pub fn set_ink(&mut self,red: u8,green: u8,blue: u8) {
+        self.ink = (red,green,blue);
+    }
+
+    //Number of lines to draw. Ignored by the edgeweb style which draws one line per edge point.
+    pub fn set_line_count(&mut self,line_count: u64) {
+        self.line_count = line_count;
+    }
+
+    //Pen width in canvas pixels.
+    pub fn set_pen_width(&mut self,pen_width: f32) {
+        self.pen_width = pen_width;
+    }
+
+    pub fn set_pen_opacity(&mut self,pen_opacity: f32) {
+        self.pen_opacity = pen_opacity;
+    }
+
+    //Bend lines to follow the image in the steered and energy styles.
+    pub fn set_curved(&mut self,curved: bool) {
+        self.curved = curved;
+    }
+
+    //Number of dots placed by the stipple style.
+    pub fn set_dot_count(&mut self,dot_count: usize) {
+        self.dot_count = dot_count;
+    }
+
+    //Join the stipple style's dots into a single continuous line instead of drawing them.
+    pub fn set_tsp(&mut self,tsp: bool) {
+        self.tsp = tsp;
+    }
+
+    //Number of points the mesh style connects into triangles.
+    pub fn set_mesh_points(&mut self,mesh_points: usize) {
+        self.mesh_points = mesh_points;
+    }
+
+    //Fraction of the mesh style's points from 0.0 to 1.0 that are placed on edges in the image. The
+    //rest are spread randomly.
+    pub fn set_edge_weight(&mut self,edge_weight: f32) {
+        self.edge_weight = edge_weight;
+    }
+
+    //Fill the mesh style's triangles instead of drawing their edges.
+    pub fn set_low_poly(&mut self,low_poly: bool) {
+        self.low_poly = low_poly;
+    }
+
+    //Number of luminance levels the contour style traces.
+    pub fn set_contour_levels(&mut self,contour_levels: usize) {
+        self.contour_levels = contour_levels;
+    }
+
+    pub fn set_level_spacing(&mut self,level_spacing: LevelSpacing) {
+        self.level_spacing = level_spacing;
+    }
+
+    //Distance in canvas pixels between the turns of the spiral style and the lines of the squiggle
+    //style. Must be at least 1.
+    pub fn set_spacing(&mut self,spacing: f32) {
+        self.spacing = spacing;
+    }
+
+    //How far in canvas pixels the spiral and squiggle styles swing to either side where the image
+    //is black.
+    pub fn set_amplitude(&mut self,amplitude: f32) {
+        self.amplitude = amplitude;
+    }
+
+    //Angle of the squiggle style's lines in radians clockwise from horizontal.
+    pub fn set_squiggle_angle(&mut self,squiggle_angle: f32) {
+        self.squiggle_angle = squiggle_angle;
+    }
+
+    //Point the radial style's lines run toward.
+    pub fn set_focus(&mut self,focus: Focus) {
+        self.focus = focus;
+    }
+
+    //Standard deviation in radians of how far the radial style's lines turn away from the focus.
+    pub fn set_radial_jitter(&mut self,radial_jitter: f32) {
+        self.radial_jitter = radial_jitter;
+    }
+
+    //Most straight lines the hough style draws.
+    pub fn set_hough_lines(&mut self,hough_lines: usize) {
+        self.hough_lines = hough_lines;
+    }
+
+    //Extend the hough style's lines across the whole canvas instead of stopping at the ends of the
+    //edges they were found on.
+    pub fn set_extend_lines(&mut self,extend_lines: bool) {
+        self.extend_lines = extend_lines;
+    }
+
+    //Shortest edge in source pixels the outline style draws.
+    pub fn set_outline_length(&mut self,outline_length: usize) {
+        self.outline_length = outline_length;
+    }
+
+    //About how many superpixels the source is split into for the regions style and for clipping.
+    pub fn set_region_count(&mut self,region_count: usize) {
+        self.region_count = region_count;
+    }
+
+    //How much superpixels favor staying compact over following the colors of the source. Around
+    //10.0 hugs edges in the source while much higher values make regions close to squares.
+    pub fn set_compactness(&mut self,compactness: f32) {
+        self.compactness = compactness;
+    }
+
+    //Cut the random, steered, and energy styles' strokes off where they leave the superpixel they
+    //started in so they don't carry colors across the borders of objects.
+    pub fn set_clip_to_regions(&mut self,clip_to_regions: bool) {
+        self.clip_to_regions = clip_to_regions;
+    }
+
+    //Layer recorded with each stroke so strokes from several renders onto the same canvas can be
+    //told apart in a stroke log.
+    pub fn set_layer(&mut self,layer: u16) {
+        self.layer = layer;
+    }
+
+    //The color mode that's actually used. Each style has its own preferred coloring when one isn't
+    //picked explicitly.
+    pub fn effective_color_mode(&self) -> ColorMode {
+        match (self.color_mode,self.style) {
+            (ColorMode::Default,Style::Random) | (ColorMode::Default,Style::Steered) => ColorMode::EndpointMix,
+            (ColorMode::Default,Style::Energy) | (ColorMode::Default,Style::Radial) => ColorMode::LightnessJitter,
+            (ColorMode::Default,Style::EdgeWeb) => ColorMode::RandomPoint,
+            (ColorMode::Default,Style::Stipple) => ColorMode::Center,
+            (ColorMode::Default,Style::Mesh) | (ColorMode::Default,Style::Contour) | (ColorMode::Default,Style::Hough) |
+            (ColorMode::Default,Style::Outline) | (ColorMode::Default,Style::Regions) => ColorMode::LineAverage,
+            (ColorMode::Default,Style::Spiral) | (ColorMode::Default,Style::Squiggle) => ColorMode::Sampled,
+            (color_mode,_) => color_mode,
+        }
+    }
+
+    //Draw onto canvas which must be the size source was created for. progress is called before the
+    //first line, every so often while drawing, and after the last line. Returning false from it
+    //cancels the render and leaves what was drawn so far on the canvas.
+    pub fn render<F>(&self,source: &Source,canvas: &mut dyn Canvas,progress: F) -> Result<RenderStatus>
+        where F: FnMut(Progress) -> bool {
+        self.render_logged(source,canvas,progress,|_| Ok(()))
+    }
+
+    //Same as render except log is called with every stroke right after it's drawn. An error from
+    //log stops the render and is passed along.
+    pub fn render_logged<F,L>(&self,source: &Source,canvas: &mut dyn Canvas,mut progress: F,mut log: L) -> Result<RenderStatus>
+        where F: FnMut(Progress) -> bool,
+              L: FnMut(&StrokeRecord) -> Result<()> {
+        if canvas.width() != source.canvas_width as usize || canvas.height() != source.canvas_height as usize {
+            return Err(Error::InvalidParameter(format!("Canvas is {}x{} but the source was prepared for {}x{}",canvas.width(),canvas.height(),source.canvas_width,source.canvas_height)));
+        }
+
+        let mut rng = rand::thread_rng();
+        let mut painter = Painter::new();
+        painter.set_pen_width(self.pen_width);
+        painter.set_pen_opacity(self.pen_opacity);
+        let color_mode = self.effective_color_mode();
+        let mut draw = |rng: &mut ThreadRng,stroke: &Stroke| {
+            let color = draw_stroke(&mut painter,rng,color_mode,self.ink,source,canvas,stroke);
+            log(&stroke.record(color,self.style,self.layer))
+        };
+
+        match self.style {
+            Style::EdgeWeb => return edge_web(&mut rng,source,&mut progress,&mut draw),
+            Style::Stipple => return stipple_dots(&mut rng,source,self.dot_count,self.tsp,&mut progress,&mut draw),
+            Style::Mesh => return mesh(&mut rng,source,self.mesh_points,self.edge_weight,self.low_poly,&mut progress,&mut draw),
+            Style::Contour => return contours(&mut rng,source,self.contour_levels,self.level_spacing,&mut progress,&mut draw),
+            Style::Spiral => return spiral(&mut rng,source,self.spacing,self.amplitude,&mut progress,&mut draw),
+            Style::Squiggle => return squiggle(&mut rng,source,self.spacing,self.amplitude,self.squiggle_angle,&mut progress,&mut draw),
+            Style::Hough => return hough(&mut rng,source,self.hough_lines,self.extend_lines,&mut progress,&mut draw),
+            Style::Outline => return outline(&mut rng,source,self.outline_length,&mut progress,&mut draw),
+            Style::Regions => return regions(&mut rng,source,self.region_count,self.compactness,&mut progress,&mut draw),
+            _ => (),
+        }
+
+        let focus = match self.style {
+            Style::Radial => focal_point(source,self.focus)?,
+            _ => (0.0,0.0),
+        };
+        let labels = if self.clip_to_regions && matches!(self.style,Style::Random | Style::Steered | Style::Energy) {
+            Some(slic(&source.pixels,source.width,source.height,self.region_count,self.compactness)?.labels)
+        }
+        else {
+            None
+        };
+
+        let total = self.line_count;
+        for completed in 0..total {
+            if completed.is_multiple_of(PROGRESS_INTERVAL) && !progress(Progress { completed,total }) {
+                return Ok(RenderStatus::Cancelled(Progress { completed,total }));
+            }
+
+            let mut stroke = match self.style {
+                Style::Random => {
+                    let (x1,y1,x2,y2) = random_line(&mut rng,source);
+                    Stroke::new(x1,y1,x2,y2)
+                },
+                Style::Steered if self.curved => random_steered_curve(&mut rng,source),
+                Style::Steered => {
+                    let (x1,y1,x2,y2) = random_steered_line(&mut rng,source);
+                    Stroke::new(x1,y1,x2,y2)
+                },
+                Style::Energy if self.curved => random_energy_curve(&mut rng,source),
+                Style::Energy => {
+                    let (xc,yc,x1,y1,x2,y2) = random_energy_line(&mut rng,source);
+                    Stroke {
+                        x1,
+                        y1,
+                        x2,
+                        y2,
+                        xc,
+                        yc,
+                        kind: StrokeKind::Line,
+                    }
+                },
+                Style::Radial => random_radial_line(&mut rng,source,focus,self.radial_jitter),
+                Style::EdgeWeb | Style::Stipple | Style::Mesh | Style::Contour | Style::Spiral | Style::Squiggle | Style::Hough | Style::Outline |
+                Style::Regions => unreachable!(),
+            };
+
+            if let Some(ref labels) = labels {
+                //Random and steered strokes grow from their start and energy strokes from their
+                //center.
+                let anchor = match (self.style,&stroke.kind) {
+                    (Style::Energy,&StrokeKind::Curve(..)) => 0.5,
+                    (Style::Energy,_) => {
+                        let (dx,dy) = (stroke.x2 as f32 - stroke.x1 as f32,stroke.y2 as f32 - stroke.y1 as f32);
+                        let length_squared = dx * dx + dy * dy;
+                        if length_squared > 0.0 {
+                            max_f32(min_f32(((stroke.xc as f32 - stroke.x1 as f32) * dx + (stroke.yc as f32 - stroke.y1 as f32) * dy) / length_squared,1.0),0.0)
+                        }
+                        else {
+                            0.0
+                        }
+                    },
+                    _ => 0.0,
+                };
+                let (x,y) = stroke.point_at(anchor);
+                let region = source.region_at(labels,x,y);
+                stroke.clip(anchor,|x,y| source.region_at(labels,x,y) == region);
+            }
+
+            draw(&mut rng,&stroke)?;
+        }
+
+        progress(Progress { completed: total,total });
+        Ok(RenderStatus::Finished)
+    }
+}
@@ -0,0 +1,337 @@
+extern crate std;
+
+use rand::{Open01,Rand,Rng};
+
+use error::{Error,Result};
+use utility::{hilbert_index,min_f32,max_f32};
+
+//Pixels with less density than this don't pull on any dots. Skipping them saves searching far
+//across empty areas for the nearest dot.
+const DENSITY_MIN: f32 = 1.0 / 255.0;
+
+//Relaxing stops early once the dots move less than this many pixels on average.
+const SETTLED_DISTANCE: f32 = 0.01;
+
+//How many of the closest dots are tried when looking for a shorter tour.
+const TOUR_NEIGHBORS: usize = 8;
+
+//Sorts dots into square cells so the ones near a position can be found without checking them all.
+struct DotGrid {
+    cell_size: f32,
+    columns: usize,
+    rows: usize,
+    cell_starts: Vec<usize>,
+    dots: Vec<usize>,
+}
+
+impl DotGrid {
+    fn new(points: &[(f32,f32)],width: usize,height: usize) -> DotGrid {
+        //About two dots per cell when they're spread evenly.
+        let cell_size = max_f32(1.0,(2.0 * width as f32 * height as f32 / points.len() as f32).sqrt());
+        let columns = (width as f32 / cell_size).ceil() as usize + 1;
+        let rows = (height as f32 / cell_size).ceil() as usize + 1;
+
+        let mut grid = DotGrid {
+            cell_size,
+            columns,
+            rows,
+            cell_starts: vec![0; columns * rows + 1],
+            dots: vec![0; points.len()],
+        };
+
+        //Counting sort so each cell's dots end up next to each other.
+        let cells: Vec<usize> = points.iter().map(|&(x,y)| grid.cell_at(x,y)).collect();
+        for &cell in &cells {
+            grid.cell_starts[cell + 1] += 1;
+        }
+        for cell in 0..columns * rows {
+            grid.cell_starts[cell + 1] += grid.cell_starts[cell];
+        }
+        let mut next = grid.cell_starts.clone();
+        for (index,&cell) in cells.iter().enumerate() {
+            grid.dots[next[cell]] = index;
+            next[cell] += 1;
+        }
+
+        grid
+    }
+
+    fn cell_coordinates(&self,x: f32,y: f32) -> (usize,usize) {
+        let column = std::cmp::min(max_f32(0.0,x / self.cell_size) as usize,self.columns - 1);
+        let row = std::cmp::min(max_f32(0.0,y / self.cell_size) as usize,self.rows - 1);
+        (column,row)
+    }
+
+    fn cell_at(&self,x: f32,y: f32) -> usize {
+        let (column,row) = self.cell_coordinates(x,y);
+        row * self.columns + column
+    }
+
+    //Call func with every dot in the cells that are exactly ring cells away from column,row.
+    fn foreach_in_ring<F>(&self,column: usize,row: usize,ring: usize,mut func: F)
+        where F: FnMut(usize) {
+        let (column,row,ring) = (column as isize,row as isize,ring as isize);
+        for cell_row in row - ring..row + ring + 1 {
+            if cell_row < 0 || cell_row >= self.rows as isize {
+                continue;
+            }
+
+            //Only the first and last rows of the ring are filled in. The rest just have their ends.
+            let step = if cell_row == row - ring || cell_row == row + ring { 1 } else { std::cmp::max(1,2 * ring) };
+            let mut cell_column = column - ring;
+            while cell_column <= column + ring {
+                if cell_column >= 0 && cell_column < self.columns as isize {
+                    let cell = cell_row as usize * self.columns + cell_column as usize;
+                    for &dot in &self.dots[self.cell_starts[cell]..self.cell_starts[cell + 1]] {
+                        func(dot);
+                    }
+                }
+                cell_column += step;
+            }
+        }
+    }
+
+    //Find the dot closest to x,y. There must be at least one dot.
+    fn closest(&self,points: &[(f32,f32)],x: f32,y: f32) -> usize {
+        let (mut closest,mut closest_distance) = (0,f32::INFINITY);
+        let (column,row) = self.cell_coordinates(x,y);
+        let ring_max = std::cmp::max(self.columns,self.rows);
+        for ring in 0..ring_max {
+            self.foreach_in_ring(column,row,ring,|dot| {
+                let distance = distance_squared(points[dot],(x,y));
+                if distance < closest_distance {
+                    closest = dot;
+                    closest_distance = distance;
+                }
+            });
+
+            //Every dot in the next ring is at least this far away.
+            let reach = ring as f32 * self.cell_size;
+            if closest_distance <= reach * reach {
+                break;
+            }
+        }
+        closest
+    }
+
+    //Find the k dots closest to x,y, nearest first, skipping the dot numbered skip.
+    fn nearest(&self,points: &[(f32,f32)],x: f32,y: f32,k: usize,skip: Option<usize>) -> Vec<usize> {
+        let mut found: Vec<(f32,usize)> = Vec::with_capacity(k + 1);
+        let (column,row) = self.cell_coordinates(x,y);
+        let ring_max = std::cmp::max(self.columns,self.rows);
+        for ring in 0..ring_max {
+            self.foreach_in_ring(column,row,ring,|dot| {
+                if Some(dot) == skip {
+                    return;
+                }
+
+                let distance = distance_squared(points[dot],(x,y));
+                if found.len() < k || distance < found[found.len() - 1].0 {
+                    let position = found.iter().position(|&(other,_)| other > distance).unwrap_or(found.len());
+                    found.insert(position,(distance,dot));
+                    found.truncate(k);
+                }
+            });
+
+            //Every dot in the next ring is at least this far away.
+            let reach = ring as f32 * self.cell_size;
+            if found.len() == k && found[found.len() - 1].0 <= reach * reach {
+                break;
+            }
+        }
+
+        found.into_iter().map(|(_,dot)| dot).collect()
+    }
+}
+
+fn distance_squared(lhs: (f32,f32),rhs: (f32,f32)) -> f32 {
+    let (diff_x,diff_y) = (lhs.0 - rhs.0,lhs.1 - rhs.1);
+    diff_x * diff_x + diff_y * diff_y
+}
+
+//Spread count dots over a width x height density map so they're packed tighter where the density
+//is higher. Densities are from 0.0 to 1.0. The dots start out randomly placed and are then relaxed
+//toward the density weighted center of their Voronoi cells, which is Secord's weighted Voronoi
+//stippling. keep_going is called with the number of finished iterations before each one and can
+//stop relaxing early by returning false. Positions are in pixels with pixel centers at 0.5.
+pub fn stipple<R,F>(density: &[f32],width: usize,height: usize,count: usize,iterations: usize,rng: &mut R,mut keep_going: F) -> Result<Vec<(f32,f32)>>
+    where R: Rng,
+          F: FnMut(usize) -> bool {
+    if width == 0 || height == 0 {
+        return Err(Error::InvalidParameter(format!("Density map must be at least 1x1 but is {}x{}",width,height)));
+    }
+    if width.checked_mul(height) != Some(density.len()) {
+        return Err(Error::InvalidParameter(format!("Density map has {} values but should have one for each pixel of {}x{}",density.len(),width,height)));
+    }
+
+    let density_max = density.iter().fold(0.0,|maximum,&value| max_f32(maximum,value));
+    if count == 0 || density_max < DENSITY_MIN {
+        return Ok(vec![]);
+    }
+
+    //Place the dots by sampling the density so they start out roughly where they'll end up and the
+    //relaxation has less to do.
+    let mut cumulative = Vec::with_capacity(density.len());
+    let mut total = 0.0f64;
+    for &value in density {
+        if value >= DENSITY_MIN {
+            total += value as f64;
+        }
+        cumulative.push(total);
+    }
+    let mut points = Vec::with_capacity(count);
+    for _ in 0..count {
+        let target = Open01::<f64>::rand(rng).0 * total;
+        let index = std::cmp::min(cumulative.partition_point(|&sum| sum < target),density.len() - 1);
+        let (x,y) = (index % width,index / width);
+        points.push((x as f32 + Open01::<f32>::rand(rng).0,y as f32 + Open01::<f32>::rand(rng).0));
+    }
+
+    let mut sums = vec![(0.0f64,0.0f64,0.0f64); count];
+    for iteration in 0..iterations {
+        if !keep_going(iteration) {
+            break;
+        }
+
+        //Every pixel adds its density to the dot it's closest to.
+        let grid = DotGrid::new(&points,width,height);
+        for sum in sums.iter_mut() {
+            *sum = (0.0,0.0,0.0);
+        }
+        for y in 0..height {
+            for x in 0..width {
+                let weight = density[y * width + x];
+                if weight < DENSITY_MIN {
+                    continue;
+                }
+
+                let (center_x,center_y) = (x as f32 + 0.5,y as f32 + 0.5);
+                let dot = grid.closest(&points,center_x,center_y);
+                let sum = &mut sums[dot];
+                sum.0 += weight as f64;
+                sum.1 += (weight * center_x) as f64;
+                sum.2 += (weight * center_y) as f64;
+            }
+        }
+
+        //Move each dot to the center of its cell. Dots that didn't pick up any pixels stay put.
+        let mut moved = 0.0;
+        for (point,&(weight,sum_x,sum_y)) in points.iter_mut().zip(sums.iter()) {
+            if weight > 0.0 {
+                let center = ((sum_x / weight) as f32,(sum_y / weight) as f32);
+                moved += distance_squared(*point,center).sqrt();
+                *point = center;
+            }
+        }
+        if moved / (count as f32) < SETTLED_DISTANCE {
+            break;
+        }
+    }
+
+    Ok(points)
+}
+
+//Order points into a short path that visits each of them once, for drawing them as a single
+//continuous line. The tour starts along a Hilbert curve and is then shortened with 2-opt moves
+//between nearby points. The longest step is left out so the path has two ends. Returns the indices
+//of the points in the order they're visited.
+pub fn tsp_path(points: &[(f32,f32)]) -> Vec<usize> {
+    let count = points.len();
+    if count < 4 {
+        return (0..count).collect();
+    }
+
+    //Start from the order the points fall along a Hilbert curve covering them.
+    let min_x = points.iter().fold(f32::INFINITY,|minimum,point| min_f32(minimum,point.0));
+    let min_y = points.iter().fold(f32::INFINITY,|minimum,point| min_f32(minimum,point.1));
+    let max_x = points.iter().fold(f32::NEG_INFINITY,|maximum,point| max_f32(maximum,point.0));
+    let max_y = points.iter().fold(f32::NEG_INFINITY,|maximum,point| max_f32(maximum,point.1));
+    let extent = max_f32(max_f32(max_x - min_x,max_y - min_y),1.0e-6);
+    let mut tour: Vec<usize> = (0..count).collect();
+    tour.sort_by_key(|&index| {
+        let (x,y) = points[index];
+        hilbert_index(((x - min_x) / extent * 65535.0) as u32,((y - min_y) / extent * 65535.0) as u32)
+    });
+
+    let width = (max_x - min_x).ceil() as usize + 1;
+    let height = (max_y - min_y).ceil() as usize + 1;
+    let shifted: Vec<(f32,f32)> = points.iter().map(|&(x,y)| (x - min_x,y - min_y)).collect();
+    let grid = DotGrid::new(&shifted,width,height);
+    let neighbors: Vec<Vec<usize>> = (0..count).map(|index| grid.nearest(&shifted,shifted[index].0,shifted[index].1,TOUR_NEIGHBORS,Some(index))).collect();
+
+    let mut positions = vec![0; count];
+    for (position,&point) in tour.iter().enumerate() {
+        positions[point] = position;
+    }
+
+    let distance = |lhs: usize,rhs: usize| distance_squared(points[lhs],points[rhs]).sqrt();
+    let next = |tour: &[usize],positions: &[usize],point: usize| tour[(positions[point] + 1) % count];
+    let previous = |tour: &[usize],positions: &[usize],point: usize| tour[(positions[point] + count - 1) % count];
+
+    //Points that might still be part of an improving move. A point is only looked at again after
+    //one of its edges changes.
+    let mut queued = vec![true; count];
+    let mut queue: std::collections::VecDeque<usize> = tour.iter().cloned().collect();
+    while let Some(a) = queue.pop_front() {
+        queued[a] = false;
+
+        'directions: for &forward in &[true,false] {
+            let b = if forward { next(&tour,&positions,a) } else { previous(&tour,&positions,a) };
+            let ab = distance(a,b);
+            for &c in &neighbors[a] {
+                let ac = distance(a,c);
+                if ac >= ab {
+                    break;
+                }
+
+                let d = if forward { next(&tour,&positions,c) } else { previous(&tour,&positions,c) };
+                if c == b || d == a {
+                    continue;
+                }
+
+                //Swap edges a-b and c-d for a-c and b-d by reversing the stretch in between.
+                if ac + distance(b,d) < ab + distance(c,d) - 1.0e-4 {
+                    let (first,last) = if forward { (positions[b],positions[c]) } else { (positions[c],positions[b]) };
+                    reverse(&mut tour,&mut positions,first,last);
+                    for &point in &[a,b,c,d] {
+                        if !queued[point] {
+                            queued[point] = true;
+                            queue.push_back(point);
+                        }
+                    }
+                    break 'directions;
+                }
+            }
+        }
+    }
+
+    //Open the loop at its longest step.
+    let longest = (0..count).max_by(|&lhs,&rhs| {
+        let lhs = distance(tour[lhs],tour[(lhs + 1) % count]);
+        let rhs = distance(tour[rhs],tour[(rhs + 1) % count]);
+        lhs.partial_cmp(&rhs).unwrap_or(std::cmp::Ordering::Equal)
+    }).unwrap();
+    tour.rotate_left((longest + 1) % count);
+    tour
+}
+
+//Reverse the part of a circular tour from position first to position last, both inclusive, going
+//forward. The shorter of the two halves is flipped since both give the same loop.
+fn reverse(tour: &mut [usize],positions: &mut [usize],first: usize,last: usize) {
+    let count = tour.len();
+    let length = (last + count - first) % count + 1;
+    let (mut first,mut last,length) = if length * 2 > count {
+        ((last + 1) % count,(first + count - 1) % count,count - length)
+    }
+    else {
+        (first,last,length)
+    };
+
+    for _ in 0..length / 2 {
+        tour.swap(first,last);
+        positions[tour[first]] = first;
+        positions[tour[last]] = last;
+        first = (first + 1) % count;
+        last = (last + count - 1) % count;
+    }
+}
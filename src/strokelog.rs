@@ -0,0 +1,470 @@
+extern crate std;
+
+use std::io::{self,Read,Write};
+
+use canvas::Canvas;
+use error::{Error,Result};
+use painter::Painter;
+use render::Style;
+
+//A stroke log is a header followed by one record per stroke in the order they were drawn. Every
+//number is little-endian.
+//
+//Header:
+//  8 bytes  "LSSTROKE"
+//  u16      Version (1)
+//  u32      Canvas width
+//  u32      Canvas height
+//  f32      Pen width in canvas pixels
+//  f32      Pen opacity from 0 to 1
+//
+//Record:
+//  u8       Shape: 0 line, 1 quadratic curve, 2 dot, 3 triangle, 4 polyline
+//  u8       Style: 0 random, 1 steered, 2 energy, 3 edgeweb, 4 stipple, 5 mesh, 6 contour, 7 spiral,
+//           8 squiggle, 9 radial, 10 hough, 11 outline, 12 regions
+//  u16      Layer
+//  f32 x 4  Line: x1, y1, x2, y2 in canvas pixels where 0, 0 is the center of the top left pixel
+//  f32 x 6  Quadratic curve: x1, y1, cx, cy, x2, y2 in canvas pixels measured the same way as lines
+//  f32 x 2  Dot: x, y in canvas pixels measured the same way as lines. Dots are as wide as the pen.
+//  f32 x 6  Triangle: x1, y1, x2, y2, x3, y3 in canvas pixels from the top left corner of the
+//           canvas rather than from pixel centers. Triangles are filled.
+//  u32      Polyline: number of points followed by an x, y pair of f32 for each point, measured the
+//           same way as triangles. Polylines that end where they started are closed.
+//  u8       Color: 0 solid, 1 gradient
+//  u8 x 3   Solid: red, green, blue
+//  u8 x 6   Gradient: red, green, blue at the start and then at the end
+const MAGIC: &[u8] = b"LSSTROKE";
+const VERSION: u16 = 1;
+
+//Longest polyline a log can hold. Keeps a damaged point count from allocating huge amounts of
+//memory.
+const POLYLINE_POINTS_MAX: usize = 1 << 24;
+
+#[derive(Clone,Debug,PartialEq)]
+pub enum StrokeShape {
+    Line { x1: f32,y1: f32,x2: f32,y2: f32 },
+    Curve { x1: f32,y1: f32,cx: f32,cy: f32,x2: f32,y2: f32 },
+    Dot { x: f32,y: f32 },
+    Triangle { x1: f32,y1: f32,x2: f32,y2: f32,x3: f32,y3: f32 },
+    Polyline { points: Vec<(f32,f32)> },
+}
+
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum StrokeColor {
+    Solid(u8,u8,u8),
+    //Fades from the first color at the start of the stroke to the second color at the end.
+    Gradient((u8,u8,u8),(u8,u8,u8)),
+}
+
+//A stroke exactly as it was drawn. Strokes drawn with the sampled color mode are recorded as a
+//gradient between the colors at their ends.
+#[derive(Clone,Debug,PartialEq)]
+pub struct StrokeRecord {
+    pub shape: StrokeShape,
+    pub color: StrokeColor,
+    pub style: Style,
+    pub layer: u16,
+}
+
+impl StrokeRecord {
+    //Move the stroke onto a canvas that is scale_x times wider and scale_y times taller.
+    pub fn scaled(&self,scale_x: f32,scale_y: f32) -> StrokeRecord {
+        //Lines, curves, and dots are measured from pixel centers so they're stretched around the
+        //top left corner of the canvas the same as triangles and polylines.
+        let center_x = |x: f32| (x + 0.5) * scale_x - 0.5;
+        let center_y = |y: f32| (y + 0.5) * scale_y - 0.5;
+        let shape = match self.shape {
+            StrokeShape::Line { x1,y1,x2,y2 } => StrokeShape::Line {
+                x1: center_x(x1),
+                y1: center_y(y1),
+                x2: center_x(x2),
+                y2: center_y(y2),
+            },
+            StrokeShape::Curve { x1,y1,cx,cy,x2,y2 } => StrokeShape::Curve {
+                x1: center_x(x1),
+                y1: center_y(y1),
+                cx: center_x(cx),
+                cy: center_y(cy),
+                x2: center_x(x2),
+                y2: center_y(y2),
+            },
+            StrokeShape::Dot { x,y } => StrokeShape::Dot {
+                x: center_x(x),
+                y: center_y(y),
+            },
+            StrokeShape::Triangle { x1,y1,x2,y2,x3,y3 } => StrokeShape::Triangle {
+                x1: x1 * scale_x,
+                y1: y1 * scale_y,
+                x2: x2 * scale_x,
+                y2: y2 * scale_y,
+                x3: x3 * scale_x,
+                y3: y3 * scale_y,
+            },
+            StrokeShape::Polyline { ref points } => StrokeShape::Polyline {
+                points: points.iter().map(|&(x,y)| (x * scale_x,y * scale_y)).collect(),
+            },
+        };
+        StrokeRecord {
+            shape,
+            color: self.color,
+            style: self.style,
+            layer: self.layer,
+        }
+    }
+}
+
+//The canvas and pen the strokes in a log were drawn with.
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub struct StrokeLogHeader {
+    pub width: u32,
+    pub height: u32,
+    pub pen_width: f32,
+    pub pen_opacity: f32,
+}
+
+pub struct StrokeLogWriter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> StrokeLogWriter<W> {
+    pub fn new(mut writer: W,header: &StrokeLogHeader) -> Result<StrokeLogWriter<W>> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+        bytes.extend_from_slice(&header.width.to_le_bytes());
+        bytes.extend_from_slice(&header.height.to_le_bytes());
+        bytes.extend_from_slice(&header.pen_width.to_le_bytes());
+        bytes.extend_from_slice(&header.pen_opacity.to_le_bytes());
+        writer.write_all(&bytes)?;
+
+        Ok(StrokeLogWriter {
+            writer,
+        })
+    }
+
+    pub fn write(&mut self,record: &StrokeRecord) -> Result<()> {
+        let mut bytes = Vec::with_capacity(36);
+        let coordinates = match record.shape {
+            StrokeShape::Polyline { ref points } if points.len() > POLYLINE_POINTS_MAX => {
+                return Err(Error::InvalidParameter(format!("Polylines in a stroke log can't have more than {} points",POLYLINE_POINTS_MAX)));
+            },
+            StrokeShape::Polyline { ref points } => {
+                bytes.push(4);
+                points.iter().flat_map(|&(x,y)| vec![x,y]).collect()
+            },
+            StrokeShape::Line { x1,y1,x2,y2 } => {
+                bytes.push(0);
+                vec![x1,y1,x2,y2]
+            },
+            StrokeShape::Curve { x1,y1,cx,cy,x2,y2 } => {
+                bytes.push(1);
+                vec![x1,y1,cx,cy,x2,y2]
+            },
+            StrokeShape::Dot { x,y } => {
+                bytes.push(2);
+                vec![x,y]
+            },
+            StrokeShape::Triangle { x1,y1,x2,y2,x3,y3 } => {
+                bytes.push(3);
+                vec![x1,y1,x2,y2,x3,y3]
+            },
+        };
+        bytes.push(style_code(record.style));
+        bytes.extend_from_slice(&record.layer.to_le_bytes());
+        if let StrokeShape::Polyline { ref points } = record.shape {
+            bytes.extend_from_slice(&(points.len() as u32).to_le_bytes());
+        }
+        for coordinate in coordinates {
+            bytes.extend_from_slice(&coordinate.to_le_bytes());
+        }
+        match record.color {
+            StrokeColor::Solid(red,green,blue) => bytes.extend_from_slice(&[0,red,green,blue]),
+            StrokeColor::Gradient((red1,green1,blue1),(red2,green2,blue2)) => bytes.extend_from_slice(&[1,red1,green1,blue1,red2,green2,blue2]),
+        }
+
+        self.writer.write_all(&bytes)?;
+        Ok(())
+    }
+
+    //Flush anything that's buffered and hand back the underlying writer.
+    pub fn finish(mut self) -> Result<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+//Reads a stroke log one record at a time. Iterating stops after the last record or the first error.
+pub struct StrokeLogReader<R: Read> {
+    reader: R,
+    header: StrokeLogHeader,
+    failed: bool,
+}
+
+impl<R: Read> StrokeLogReader<R> {
+    pub fn new(mut reader: R) -> Result<StrokeLogReader<R>> {
+        let mut bytes = [0; 26];
+        match reader.read_exact(&mut bytes) {
+            Ok(()) => (),
+            Err(ref e) if e.kind() == io::ErrorKind::UnexpectedEof => return Err(not_a_stroke_log()),
+            Err(e) => return Err(e.into()),
+        }
+        if &bytes[0..8] != MAGIC {
+            return Err(not_a_stroke_log());
+        }
+        let version = u16::from_le_bytes([bytes[8],bytes[9]]);
+        if version != VERSION {
+            return Err(Error::UnsupportedFormat(format!("Stroke log version {} is not supported",version)));
+        }
+
+        let header = StrokeLogHeader {
+            width: u32::from_le_bytes([bytes[10],bytes[11],bytes[12],bytes[13]]),
+            height: u32::from_le_bytes([bytes[14],bytes[15],bytes[16],bytes[17]]),
+            pen_width: f32::from_le_bytes([bytes[18],bytes[19],bytes[20],bytes[21]]),
+            pen_opacity: f32::from_le_bytes([bytes[22],bytes[23],bytes[24],bytes[25]]),
+        };
+        check_header(&header)?;
+
+        Ok(StrokeLogReader {
+            reader,
+            header,
+            failed: false,
+        })
+    }
+
+    pub fn header(&self) -> &StrokeLogHeader {
+        &self.header
+    }
+
+    fn read_record(&mut self) -> Result<Option<StrokeRecord>> {
+        let mut shape = [0; 1];
+        loop {
+            match self.reader.read(&mut shape) {
+                Ok(0) => return Ok(None),
+                Ok(_) => break,
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        let mut style_and_layer = [0; 3];
+        self.read_exact(&mut style_and_layer)?;
+        let style = match style_from_code(style_and_layer[0]) {
+            Some(style) => style,
+            None => return Err(Error::UnsupportedFormat(format!("Unknown style {} in stroke log",style_and_layer[0]))),
+        };
+        let layer = u16::from_le_bytes([style_and_layer[1],style_and_layer[2]]);
+
+        let coordinate_count = match shape[0] {
+            0 => 4,
+            1 => 6,
+            2 => 2,
+            3 => 6,
+            4 => {
+                let mut count = [0; 4];
+                self.read_exact(&mut count)?;
+                match u32::from_le_bytes(count) as usize {
+                    count if count > POLYLINE_POINTS_MAX => return Err(Error::UnsupportedFormat(format!("Stroke log has a polyline with {} points",count))),
+                    count => count * 2,
+                }
+            },
+            shape => return Err(Error::UnsupportedFormat(format!("Unknown stroke shape {} in stroke log",shape))),
+        };
+        let mut bytes = vec![0; coordinate_count * 4 + 1];
+        self.read_exact(&mut bytes)?;
+
+        let coordinates: Vec<f32> = bytes[..coordinate_count * 4].chunks(4).map(|chunk| f32::from_le_bytes([chunk[0],chunk[1],chunk[2],chunk[3]])).collect();
+        if coordinates.iter().any(|coordinate| !coordinate.is_finite()) {
+            return Err(Error::InvalidParameter("Stroke log has a coordinate that isn't a finite number".to_string()));
+        }
+        let shape = match shape[0] {
+            0 => StrokeShape::Line { x1: coordinates[0],y1: coordinates[1],x2: coordinates[2],y2: coordinates[3] },
+            1 => StrokeShape::Curve { x1: coordinates[0],y1: coordinates[1],cx: coordinates[2],cy: coordinates[3],x2: coordinates[4],y2: coordinates[5] },
+            2 => StrokeShape::Dot { x: coordinates[0],y: coordinates[1] },
+            3 => StrokeShape::Triangle { x1: coordinates[0],y1: coordinates[1],x2: coordinates[2],y2: coordinates[3],x3: coordinates[4],y3: coordinates[5] },
+            _ => StrokeShape::Polyline { points: coordinates.chunks(2).map(|point| (point[0],point[1])).collect() },
+        };
+
+        let color = match bytes[bytes.len() - 1] {
+            0 => {
+                let mut rgb = [0; 3];
+                self.read_exact(&mut rgb)?;
+                StrokeColor::Solid(rgb[0],rgb[1],rgb[2])
+            },
+            1 => {
+                let mut rgb = [0; 6];
+                self.read_exact(&mut rgb)?;
+                StrokeColor::Gradient((rgb[0],rgb[1],rgb[2]),(rgb[3],rgb[4],rgb[5]))
+            },
+            color => return Err(Error::UnsupportedFormat(format!("Unknown stroke color {} in stroke log",color))),
+        };
+
+        Ok(Some(StrokeRecord {
+            shape,
+            color,
+            style,
+            layer,
+        }))
+    }
+
+    //Running out of bytes partway through a record means the log was cut off.
+    fn read_exact(&mut self,bytes: &mut [u8]) -> Result<()> {
+        match self.reader.read_exact(bytes) {
+            Ok(()) => Ok(()),
+            Err(ref e) if e.kind() == io::ErrorKind::UnexpectedEof => Err(Error::Io(io::Error::new(io::ErrorKind::UnexpectedEof,"Stroke log is truncated"))),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+impl<R: Read> Iterator for StrokeLogReader<R> {
+    type Item = Result<StrokeRecord>;
+
+    fn next(&mut self) -> Option<Result<StrokeRecord>> {
+        if self.failed {
+            return None;
+        }
+
+        match self.read_record() {
+            Ok(record) => record.map(Ok),
+            Err(e) => {
+                self.failed = true;
+                Some(Err(e))
+            },
+        }
+    }
+}
+
+//Draws the strokes from a log onto a canvas of any size. The strokes are stretched to fit and the
+//pen width grows or shrinks along with them unless it's overridden.
+pub struct Replayer {
+    pen_width: Option<f32>,
+    pen_opacity: Option<f32>,
+    ink: Option<(u8,u8,u8)>,
+}
+
+impl Default for Replayer {
+    fn default() -> Replayer {
+        Replayer::new()
+    }
+}
+
+impl Replayer {
+    pub fn new() -> Replayer {
+        Replayer {
+            pen_width: None,
+            pen_opacity: None,
+            ink: None,
+        }
+    }
+
+    //Pen width in pixels of the canvas being replayed onto instead of the logged width.
+    pub fn set_pen_width(&mut self,pen_width: f32) {
+        self.pen_width = Some(pen_width);
+    }
+
+    pub fn set_pen_opacity(&mut self,pen_opacity: f32) {
+        self.pen_opacity = Some(pen_opacity);
+    }
+
+    //Draw every stroke in this color instead of the logged colors.
+    pub fn set_ink(&mut self,red: u8,green: u8,blue: u8) {
+        self.ink = Some((red,green,blue));
+    }
+
+    //Draw records that were logged with header onto canvas. Returns how many strokes were drawn.
+    pub fn replay<I>(&self,header: &StrokeLogHeader,records: I,canvas: &mut dyn Canvas) -> Result<u64>
+        where I: IntoIterator<Item = Result<StrokeRecord>> {
+        check_header(header)?;
+        let scale_x = canvas.width() as f32 / header.width as f32;
+        let scale_y = canvas.height() as f32 / header.height as f32;
+
+        let mut painter = Painter::new();
+        painter.set_pen_width(self.pen_width.unwrap_or(header.pen_width * (scale_x * scale_y).sqrt()));
+        painter.set_pen_opacity(self.pen_opacity.unwrap_or(header.pen_opacity));
+
+        let mut count = 0;
+        for record in records {
+            let record = record?.scaled(scale_x,scale_y);
+            match (self.ink,record.color) {
+                (Some((red,green,blue)),_) | (None,StrokeColor::Solid(red,green,blue)) => painter.set_pen(red,green,blue),
+                (None,StrokeColor::Gradient((red1,green1,blue1),(red2,green2,blue2))) => painter.set_pen_gradient(red1,green1,blue1,red2,green2,blue2),
+            }
+
+            match record.shape {
+                StrokeShape::Line { x1,y1,x2,y2 } => {
+                    painter.line(canvas,x1.round() as i32,y1.round() as i32,x2.round() as i32,y2.round() as i32);
+                },
+                StrokeShape::Curve { x1,y1,cx,cy,x2,y2 } => {
+                    painter.quadratic_bezier(canvas,x1,y1,cx,cy,x2,y2);
+                },
+                StrokeShape::Dot { x,y } => {
+                    painter.dot(canvas,x.round() + 0.5,y.round() + 0.5);
+                },
+                StrokeShape::Triangle { x1,y1,x2,y2,x3,y3 } => {
+                    painter.polygon(canvas,&[(x1,y1),(x2,y2),(x3,y3)]);
+                },
+                StrokeShape::Polyline { ref points } => {
+                    painter.polyline(canvas,points);
+                },
+            }
+            count += 1;
+        }
+
+        Ok(count)
+    }
+}
+
+//Headers are checked the same as records so a damaged log can't draw with a pen that isn't a
+//number.
+fn check_header(header: &StrokeLogHeader) -> Result<()> {
+    if header.width == 0 || header.height == 0 {
+        return Err(Error::EmptyImage);
+    }
+    if header.pen_width <= 0.0 || !header.pen_width.is_finite() {
+        return Err(Error::InvalidParameter(format!("Stroke log has a pen width of {} but it must be a positive number",header.pen_width)));
+    }
+    if !header.pen_opacity.is_finite() {
+        return Err(Error::InvalidParameter("Stroke log has a pen opacity that isn't a finite number".to_string()));
+    }
+    Ok(())
+}
+
+fn not_a_stroke_log() -> Error {
+    Error::UnsupportedFormat("Not a line-splat stroke log".to_string())
+}
+
+fn style_code(style: Style) -> u8 {
+    match style {
+        Style::Random => 0,
+        Style::Steered => 1,
+        Style::Energy => 2,
+        Style::EdgeWeb => 3,
+        Style::Stipple => 4,
+        Style::Mesh => 5,
+        Style::Contour => 6,
+        Style::Spiral => 7,
+        Style::Squiggle => 8,
+        Style::Radial => 9,
+        Style::Hough => 10,
+        Style::Outline => 11,
+        Style::Regions => 12,
+    }
+}
+
+fn style_from_code(code: u8) -> Option<Style> {
+    match code {
+        0 => Some(Style::Random),
+        1 => Some(Style::Steered),
+        2 => Some(Style::Energy),
+        3 => Some(Style::EdgeWeb),
+        4 => Some(Style::Stipple),
+        5 => Some(Style::Mesh),
+        6 => Some(Style::Contour),
+        7 => Some(Style::Spiral),
+        8 => Some(Style::Squiggle),
+        9 => Some(Style::Radial),
+        10 => Some(Style::Hough),
+        11 => Some(Style::Outline),
+        12 => Some(Style::Regions),
+        _ => None,
+    }
+}
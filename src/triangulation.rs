@@ -0,0 +1,188 @@
+extern crate std;
+
+use utility::{hilbert_index,min_f32,max_f32};
+
+//How much bigger than the points' bounding box the starting triangle is. It has to be big enough
+//that its corners don't bend the triangles along the outside of the points.
+const SUPER_TRIANGLE_SCALE: f64 = 64.0;
+
+//Corners are stored counterclockwise. neighbors[i] is the triangle across the edge opposite
+//corners[i] or None along the outside of the starting triangle.
+#[derive(Clone,Copy)]
+struct Triangle {
+    corners: [usize; 3],
+    neighbors: [Option<usize>; 3],
+    alive: bool,
+    //Number of the insertion that last added this triangle to a cavity.
+    stamp: usize,
+}
+
+impl Triangle {
+    //Find the edge opposite corner. The triangle's inside is to the left going from the first
+    //point to the second.
+    fn edge(&self,corner: usize) -> (usize,usize) {
+        (self.corners[(corner + 1) % 3],self.corners[(corner + 2) % 3])
+    }
+}
+
+//Twice the signed area of the triangle a,b,c. Positive when the points are counterclockwise.
+fn orientation(a: (f64,f64),b: (f64,f64),c: (f64,f64)) -> f64 {
+    (b.0 - a.0) * (c.1 - a.1) - (b.1 - a.1) * (c.0 - a.0)
+}
+
+//Check if p is strictly inside the circle passing through the counterclockwise triangle a,b,c.
+fn in_circumcircle(a: (f64,f64),b: (f64,f64),c: (f64,f64),p: (f64,f64)) -> bool {
+    let (adx,ady) = (a.0 - p.0,a.1 - p.1);
+    let (bdx,bdy) = (b.0 - p.0,b.1 - p.1);
+    let (cdx,cdy) = (c.0 - p.0,c.1 - p.1);
+    let determinant = (adx * adx + ady * ady) * (bdx * cdy - cdx * bdy)
+                    + (bdx * bdx + bdy * bdy) * (cdx * ady - adx * cdy)
+                    + (cdx * cdx + cdy * cdy) * (adx * bdy - bdx * ady);
+    determinant > 0.0
+}
+
+//Compute the Delaunay triangulation of points using the Bowyer-Watson algorithm. Points are added
+//one at a time in Hilbert curve order so each one can be found by walking over from the last
+//triangle that was made. Duplicate points are skipped. Returns the indices of each triangle's
+//corners in counterclockwise order when y points up (clockwise on screen).
+pub fn triangulate(points: &[(f32,f32)]) -> Vec<[usize; 3]> {
+    let count = points.len();
+    if count < 3 || points.iter().any(|point| !point.0.is_finite() || !point.1.is_finite()) {
+        return vec![];
+    }
+
+    let min_x = points.iter().fold(f32::INFINITY,|minimum,point| min_f32(minimum,point.0));
+    let min_y = points.iter().fold(f32::INFINITY,|minimum,point| min_f32(minimum,point.1));
+    let max_x = points.iter().fold(f32::NEG_INFINITY,|maximum,point| max_f32(maximum,point.0));
+    let max_y = points.iter().fold(f32::NEG_INFINITY,|maximum,point| max_f32(maximum,point.1));
+    let extent = max_f32(max_f32(max_x - min_x,max_y - min_y),1.0e-6);
+
+    //Work relative to the bounding box so the circle test keeps as much precision as it can.
+    let mut vertices: Vec<(f64,f64)> = points.iter().map(|&(x,y)| ((x - min_x) as f64,(y - min_y) as f64)).collect();
+
+    //Start with one triangle that covers every point. Its corners are removed at the end.
+    let size = extent as f64 * SUPER_TRIANGLE_SCALE;
+    let center = ((max_x - min_x) as f64 / 2.0,(max_y - min_y) as f64 / 2.0);
+    vertices.push((center.0 - size,center.1 - size));
+    vertices.push((center.0 + size,center.1 - size));
+    vertices.push((center.0,center.1 + size));
+    let mut triangles = vec![Triangle {
+        corners: [count,count + 1,count + 2],
+        neighbors: [None; 3],
+        alive: true,
+        stamp: 0,
+    }];
+
+    let mut order: Vec<usize> = (0..count).collect();
+    order.sort_by_key(|&index| {
+        let (x,y) = vertices[index];
+        hilbert_index((x / extent as f64 * 65535.0) as u32,(y / extent as f64 * 65535.0) as u32)
+    });
+
+    let mut last = 0;
+    let mut cavity = vec![];
+    let mut boundary: Vec<(usize,usize,Option<usize>)> = vec![];
+    let mut fan: Vec<usize> = vec![];
+    for (insertion,&point) in order.iter().enumerate() {
+        let p = vertices[point];
+        let stamp = insertion + 1;
+
+        let start = match locate(&vertices,&triangles,last,p) {
+            Some(start) => start,
+            None => continue,
+        };
+        if triangles[start].corners.iter().any(|&corner| vertices[corner] == p) {
+            continue;
+        }
+
+        //Gather every triangle whose circumcircle holds the point. A triangle is also taken if
+        //the point can't see the edge it shares with the cavity so the hole stays star-shaped even
+        //when rounding makes the circle test disagree with itself.
+        cavity.clear();
+        boundary.clear();
+        cavity.push(start);
+        triangles[start].stamp = stamp;
+        let mut next = 0;
+        while next < cavity.len() {
+            let triangle = triangles[cavity[next]];
+            next += 1;
+            for corner in 0..3 {
+                let (b,c) = triangle.edge(corner);
+                match triangle.neighbors[corner] {
+                    Some(neighbor) if triangles[neighbor].stamp == stamp => (),
+                    Some(neighbor) if orientation(vertices[b],vertices[c],p) <= 0.0 || {
+                        let [x,y,z] = triangles[neighbor].corners;
+                        in_circumcircle(vertices[x],vertices[y],vertices[z],p)
+                    } => {
+                        triangles[neighbor].stamp = stamp;
+                        cavity.push(neighbor);
+                    },
+                    outside => boundary.push((b,c,outside)),
+                }
+            }
+        }
+        //An edge can end up inside of the cavity if the triangle across it was pulled in later.
+        boundary.retain(|&(_,_,outside)| outside.is_none_or(|outside| triangles[outside].stamp != stamp));
+
+        //Fill the cavity with a fan of triangles around the point.
+        for &index in &cavity {
+            triangles[index].alive = false;
+        }
+        fan.clear();
+        for &(b,c,outside) in &boundary {
+            let index = triangles.len();
+            triangles.push(Triangle {
+                corners: [point,b,c],
+                neighbors: [outside,None,None],
+                alive: true,
+                stamp,
+            });
+            if let Some(outside) = outside {
+                //The triangle outside shares the edge going the other way.
+                if let Some(corner) = (0..3).find(|&corner| triangles[outside].edge(corner) == (c,b)) {
+                    triangles[outside].neighbors[corner] = Some(index);
+                }
+            }
+            fan.push(index);
+        }
+        for &index in &fan {
+            let [_,b,c] = triangles[index].corners;
+            //Across from b is the edge c,point which the fan triangle starting at c shares.
+            let after = fan.iter().cloned().find(|&other| triangles[other].corners[1] == c);
+            let before = fan.iter().cloned().find(|&other| triangles[other].corners[2] == b);
+            triangles[index].neighbors[1] = after;
+            triangles[index].neighbors[2] = before;
+        }
+        last = fan[0];
+    }
+
+    triangles.iter()
+             .filter(|triangle| triangle.alive && triangle.corners.iter().all(|&corner| corner < count))
+             .map(|triangle| triangle.corners)
+             .collect()
+}
+
+//Find a triangle that holds p by walking across whichever edge p is outside of. Falls back to
+//checking every triangle if the walk goes in circles.
+fn locate(vertices: &[(f64,f64)],triangles: &[Triangle],start: usize,p: (f64,f64)) -> Option<usize> {
+    let outside_edge = |index: usize| {
+        let triangle = &triangles[index];
+        (0..3).find(|&corner| {
+            let (b,c) = triangle.edge(corner);
+            orientation(vertices[b],vertices[c],p) < 0.0
+        })
+    };
+
+    let mut current = start;
+    for _ in 0..triangles.len() {
+        match outside_edge(current) {
+            None => return Some(current),
+            Some(corner) => match triangles[current].neighbors[corner] {
+                Some(neighbor) => current = neighbor,
+                None => break,
+            },
+        }
+    }
+
+    (0..triangles.len()).find(|&index| triangles[index].alive && outside_edge(index).is_none())
+}
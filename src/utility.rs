@@ -2,6 +2,7 @@ extern crate std;
 
 use std::ops::Rem;
 
+#[allow(clippy::manual_clamp)]
 pub fn clamp_to_u8(value: f64) -> u8 {
     let value = value.round() as i32;
     std::cmp::min(std::cmp::max(0,value),255) as u8
@@ -25,6 +26,7 @@ pub fn max_f32(lhs: f32,rhs: f32) -> f32 {
     }
 }
 
+#[allow(clippy::needless_return)]
 pub fn difference_theta(theta1: f32,theta2: f32) -> f32 {
 	//Find angle difference while taking wrapping into account.
 	return min_f32(
@@ -86,6 +88,7 @@ pub fn rgb_to_hsl(red: u8,green: u8,blue: u8) -> (f32,f32,f32) {
     (hue,saturation,lightness)
 }
 
+#[allow(clippy::manual_range_contains)]
 pub fn hsl_to_rgb(hue: f32,saturation: f32,lightness: f32) -> (u8,u8,u8) {
     if saturation == 0.0 {
         let v = clamp_to_u8((lightness * 255.0) as f64);
@@ -124,3 +127,88 @@ pub fn hsl_to_rgb(hue: f32,saturation: f32,lightness: f32) -> (u8,u8,u8) {
      clamp_to_u8(((blue + m) * 255.0) as f64))
 }
 
+//Parse a color written as # followed by six hex digits, like #1a2b3c.
+pub fn parse_hex_color(value: &str) -> Option<(u8,u8,u8)> {
+    let value = value.strip_prefix('#')?;
+    if value.len() != 6 || !value.is_ascii() {
+        return None;
+    }
+
+    let component = |start: usize| u8::from_str_radix(&value[start..start + 2],16).ok();
+    match (component(0),component(2),component(4)) {
+        (Some(red),Some(green),Some(blue)) => Some((red,green,blue)),
+        _ => None,
+    }
+}
+
+//Wrap an angle in radians to the range -PI to PI.
+pub fn wrap_angle(angle: f32) -> f32 {
+    let two_pi = 2.0 * std::f32::consts::PI;
+    let mut angle = angle.rem(two_pi);
+    if angle > std::f32::consts::PI {
+        angle -= two_pi;
+    }
+    else if angle < -std::f32::consts::PI {
+        angle += two_pi;
+    }
+    angle
+}
+
+//Convert an sRGB encoded channel from 0.0 to 1.0 into linear light.
+pub fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    }
+    else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+//Convert a linear light channel from 0.0 to 1.0 into sRGB encoding.
+pub fn linear_to_srgb(value: f32) -> f32 {
+    let value = max_f32(min_f32(value,1.0),0.0);
+    if value <= 0.0031308 {
+        value * 12.92
+    }
+    else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+//Convert an sRGB color into CIELAB with a D65 white point. Lightness runs from 0.0 to 100.0 and
+//equal distances are roughly equally noticeable differences in color.
+pub fn rgb_to_lab(red: u8,green: u8,blue: u8) -> (f32,f32,f32) {
+    let red = srgb_to_linear(red as f32 / 255.0);
+    let green = srgb_to_linear(green as f32 / 255.0);
+    let blue = srgb_to_linear(blue as f32 / 255.0);
+    let x = (0.4124 * red + 0.3576 * green + 0.1805 * blue) / 0.95047;
+    let y = 0.2126 * red + 0.7152 * green + 0.0722 * blue;
+    let z = (0.0193 * red + 0.1192 * green + 0.9505 * blue) / 1.08883;
+
+    let f = |t: f32| if t > 0.008856 { t.cbrt() } else { 7.787 * t + 16.0 / 116.0 };
+    let (fx,fy,fz) = (f(x),f(y),f(z));
+    (116.0 * fy - 16.0,500.0 * (fx - fy),200.0 * (fy - fz))
+}
+
+//Find how far along a Hilbert curve filling a 65536 x 65536 square the point x,y is.
+pub fn hilbert_index(x: u32,y: u32) -> u64 {
+    let (mut x,mut y) = (x,y);
+    let mut index = 0;
+    let mut size = 1 << 15;
+    while size > 0 {
+        let rx = ((x & size) > 0) as u32;
+        let ry = ((y & size) > 0) as u32;
+        index += size as u64 * size as u64 * ((3 * rx) ^ ry) as u64;
+
+        //Rotate the quadrant so the curve inside of it lines up.
+        if ry == 0 {
+            if rx == 1 {
+                x = 65535 - x;
+                y = 65535 - y;
+            }
+            std::mem::swap(&mut x,&mut y);
+        }
+        size /= 2;
+    }
+    index
+}
@@ -0,0 +1,111 @@
+extern crate line_splat;
+
+use std::fs::{self,File};
+use std::path::PathBuf;
+use std::process::{Command,Stdio};
+
+use line_splat::canvas::{Canvas,RgbCanvas};
+use line_splat::formats::{OutputFormat,write_canvas};
+use line_splat::metadata::Metadata;
+
+const STYLES: &[&str] = &["random","steered","energy","edgeweb","stipple","mesh","contour","spiral","squiggle","radial","hough","outline","regions"];
+
+//A directory that's only used by one test so tests can run in parallel.
+fn test_directory(name: &str) -> PathBuf {
+    let directory = std::env::temp_dir().join(format!("line-splat-{}-{}",name,std::process::id()));
+    fs::create_dir_all(&directory).unwrap();
+    directory
+}
+
+fn write_png(path: &PathBuf,width: usize,height: usize) {
+    let mut canvas = RgbCanvas::new(width,height).unwrap();
+    for x in 0..width {
+        canvas.blend(x,0,255,128,0,1.0);
+    }
+    let mut file = File::create(path).unwrap();
+    write_canvas(&canvas,&mut file,OutputFormat::Png,8,false,75,&Metadata::default()).unwrap();
+}
+
+fn line_splat() -> Command {
+    Command::new(env!("CARGO_BIN_EXE_line-splat"))
+}
+
+#[test]
+fn every_style_finishes_on_thin_images() {
+    let directory = test_directory("thin");
+    for &(width,height) in &[(1,1),(9,1),(1,9),(2,2)] {
+        let input = directory.join(format!("{}x{}.png",width,height));
+        write_png(&input,width,height);
+
+        for style in STYLES {
+            for curved in &[false,true] {
+                let output = directory.join(format!("{}x{}_{}_{}.png",width,height,style,curved));
+                let mut command = line_splat();
+                command.args(["--quiet","--line-count","2000","--dot-count","200","--style",style]).arg(&input).arg(&output);
+                if *curved {
+                    command.args(["--curved","--tsp","--low-poly","--squiggle-angle","45","--focus","salient","--extend-lines","--clip-to-regions","--detail","4","--gradient-operator","scharr","--luminance","linear","--color-gradient"]);
+                }
+
+                let status = command.status().unwrap();
+                assert!(status.success(),"{} style failed on a {}x{} image",style,width,height);
+                assert!(output.exists());
+            }
+        }
+    }
+    fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn failures_exit_with_their_own_codes() {
+    let directory = test_directory("exit-codes");
+    let input = directory.join("input.png");
+    write_png(&input,4,4);
+
+    let empty = directory.join("empty.ppm");
+    fs::write(&empty,b"P6\n0 0\n255\n").unwrap();
+    let garbage = directory.join("garbage.png");
+    fs::write(&garbage,b"not an image").unwrap();
+
+    let exit_code = |arguments: &[&std::ffi::OsStr]| line_splat().args(arguments).stderr(Stdio::null()).status().unwrap().code();
+    let output = directory.join("output.png");
+    assert_eq!(exit_code(&[input.as_os_str(),output.as_os_str(),"--pen-width".as_ref(),"0".as_ref()]),Some(2));
+    for &ink in &["ff0000","##ff0000","#ff00"] {
+        assert_eq!(exit_code(&[input.as_os_str(),output.as_os_str(),"--ink-color".as_ref(),ink.as_ref()]),Some(2),"{}",ink);
+    }
+    assert_eq!(exit_code(&[directory.join("missing.png").as_os_str(),output.as_os_str()]),Some(3));
+    assert_eq!(exit_code(&[garbage.as_os_str(),output.as_os_str()]),Some(4));
+    assert_eq!(exit_code(&[input.as_os_str(),directory.join("output.xyz").as_os_str()]),Some(5));
+    assert_eq!(exit_code(&[empty.as_os_str(),output.as_os_str()]),Some(6));
+    fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn time_limit_still_saves_the_image() {
+    let directory = test_directory("time-limit");
+    let input = directory.join("input.png");
+    write_png(&input,64,64);
+
+    let output = directory.join("output.png");
+    let status = line_splat().args(["--quiet","--time-limit","0","--line-count","100000000"]).arg(&input).arg(&output).status().unwrap();
+    assert!(status.success());
+    assert!(output.exists());
+    fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn replaying_a_stroke_log_redraws_the_same_image() {
+    let directory = test_directory("replay");
+    let input = directory.join("input.png");
+    write_png(&input,32,24);
+
+    let (log,drawn,replayed) = (directory.join("strokes.log"),directory.join("drawn.png"),directory.join("replayed.png"));
+    let status = line_splat().args(["--quiet","--line-count","3000","--style","energy","--supersample","2","--stroke-log"]).arg(&log).arg(&input).arg(&drawn).status().unwrap();
+    assert!(status.success());
+    let status = line_splat().args(["replay","--supersample","2"]).arg(&log).arg(&replayed).status().unwrap();
+    assert!(status.success());
+    assert!(fs::read(&drawn).unwrap() == fs::read(&replayed).unwrap());
+
+    let status = line_splat().args(["replay","--width","64","--ink-color","#ff0000"]).arg(&log).arg(&replayed).status().unwrap();
+    assert!(status.success());
+    fs::remove_dir_all(&directory).unwrap();
+}
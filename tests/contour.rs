@@ -0,0 +1,71 @@
+extern crate line_splat;
+
+use line_splat::contour::{LevelSpacing,contour_levels,isolines};
+use line_splat::imageprocessing::gaussian_blur;
+
+#[test]
+fn a_cone_has_one_round_contour() {
+    //Values grow with the distance from the center of a 41x41 grid.
+    let size = 41;
+    let values: Vec<f32> = (0..size * size).map(|index| ((index % size) as f32 - 20.0).hypot((index / size) as f32 - 20.0)).collect();
+    let lines = isolines(&values,size,size,10.5);
+
+    assert_eq!(lines.len(),1);
+    let line = &lines[0];
+    assert_eq!(line.first(),line.last(),"the contour isn't closed");
+    assert!(line.len() > 40);
+    for &(x,y) in line {
+        let distance = (x - 20.0).hypot(y - 20.0);
+        assert!((distance - 10.5).abs() < 0.3,"{:?} is {} from the center",(x,y),distance);
+    }
+}
+
+#[test]
+fn a_ramp_has_one_open_contour() {
+    //Values grow from left to right so the contour runs straight down the middle.
+    let (width,height) = (10,6);
+    let values: Vec<f32> = (0..width * height).map(|index| (index % width) as f32).collect();
+    let lines = isolines(&values,width,height,4.25);
+
+    assert_eq!(lines.len(),1);
+    assert_eq!(lines[0].len(),height);
+    assert!(lines[0].iter().all(|&(x,_)| (x - 4.25).abs() < 1.0e-6));
+    assert!(isolines(&values,width,height,20.0).is_empty());
+    assert!(isolines(&values[..width],width,1,4.25).is_empty());
+}
+
+#[test]
+fn levels_follow_the_spacing() {
+    let values: Vec<f32> = (0..101).map(|value| (value * value) as f32 / 100.0).collect();
+    let linear = contour_levels(&values,4,LevelSpacing::Linear);
+    assert_eq!(linear.len(),4);
+    assert!(linear.iter().zip(&[20.0,40.0,60.0,80.0]).all(|(level,expected)| (level - expected).abs() < 1.0e-4),"{:?}",linear);
+
+    //Most values are small so quantile levels bunch up toward the bottom.
+    let quantiles = contour_levels(&values,4,LevelSpacing::Quantile);
+    assert_eq!(quantiles.len(),4);
+    assert!(quantiles.windows(2).all(|pair| pair[0] < pair[1]));
+    assert!(quantiles[1] < 30.0 && quantiles[3] > 60.0,"{:?}",quantiles);
+
+    //Flat images have nothing to trace.
+    assert!(contour_levels(&[5.0; 50],8,LevelSpacing::Linear).is_empty());
+    assert!(contour_levels(&[5.0; 50],8,LevelSpacing::Quantile).is_empty());
+    assert!(contour_levels(&values,0,LevelSpacing::Linear).is_empty());
+}
+
+#[test]
+fn blurring_spreads_values_without_changing_their_total() {
+    let (width,height) = (15,11);
+    let mut values = vec![0.0; width * height];
+    values[5 * width + 7] = 100.0;
+    let blurred = gaussian_blur(&values,width as u32,height as u32,1.5).unwrap();
+
+    let total: f32 = blurred.iter().sum();
+    assert!((total - 100.0).abs() < 0.01,"total is {}",total);
+    assert!(blurred[5 * width + 7] < 20.0);
+    assert!((blurred[5 * width + 6] - blurred[5 * width + 8]).abs() < 1.0e-5);
+    assert!((blurred[4 * width + 7] - blurred[6 * width + 7]).abs() < 1.0e-5);
+
+    let flat = gaussian_blur(&[7.0; 12],4,3,3.0).unwrap();
+    assert!(flat.iter().all(|&value| (value - 7.0).abs() < 1.0e-4));
+}
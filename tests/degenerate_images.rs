@@ -0,0 +1,185 @@
+extern crate image;
+extern crate line_splat;
+
+use std::io::Cursor;
+
+use image::ImageFormat;
+
+use line_splat::Error;
+use line_splat::canvas::{Canvas,DownsampleFilter,LinearCanvas,RgbCanvas,RgbaCanvas,downsample};
+use line_splat::formats::{OutputFormat,load_rgb,write_canvas};
+use line_splat::imageprocessing::{gradient,non_maximum_suppression};
+use line_splat::metadata::{Metadata,read_metadata};
+use line_splat::painter::Painter;
+
+//Shapes that have tripped up the edge handling before: a single pixel, a single row, a single
+//column, and a small image with a one pixel border around a single inner pixel.
+const SHAPES: &[(u32,u32)] = &[(1,1),(7,1),(1,7),(2,2),(3,3)];
+
+//Striped so the gradient isn't zero everywhere.
+fn striped_rgb(width: u32,height: u32) -> Vec<u8> {
+    let mut pixels = vec![];
+    for y in 0..height {
+        for x in 0..width {
+            let value = if (x + y) % 2 == 0 { 255 } else { 0 };
+            pixels.extend_from_slice(&[value,value / 2,0]);
+        }
+    }
+    pixels
+}
+
+#[test]
+fn gradient_handles_thin_images() {
+    for &(width,height) in SHAPES {
+        let result = gradient(&striped_rgb(width,height),width,height).unwrap();
+        assert_eq!(result.len(),(width * height * 2) as usize);
+        assert!(result.iter().all(|value| value.is_finite()));
+    }
+}
+
+#[test]
+fn gradient_rejects_bad_dimensions() {
+    assert!(matches!(gradient(&[],0,0),Err(Error::EmptyImage)));
+    assert!(matches!(gradient(&[],0,5),Err(Error::EmptyImage)));
+    assert!(matches!(gradient(&[0; 3],2,2),Err(Error::InvalidParameter(_))));
+
+    //Large enough that (y * width + x) * 3 overflows u32.
+    assert!(matches!(gradient(&[0; 12],u32::MAX,u32::MAX),Err(Error::InvalidParameter(_))));
+}
+
+#[test]
+fn non_maximum_suppression_handles_thin_images() {
+    for &(width,height) in SHAPES {
+        let gradient = gradient(&striped_rgb(width,height),width,height).unwrap();
+        let edges = non_maximum_suppression(&gradient,width,height).unwrap();
+        assert_eq!(edges.len(),(width * height) as usize);
+    }
+
+    assert!(matches!(non_maximum_suppression(&[],0,0),Err(Error::EmptyImage)));
+    assert!(matches!(non_maximum_suppression(&[0.0; 2],1,2),Err(Error::InvalidParameter(_))));
+    assert!(matches!(non_maximum_suppression(&[0.0; 8],u32::MAX,u32::MAX),Err(Error::InvalidParameter(_))));
+}
+
+#[test]
+fn canvas_rejects_empty_and_huge_sizes() {
+    assert!(matches!(RgbCanvas::new(0,0),Err(Error::EmptyImage)));
+    assert!(matches!(RgbaCanvas::new(5,0),Err(Error::EmptyImage)));
+    assert!(matches!(LinearCanvas::new(0,5),Err(Error::EmptyImage)));
+    assert!(matches!(RgbCanvas::new(usize::MAX,2),Err(Error::InvalidParameter(_))));
+    assert!(matches!(LinearCanvas::new(usize::MAX / 4,usize::MAX / 4),Err(Error::InvalidParameter(_))));
+}
+
+#[test]
+fn painter_stays_inside_thin_canvases() {
+    let far = [i32::MIN,-1000,-1,0,1,1000,i32::MAX];
+    for &(width,height) in SHAPES {
+        for &pen_width in &[1.0,3.0] {
+            let mut canvas = RgbCanvas::new(width as usize,height as usize).unwrap();
+            let mut painter = Painter::new();
+            painter.set_pen(255,255,255);
+            painter.set_pen_width(pen_width);
+            for &x1 in &far {
+                for &y1 in &far {
+                    painter.line(&mut canvas,x1,y1,0,0);
+                    painter.line(&mut canvas,x1,y1,y1.saturating_neg(),x1);
+                    painter.polyline(&mut canvas,&[(x1 as f32,y1 as f32),(0.5,0.5),(y1 as f32,x1 as f32)]);
+
+                    for &(x2,y2) in &[(0,0),(y1.saturating_neg(),x1),(i32::MAX,i32::MIN)] {
+                        Painter::line_foreach(width as usize,height as usize,x1,y1,x2,y2,|x,y| {
+                            assert!(x < width as usize && y < height as usize);
+                        });
+                    }
+                }
+            }
+            painter.arc(&mut canvas,0.0,0.0,1.0e6,0.0,std::f32::consts::PI);
+            painter.cubic_bezier(&mut canvas,-1.0e9,0.0,0.0,1.0e9,1.0e9,0.0,0.0,-1.0e9);
+        }
+    }
+}
+
+#[test]
+fn painter_ignores_empty_canvas_sizes() {
+    Painter::line_foreach(0,0,0,0,10,10,|_,_| panic!("Nothing should be drawn"));
+    Painter::polygon_foreach(0,5,&[(0.0,0.0),(5.0,0.0),(5.0,5.0)],|_,_| panic!("Nothing should be drawn"));
+    Painter::disc_foreach(5,0,2.0,2.0,2.0,|_,_| panic!("Nothing should be drawn"));
+}
+
+#[test]
+fn downsample_handles_thin_canvases() {
+    for &(width,height) in SHAPES {
+        let mut canvas = RgbCanvas::new(width as usize,height as usize).unwrap();
+        canvas.blend(0,0,255,255,255,1.0);
+        for &filter in &[DownsampleFilter::Box,DownsampleFilter::Lanczos] {
+            let result = downsample(&canvas,4,filter).unwrap();
+            assert_eq!((result.width(),result.height()),(1,1));
+        }
+    }
+
+    let canvas = RgbCanvas::new(2,2).unwrap();
+    assert!(matches!(downsample(&canvas,0,DownsampleFilter::Box),Err(Error::InvalidParameter(_))));
+}
+
+#[test]
+fn thin_images_round_trip_through_every_format() {
+    let formats = [OutputFormat::Png,OutputFormat::Jpeg,OutputFormat::Bmp,OutputFormat::Gif,OutputFormat::Pnm,OutputFormat::Tga,OutputFormat::Tiff];
+    for &(width,height) in SHAPES {
+        let mut canvas = RgbCanvas::new(width as usize,height as usize).unwrap();
+        canvas.blend(0,0,200,100,50,1.0);
+
+        for &format in &formats {
+            let mut bytes = vec![];
+            write_canvas(&canvas,&mut bytes,format,8,false,90,&Metadata::default()).unwrap();
+
+            let image_format = match format {
+                OutputFormat::Png => ImageFormat::PNG,
+                OutputFormat::Jpeg => ImageFormat::JPEG,
+                OutputFormat::Bmp => ImageFormat::BMP,
+                OutputFormat::Gif => ImageFormat::GIF,
+                OutputFormat::Pnm => ImageFormat::PPM,
+                OutputFormat::Tga => ImageFormat::TGA,
+                OutputFormat::Tiff => ImageFormat::TIFF,
+            };
+            let image = load_rgb(&bytes,image_format).unwrap();
+            assert_eq!(image.dimensions(),(width,height),"{} at {}x{}",format.name(),width,height);
+        }
+    }
+}
+
+#[test]
+fn write_canvas_rejects_bad_parameters() {
+    let canvas = RgbCanvas::new(1,1).unwrap();
+    let mut bytes = Cursor::new(vec![]);
+    let metadata = Metadata::default();
+    assert!(matches!(write_canvas(&canvas,&mut bytes,OutputFormat::Png,12,false,75,&metadata),Err(Error::InvalidParameter(_))));
+    assert!(matches!(write_canvas(&canvas,&mut bytes,OutputFormat::Jpeg,8,false,0,&metadata),Err(Error::InvalidParameter(_))));
+    assert!(matches!(write_canvas(&canvas,&mut bytes,OutputFormat::Jpeg,16,false,75,&metadata),Err(Error::UnsupportedFormat(_))));
+    assert!(matches!(write_canvas(&canvas,&mut bytes,OutputFormat::Bmp,8,true,75,&metadata),Err(Error::UnsupportedFormat(_))));
+}
+
+#[test]
+fn load_rgb_rejects_empty_images() {
+    assert!(matches!(load_rgb(b"P6\n0 0\n255\n",ImageFormat::PPM),Err(Error::EmptyImage)));
+    assert!(load_rgb(&[],ImageFormat::PNG).is_err());
+}
+
+#[test]
+fn read_metadata_survives_truncated_files() {
+    let mut canvas = RgbCanvas::new(3,3).unwrap();
+    canvas.blend(1,1,255,0,0,1.0);
+    let metadata = Metadata {
+        orientation: None,
+        artist: Some("Someone".to_string()),
+        copyright: Some("Nobody".to_string()),
+        icc_profile: Some(vec![7; 300]),
+    };
+
+    for &format in &[OutputFormat::Png,OutputFormat::Jpeg,OutputFormat::Tiff] {
+        let mut bytes = vec![];
+        write_canvas(&canvas,&mut bytes,format,8,false,75,&metadata).unwrap();
+        assert_eq!(read_metadata(&bytes),metadata);
+
+        for length in 0..bytes.len() {
+            read_metadata(&bytes[..length]);
+        }
+    }
+}
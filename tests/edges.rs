@@ -0,0 +1,97 @@
+extern crate line_splat;
+
+use line_splat::imageprocessing::{douglas_peucker,edge_chains};
+use line_splat::path::Path;
+
+fn edge_map(width: usize,height: usize,pixels: &[(usize,usize)]) -> Vec<u8> {
+    let mut edges = vec![0; width * height];
+    for &(x,y) in pixels {
+        edges[y * width + x] = 255;
+    }
+    edges
+}
+
+//Every step along a chain moves to a touching pixel.
+fn is_connected(chain: &[(f32,f32)]) -> bool {
+    chain.windows(2).all(|pair| (pair[1].0 - pair[0].0).abs() <= 1.0 && (pair[1].1 - pair[0].1).abs() <= 1.0 && pair[0] != pair[1])
+}
+
+#[test]
+fn a_line_is_traced_from_end_to_end() {
+    //A staircase running down and to the right.
+    let pixels: Vec<(usize,usize)> = (0..20).map(|index| (3 + index,2 + index / 2)).collect();
+    let chains = edge_chains(&edge_map(30,15,&pixels),30,15,0,0.0).unwrap();
+
+    assert_eq!(chains.len(),1);
+    let chain = &chains[0];
+    assert_eq!(chain.len(),20);
+    assert!(is_connected(chain));
+    let ends = [chain[0],chain[chain.len() - 1]];
+    assert!(ends.contains(&(3.0,2.0)) && ends.contains(&(22.0,11.0)),"{:?}",ends);
+}
+
+#[test]
+fn loops_end_where_they_start() {
+    //The outline of a 10x6 rectangle.
+    let mut pixels = vec![];
+    for x in 2..12 {
+        pixels.push((x,3));
+        pixels.push((x,8));
+    }
+    for y in 4..8 {
+        pixels.push((2,y));
+        pixels.push((11,y));
+    }
+    let chains = edge_chains(&edge_map(15,12,&pixels),15,12,0,0.0).unwrap();
+
+    assert_eq!(chains.len(),1);
+    let chain = &chains[0];
+    assert_eq!(chain.first(),chain.last());
+    assert_eq!(chain.len(),pixels.len() + 1);
+    assert!(is_connected(chain));
+
+    //Simplifying keeps the loop closed and cuts it down to its corners.
+    let simplified = edge_chains(&edge_map(15,12,&pixels),15,12,0,0.5).unwrap();
+    assert_eq!(simplified[0].first(),simplified[0].last());
+    assert!(simplified[0].len() <= 6,"{:?}",simplified[0]);
+}
+
+#[test]
+fn branches_join_the_edge_they_split_from() {
+    //A horizontal line with a vertical branch hanging down from its middle, and a speck that's too
+    //short to keep.
+    let mut pixels: Vec<(usize,usize)> = (1..20).map(|x| (x,2)).collect();
+    pixels.extend((3..10).map(|y| (10,y)));
+    pixels.extend_from_slice(&[(25,10),(26,10)]);
+    let chains = edge_chains(&edge_map(30,12,&pixels),30,12,3,0.0).unwrap();
+
+    assert_eq!(chains.len(),2,"{:?}",chains);
+    assert!(chains.iter().all(|chain| is_connected(chain)));
+    let total: usize = chains.iter().map(|chain| chain.len()).sum();
+    assert_eq!(total,19 + 7 + 1,"every pixel is used once plus one pixel where the branch joins");
+    assert!(chains.iter().any(|chain| chain.contains(&(10.0,2.0)) && chain.contains(&(10.0,9.0))));
+}
+
+#[test]
+fn douglas_peucker_keeps_corners() {
+    let points = [(0.0,0.0),(1.0,0.1),(2.0,-0.1),(3.0,0.0),(3.1,1.0),(3.0,2.0),(3.0,3.0)];
+    assert_eq!(douglas_peucker(&points,0.5),vec![(0.0,0.0),(3.0,0.0),(3.0,3.0)]);
+    assert_eq!(douglas_peucker(&points,0.0).len(),points.len());
+    assert_eq!(douglas_peucker(&points[..2],5.0),points[..2].to_vec());
+}
+
+#[test]
+fn smooth_paths_round_off_corners() {
+    let points = [(0.0,0.0),(10.0,0.0),(10.0,10.0)];
+    let flattened = Path::smooth(&points).unwrap().flatten();
+    assert_eq!(flattened[0],(0.0,0.0));
+    assert_eq!(flattened[flattened.len() - 1],(10.0,10.0));
+    assert!(!flattened.contains(&(10.0,0.0)),"the corner wasn't rounded off");
+
+    let square = [(0.0,0.0),(10.0,0.0),(10.0,10.0),(0.0,10.0),(0.0,0.0)];
+    let path = Path::smooth(&square).unwrap();
+    assert!(path.is_closed());
+    assert_eq!(path.start(),(5.0,0.0));
+    let flattened = path.flatten();
+    assert_eq!(flattened[0],flattened[flattened.len() - 1]);
+}
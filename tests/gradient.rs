@@ -0,0 +1,90 @@
+extern crate line_splat;
+
+use line_splat::imageprocessing::{GradientOperator,GradientOptions,LuminanceModel,gradient,gradient_with,luminance_with};
+
+const OPERATORS: &[GradientOperator] = &[GradientOperator::Sobel,GradientOperator::Scharr,GradientOperator::Prewitt,GradientOperator::CentralDifference];
+
+//An image with left on the left side of column split and right from there on.
+fn split(width: usize,height: usize,split: usize,left: [u8; 3],right: [u8; 3]) -> Vec<u8> {
+    let mut pixels = vec![];
+    for _ in 0..height {
+        for x in 0..width {
+            pixels.extend_from_slice(if x < split { &left } else { &right });
+        }
+    }
+    pixels
+}
+
+fn options(operator: GradientOperator,luminance: LuminanceModel,color: bool) -> GradientOptions {
+    GradientOptions {
+        operator,
+        luminance,
+        color,
+    }
+}
+
+#[test]
+fn operators_are_equally_strong_across_straight_edges() {
+    let pixels = split(10,6,5,[20,20,20],[120,120,120]);
+    let sobel = gradient(&pixels,10,6).unwrap();
+    assert_eq!(gradient_with(&pixels,10,6,&GradientOptions::default()).unwrap(),sobel);
+
+    //The edge is between columns 4 and 5 so both of them see all of it.
+    let index = (3 * 10 + 4) * 2;
+    assert!((sobel[index] - 400.0).abs() < 1.0e-3,"{}",sobel[index]);
+    for &operator in OPERATORS {
+        let result = gradient_with(&pixels,10,6,&options(operator,LuminanceModel::Rec601,false)).unwrap();
+        for &column in &[4,5] {
+            let index = (3 * 10 + column) * 2;
+            assert!((result[index] - sobel[index]).abs() < 1.0e-3,"{:?} measured {}",operator,result[index]);
+            assert!(result[index + 1].abs() < 1.0e-6);
+        }
+        assert_eq!(result[(3 * 10 + 1) * 2],0.0);
+        assert_eq!(GradientOperator::from_name(&format!("{:?}",operator).to_uppercase()).is_some(),operator != GradientOperator::CentralDifference);
+    }
+    assert_eq!(GradientOperator::from_name("central"),Some(GradientOperator::CentralDifference));
+    assert_eq!(GradientOperator::from_name("canny"),None);
+}
+
+#[test]
+fn luminance_models_weigh_colors_differently() {
+    let pixels = [0,255,0,128,128,128];
+    let rec601 = luminance_with(&pixels,2,1,LuminanceModel::Rec601).unwrap();
+    let rec709 = luminance_with(&pixels,2,1,LuminanceModel::Rec709).unwrap();
+    let linear = luminance_with(&pixels,2,1,LuminanceModel::Linear).unwrap();
+    assert!((rec601[0] - 0.587 * 255.0).abs() < 1.0e-3);
+    assert!((rec709[0] - 0.7152 * 255.0).abs() < 1.0e-3);
+    assert!((linear[0] - 0.7152 * 255.0).abs() < 1.0e-3);
+
+    //Middle gray is much darker in linear light.
+    assert!((rec601[1] - 128.0).abs() < 1.0e-3 && (rec709[1] - 128.0).abs() < 1.0e-3);
+    assert!((linear[1] - 0.2158 * 255.0).abs() < 0.1,"{}",linear[1]);
+
+    assert_eq!(LuminanceModel::from_name("Rec709"),Some(LuminanceModel::Rec709));
+    assert_eq!(LuminanceModel::from_name("srgb"),None);
+}
+
+#[test]
+fn color_gradient_finds_edges_between_equally_bright_colors() {
+    //Both sides are about 112 with the Rec. 601 weights.
+    let pixels = split(10,6,5,[200,80,60],[60,140,90]);
+    let index = (3 * 10 + 4) * 2;
+    let plain = gradient(&pixels,10,6).unwrap();
+    assert!(plain[index] < 20.0,"{}",plain[index]);
+
+    let color = gradient_with(&pixels,10,6,&options(GradientOperator::Sobel,LuminanceModel::Rec601,true)).unwrap();
+    assert!(color[index] > 200.0,"{}",color[index]);
+    //The channels mostly get darker to the right so the gradient points left.
+    assert!(color[index + 1].cos() < -0.999,"{}",color[index + 1]);
+
+    //Gray images measure the same in color.
+    let gray = split(10,6,3,[40,40,40],[200,200,200]);
+    let plain = gradient(&gray,10,6).unwrap();
+    let color = gradient_with(&gray,10,6,&options(GradientOperator::Sobel,LuminanceModel::Rec601,true)).unwrap();
+    for (plain,color) in plain.chunks(2).zip(color.chunks(2)) {
+        assert!((plain[0] - color[0]).abs() < 1.0e-2,"{} vs {}",plain[0],color[0]);
+        if plain[0] > 0.0 {
+            assert!((plain[1] - color[1]).abs() < 1.0e-4);
+        }
+    }
+}
@@ -0,0 +1,61 @@
+extern crate line_splat;
+
+use line_splat::imageprocessing::{HoughSegment,hough_lines};
+
+fn edge_map(width: usize,height: usize,pixels: &[(usize,usize)]) -> Vec<u8> {
+    let mut edges = vec![0; width * height];
+    for &(x,y) in pixels {
+        edges[y * width + x] = 255;
+    }
+    edges
+}
+
+//Check that a segment runs between two points in either direction.
+fn runs_between(segment: &HoughSegment,(x1,y1): (f32,f32),(x2,y2): (f32,f32)) -> bool {
+    let close = |x: f32,y: f32,other_x: f32,other_y: f32| (x - other_x).hypot(y - other_y) < 1.5;
+    (close(segment.x1,segment.y1,x1,y1) && close(segment.x2,segment.y2,x2,y2)) ||
+    (close(segment.x1,segment.y1,x2,y2) && close(segment.x2,segment.y2,x1,y1))
+}
+
+#[test]
+fn straight_edges_are_found_strongest_first() {
+    //A long horizontal edge, a shorter diagonal one, and a vertical one with scattered pixels
+    //around them that don't line up.
+    let mut pixels: Vec<(usize,usize)> = (10..90).map(|x| (x,20)).collect();
+    pixels.extend((30..71).map(|offset| (offset,offset)));
+    pixels.extend((10..70).map(|y| (5,y)));
+    pixels.extend((0..40).map(|index| ((index * 37) % 100,(index * 53) % 80)));
+    let segments = hough_lines(&edge_map(100,80,&pixels),100,80,10,20,2.0).unwrap();
+
+    assert_eq!(segments.len(),3,"{:?}",segments);
+    assert!(runs_between(&segments[0],(10.0,20.0),(89.0,20.0)),"{:?}",segments[0]);
+    assert!(runs_between(&segments[1],(5.0,10.0),(5.0,69.0)),"{:?}",segments[1]);
+    assert!(runs_between(&segments[2],(30.0,30.0),(70.0,70.0)),"{:?}",segments[2]);
+    assert!(segments[0].votes >= 80 && segments[1].votes >= 60 && segments[2].votes >= 41);
+}
+
+#[test]
+fn gaps_split_lines() {
+    //Two pieces of the same line. Only the longer piece is kept when the gap is too wide to jump.
+    let mut pixels: Vec<(usize,usize)> = (0..30).map(|x| (x,10)).collect();
+    pixels.extend((40..50).map(|x| (x,10)));
+    let edges = edge_map(60,20,&pixels);
+
+    let segments = hough_lines(&edges,60,20,1,5,3.0).unwrap();
+    assert_eq!(segments.len(),1);
+    assert!(runs_between(&segments[0],(0.0,10.0),(29.0,10.0)),"{:?}",segments[0]);
+
+    let segments = hough_lines(&edges,60,20,1,5,20.0).unwrap();
+    assert!(runs_between(&segments[0],(0.0,10.0),(49.0,10.0)),"{:?}",segments[0]);
+    assert_eq!(segments[0].votes,40);
+}
+
+#[test]
+fn short_or_missing_edges_make_no_lines() {
+    let pixels: Vec<(usize,usize)> = (0..15).map(|x| (x,3)).collect();
+    let edges = edge_map(20,8,&pixels);
+    assert!(hough_lines(&edges,20,8,5,16,2.0).unwrap().is_empty());
+    assert!(hough_lines(&edges,20,8,0,5,2.0).unwrap().is_empty());
+    assert!(hough_lines(&[0; 160],20,8,5,5,2.0).unwrap().is_empty());
+    assert!(hough_lines(&edges[..100],20,8,5,5,2.0).is_err());
+}
@@ -0,0 +1,60 @@
+extern crate line_splat;
+
+use line_splat::painter::{LineJoin,Painter};
+use line_splat::path::Path;
+
+const JOINS: &[LineJoin] = &[LineJoin::Miter,LineJoin::Round,LineJoin::Bevel];
+
+fn covered(path: &Path,pen_width: f32,line_join: LineJoin) -> Vec<(usize,usize)> {
+    let mut pixels = vec![];
+    Painter::path_foreach(40,40,path,pen_width,line_join,|x,y| pixels.push((x,y)));
+    pixels
+}
+
+#[test]
+fn joins_fill_the_outside_of_the_turn() {
+    //Right and then down, so the outside of the turn is above and to the right of 20,10.
+    let path = Path::polyline(&[(2.0,10.0),(20.0,10.0),(20.0,28.0)]).unwrap();
+    for &line_join in JOINS {
+        let pixels = covered(&path,8.0,line_join);
+        for y in 6..10 {
+            for x in 20..24 {
+                let (dx,dy) = (x as f32 + 0.5 - 20.0,10.0 - (y as f32 + 0.5));
+                let inside = match line_join {
+                    LineJoin::Miter => true,
+                    LineJoin::Round => dx.hypot(dy) < 3.5,
+                    LineJoin::Bevel => dx + dy < 3.5,
+                };
+                if inside {
+                    assert!(pixels.contains(&(x,y)),"{:?} missed {},{}",line_join,x,y);
+                }
+            }
+        }
+
+        //No join reaches past the miter on the outside of the turn, which is as far as the pen's
+        //edges reach above and to the right of 20,10.
+        assert!(pixels.iter().all(|&(x,y)| x < 24 && y >= 6),"{:?} spilled out",line_join);
+    }
+}
+
+#[test]
+fn every_pixel_is_visited_once() {
+    let mut curve = Path::new(3.0,30.0);
+    curve.cubic_to(10.0,-10.0,30.0,50.0,36.0,5.0);
+    curve.line_to(20.0,36.0);
+    let mut closed = Path::polyline(&[(5.0,5.0),(35.0,8.0),(12.0,20.0),(30.0,34.0)]).unwrap();
+    closed.close();
+
+    for path in &[curve,closed] {
+        for &pen_width in &[1.0,3.0,8.0] {
+            for &line_join in JOINS {
+                let mut pixels = covered(path,pen_width,line_join);
+                let count = pixels.len();
+                assert!(count > 0);
+                pixels.sort();
+                pixels.dedup();
+                assert_eq!(pixels.len(),count,"{:?} at width {} visits pixels twice",line_join,pen_width);
+            }
+        }
+    }
+}
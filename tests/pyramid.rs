@@ -0,0 +1,77 @@
+extern crate line_splat;
+
+use line_splat::Error;
+use line_splat::imageprocessing::{gaussian_pyramid,gradient,multiscale_gradient};
+
+//Gray vertical stripes that get darker and lighter three times across the image no matter how wide
+//it is.
+fn waves(width: usize,height: usize) -> Vec<u8> {
+    let mut pixels = vec![];
+    for _ in 0..height {
+        for x in 0..width {
+            let u = (x as f32 + 0.5) / width as f32;
+            let value = (128.0 + 100.0 * (u * 3.0 * 2.0 * std::f32::consts::PI).sin()).round() as u8;
+            pixels.extend_from_slice(&[value,value,value]);
+        }
+    }
+    pixels
+}
+
+#[test]
+fn pyramid_levels_halve_down_to_a_pixel() {
+    let values = vec![42.0; 13 * 5];
+    let pyramid = gaussian_pyramid(&values,13,5,10).unwrap();
+    let sizes: Vec<(u32,u32)> = pyramid.iter().map(|level| (level.width,level.height)).collect();
+    assert_eq!(sizes,vec![(13,5),(7,3),(4,2),(2,1),(1,1)]);
+    for level in &pyramid {
+        assert_eq!(level.values.len(),(level.width * level.height) as usize);
+        assert!(level.values.iter().all(|&value| (value - 42.0).abs() < 1.0e-3));
+    }
+
+    assert_eq!(gaussian_pyramid(&values,13,5,2).unwrap().len(),2);
+    assert_eq!(gaussian_pyramid(&values,13,5,0).unwrap().len(),1);
+    assert!(matches!(gaussian_pyramid(&values,13,6,2),Err(Error::InvalidParameter(_))));
+}
+
+#[test]
+fn level_zero_is_the_plain_gradient() {
+    let pixels = waves(30,20);
+    let plain = gradient(&pixels,30,20).unwrap();
+    assert_eq!(multiscale_gradient(&pixels,30,20,0.0).unwrap(),plain);
+    assert_eq!(multiscale_gradient(&pixels,30,20,-1.0).unwrap(),plain);
+    assert!(matches!(multiscale_gradient(&pixels,30,20,f32::NAN),Err(Error::InvalidParameter(_))));
+}
+
+#[test]
+fn shrinking_matches_a_smaller_image() {
+    //The same picture at two sizes. Measured two levels down, the bigger one's gradient should be
+    //about the same as the smaller one's at full size, where it's about four times as strong.
+    let (small,large) = (waves(64,16),waves(256,64));
+    let small_gradient = gradient(&small,64,16).unwrap();
+    let large_gradient = gradient(&large,256,64).unwrap();
+    let shrunk_gradient = multiscale_gradient(&large,256,64,2.0).unwrap();
+
+    let (mut small_total,mut large_total,mut shrunk_total) = (0.0,0.0,0.0);
+    for y in 2..14 {
+        for x in 2..62 {
+            let (small_index,large_index) = ((y * 64 + x) * 2,(y * 4 * 256 + x * 4) * 2);
+            small_total += small_gradient[small_index];
+            large_total += large_gradient[large_index];
+            shrunk_total += shrunk_gradient[large_index];
+
+            //Where the gradient is strong enough to have a direction it points the same way.
+            if small_gradient[small_index] > 20.0 {
+                assert!((small_gradient[small_index + 1] - shrunk_gradient[large_index + 1]).cos() > 0.99);
+            }
+        }
+    }
+    assert!((shrunk_total / small_total - 1.0f32).abs() < 0.15,"{} vs {}",shrunk_total,small_total);
+    assert!(large_total / small_total < 0.4,"{} vs {}",large_total,small_total);
+
+    //Between two levels the gradient is between theirs.
+    let halfway = multiscale_gradient(&large,256,64,1.5).unwrap();
+    let level_one = multiscale_gradient(&large,256,64,1.0).unwrap();
+    let index = (32 * 256 + 100) * 2;
+    let (lower,upper) = (level_one[index].min(shrunk_gradient[index]),level_one[index].max(shrunk_gradient[index]));
+    assert!(halfway[index] >= lower && halfway[index] <= upper);
+}
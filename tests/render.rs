@@ -0,0 +1,283 @@
+extern crate image;
+extern crate line_splat;
+
+use image::RgbImage;
+
+use line_splat::Error;
+use line_splat::canvas::{Canvas,RgbCanvas};
+use line_splat::imageprocessing::{angle_to_direction,gradient};
+use line_splat::render::{Detail,Focus,Progress,RenderStatus,Renderer,Source,Style};
+use line_splat::strokelog::StrokeShape;
+
+const STYLES: &[Style] = &[Style::Random,Style::Steered,Style::Energy,Style::EdgeWeb,Style::Stipple,Style::Mesh,Style::Contour,Style::Spiral,Style::Squiggle,Style::Radial,Style::Hough,Style::Outline,Style::Regions];
+
+//A diagonal split so edgeweb has edges to find.
+fn split_image(width: u32,height: u32) -> RgbImage {
+    RgbImage::from_fn(width,height,|x,y| if x > y { image::Rgb([255,200,0]) } else { image::Rgb([0,40,90]) })
+}
+
+#[test]
+fn progress_is_reported_from_start_to_finish() {
+    for &style in STYLES {
+        let source = Source::new(split_image(48,32),48,32).unwrap();
+        let mut canvas = RgbCanvas::new(48,32).unwrap();
+        let mut renderer = Renderer::new(style);
+        renderer.set_line_count(5000);
+        renderer.set_dot_count(300);
+
+        let mut reports: Vec<Progress> = vec![];
+        let status = renderer.render(&source,&mut canvas,|progress| {
+            reports.push(progress);
+            true
+        }).unwrap();
+
+        assert_eq!(status,RenderStatus::Finished);
+        assert_eq!(reports.first().unwrap().completed,0);
+        let last = reports.last().unwrap();
+        assert!(last.total > 0 && last.completed == last.total,"{:?} finished at {:?}",style,last);
+        assert!(reports.windows(2).all(|pair| pair[0].completed <= pair[1].completed));
+    }
+}
+
+#[test]
+fn cancelling_stops_drawing() {
+    for &style in STYLES {
+        let source = Source::new(split_image(48,32),48,32).unwrap();
+        let mut canvas = RgbCanvas::new(48,32).unwrap();
+        let renderer = Renderer::new(style);
+
+        match renderer.render(&source,&mut canvas,|_| false).unwrap() {
+            RenderStatus::Cancelled(progress) => assert_eq!(progress.completed,0),
+            RenderStatus::Finished => panic!("{:?} ignored the cancellation",style),
+        }
+
+        //Nothing was drawn before the first progress report. Stipple still draws its dots wherever
+        //they were when relaxing stopped.
+        if style == Style::Stipple {
+            continue;
+        }
+        for y in 0..canvas.height() {
+            for x in 0..canvas.width() {
+                assert_eq!(canvas.pixel(x,y),(0.0,0.0,0.0,1.0));
+            }
+        }
+    }
+}
+
+#[test]
+fn render_rejects_a_mismatched_canvas() {
+    let source = Source::new(split_image(8,8),16,16).unwrap();
+    let mut canvas = RgbCanvas::new(8,8).unwrap();
+    let result = Renderer::new(Style::Random).render(&source,&mut canvas,|_| true);
+    assert!(matches!(result,Err(Error::InvalidParameter(_))));
+}
+
+//Counts how many times each pixel is drawn over.
+struct CountingCanvas {
+    width: usize,
+    height: usize,
+    counts: Vec<u32>,
+}
+
+impl Canvas for CountingCanvas {
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    fn height(&self) -> usize {
+        self.height
+    }
+
+    fn blend(&mut self,x: usize,y: usize,_red: u8,_green: u8,_blue: u8,_alpha: f32) {
+        self.counts[y * self.width + x] += 1;
+    }
+
+    fn pixel(&self,x: usize,y: usize) -> (f32,f32,f32,f32) {
+        let count = self.counts[y * self.width + x] as f32;
+        (count,count,count,1.0)
+    }
+}
+
+#[test]
+fn low_poly_triangles_cover_every_pixel_once() {
+    let (width,height) = (61,37);
+    let source = Source::new(split_image(width as u32,height as u32),width as u32,height as u32).unwrap();
+    let mut canvas = CountingCanvas {
+        width,
+        height,
+        counts: vec![0; width * height],
+    };
+    let mut renderer = Renderer::new(Style::Mesh);
+    renderer.set_mesh_points(150);
+    renderer.set_low_poly(true);
+    renderer.set_pen_opacity(0.5);
+    assert_eq!(renderer.render(&source,&mut canvas,|_| true).unwrap(),RenderStatus::Finished);
+
+    assert!(canvas.counts.iter().all(|&count| count == 1),"{:?}",canvas.counts);
+}
+
+#[test]
+fn squiggles_are_one_path_that_waves_over_dark_areas() {
+    //White on the left and black on the right.
+    let (width,height) = (60,40);
+    let image = RgbImage::from_fn(width,height,|x,_| if x < width / 2 { image::Rgb([255,255,255]) } else { image::Rgb([0,0,0]) });
+    let source = Source::new(image,width,height).unwrap();
+    let mut canvas = RgbCanvas::new(width as usize,height as usize).unwrap();
+    let mut renderer = Renderer::new(Style::Squiggle);
+    renderer.set_spacing(6.0);
+    renderer.set_amplitude(2.0);
+
+    let mut pieces = vec![];
+    renderer.render_logged(&source,&mut canvas,|_| true,|record| {
+        if let StrokeShape::Polyline { ref points } = record.shape {
+            pieces.push(points.clone());
+        }
+        Ok(())
+    }).unwrap();
+    //The path never leaves the canvas so it's drawn as a single polyline.
+    assert_eq!(pieces.len(),1,"the path was split up");
+
+    //Seven lines fit with their centers at 2, 8, ... 38. Connections between lines run along the
+    //left and right edges.
+    let points: Vec<(f32,f32)> = pieces.iter().flat_map(|piece| piece.iter().cloned()).collect();
+    assert!(points.iter().all(|&(x,y)| x >= 0.0 && y >= 0.0 && x <= width as f32 && y <= height as f32));
+    let offset = |y: f32| (y - 2.0) - ((y - 2.0) / 6.0).round() * 6.0;
+    assert!(points.iter().filter(|&&(x,_)| x > 1.0 && x < 20.0).all(|&(_,y)| offset(y).abs() < 0.05));
+    let swing = points.iter().filter(|&&(x,_)| x > 40.0 && x < 59.0).map(|&(_,y)| offset(y).abs()).fold(0.0,f32::max);
+    assert!(swing > 1.8 && swing <= 2.0 + 1.0e-4,"dark areas swing {} pixels",swing);
+
+    //A spiral is cut into runs where it leaves the canvas but each run is still one path.
+    let mut renderer = Renderer::new(Style::Spiral);
+    renderer.set_spacing(4.0);
+    let mut runs: Vec<Vec<(f32,f32)>> = vec![];
+    renderer.render_logged(&source,&mut canvas,|_| true,|record| {
+        if let StrokeShape::Polyline { ref points } = record.shape {
+            runs.push(points.clone());
+        }
+        Ok(())
+    }).unwrap();
+    assert!(runs.len() > 1);
+    assert!(runs.windows(2).all(|pair| pair[0].last() != pair[1].first()),"a run was split up");
+
+    renderer.set_spacing(0.5);
+    assert!(matches!(renderer.render(&source,&mut canvas,|_| true),Err(Error::InvalidParameter(_))));
+}
+
+#[test]
+fn radial_lines_point_at_the_focus() {
+    let (width,height) = (64,48);
+    let source = Source::new(RgbImage::from_pixel(width,height,image::Rgb([120,120,120])),width * 2,height * 2).unwrap();
+    let mut canvas = RgbCanvas::new((width * 2) as usize,(height * 2) as usize).unwrap();
+    let mut renderer = Renderer::new(Style::Radial);
+    renderer.set_line_count(500);
+    renderer.set_focus(Focus::Point(10.0,30.0));
+    renderer.set_radial_jitter(0.0);
+
+    //The focus is given in source pixels so it's at 20,60 on the canvas. Ends are rounded to pixels,
+    //including where rays are clipped at the edge of the canvas, which throws each line off by a
+    //couple of pixels and by more the farther the line is extended out toward the focus.
+    let mut checked = 0;
+    renderer.render_logged(&source,&mut canvas,|_| true,|record| {
+        if let StrokeShape::Line { x1,y1,x2,y2 } = record.shape {
+            let length = (x2 - x1).hypot(y2 - y1);
+            if length >= 10.0 {
+                let miss = ((x2 - x1) * (60.0 - y1) - (y2 - y1) * (20.0 - x1)).abs() / length;
+                let reach = ((x1 + x2) / 2.0 - 20.0).hypot((y1 + y2) / 2.0 - 60.0);
+                assert!(miss < 3.0 + 2.0 * reach / length,"{:?} misses the focus by {}",record.shape,miss);
+                checked += 1;
+            }
+        }
+        Ok(())
+    }).unwrap();
+    assert!(checked > 100);
+}
+
+#[test]
+fn clipped_lines_stay_in_the_region_they_start_in() {
+    //Orange on the left and dark blue on the right.
+    let (width,height) = (60,40);
+    let image = RgbImage::from_fn(width,height,|x,_| if x < 27 { image::Rgb([255,140,0]) } else { image::Rgb([10,30,90]) });
+    for &style in &[Style::Random,Style::Steered,Style::Energy] {
+        for &curved in &[false,true] {
+            let source = Source::new(image.clone(),width,height).unwrap();
+            let mut canvas = RgbCanvas::new(width as usize,height as usize).unwrap();
+            let mut renderer = Renderer::new(style);
+            renderer.set_line_count(2000);
+            renderer.set_curved(curved);
+            renderer.set_region_count(12);
+            renderer.set_clip_to_regions(true);
+
+            renderer.render_logged(&source,&mut canvas,|_| true,|record| {
+                let (x1,x2) = match record.shape {
+                    StrokeShape::Line { x1,x2,.. } | StrokeShape::Curve { x1,x2,.. } => (x1,x2),
+                    _ => panic!("{:?} drew {:?}",style,record.shape),
+                };
+                assert_eq!(x1 < 27.0,x2 < 27.0,"{:?} crosses over with {:?}",style,record.shape);
+                Ok(())
+            }).unwrap();
+        }
+    }
+}
+
+#[test]
+fn edge_web_joins_edges_that_face_the_same_way() {
+    //A disc has edges facing every way.
+    let (width,height) = (64,64);
+    let image = RgbImage::from_fn(width,height,|x,y| {
+        if (x as f32 - 30.0).hypot(y as f32 - 34.0) < 20.0 { image::Rgb([240,240,240]) } else { image::Rgb([10,10,10]) }
+    });
+    let gradient = gradient(&image.clone().into_raw(),width,height).unwrap();
+    let direction_at = |x: f32,y: f32| angle_to_direction(gradient[(y as usize * width as usize + x as usize) * 2 + 1]);
+
+    let source = Source::new(image,width,height).unwrap();
+    let mut canvas = RgbCanvas::new(width as usize,height as usize).unwrap();
+    let mut lines = 0;
+    Renderer::new(Style::EdgeWeb).render_logged(&source,&mut canvas,|_| true,|record| {
+        if let StrokeShape::Line { x1,y1,x2,y2 } = record.shape {
+            assert_eq!(direction_at(x1,y1),direction_at(x2,y2),"{:?} joins edges facing different ways",record.shape);
+            lines += 1;
+        }
+        Ok(())
+    }).unwrap();
+    assert!(lines > 10);
+}
+
+#[test]
+fn focus_names_are_parsed() {
+    assert_eq!(Focus::from_name("Center"),Some(Focus::Center));
+    assert_eq!(Focus::from_name("salient"),Some(Focus::Salient));
+    assert_eq!(Focus::from_name("12.5, 40"),Some(Focus::Point(12.5,40.0)));
+    assert_eq!(Focus::from_name("12"),None);
+    assert_eq!(Focus::from_name("1,2,3"),None);
+    assert_eq!(Focus::from_name("nan,2"),None);
+}
+
+#[test]
+fn detail_keeps_stroke_lengths_in_step() {
+    assert_eq!(Detail::from_name("Full"),Some(Detail::Full));
+    assert_eq!(Detail::from_name("500"),Some(Detail::Size(500.0)));
+    assert_eq!(Detail::from_name("0.5"),None);
+    assert_eq!(Detail::from_name("inf"),None);
+
+    //Random lines are up to 128 pixels long at the size the gradient is measured at, give or take
+    //rounding to pixels. Shrinking a 256x256 source down to 32 pixels across makes them up to eight
+    //times as long on the canvas.
+    let longest = |detail: Detail| {
+        let mut source = Source::new(split_image(256,256),64,64).unwrap();
+        source.set_detail(detail).unwrap();
+        let mut renderer = Renderer::new(Style::Random);
+        renderer.set_line_count(3000);
+        let mut canvas = RgbCanvas::new(64,64).unwrap();
+        let mut longest = 0.0f32;
+        renderer.render_logged(&source,&mut canvas,|_| true,|record| {
+            if let StrokeShape::Line { x1,y1,x2,y2 } = record.shape {
+                longest = longest.max((x2 - x1).hypot(y2 - y1));
+            }
+            Ok(())
+        }).unwrap();
+        longest
+    };
+    assert!(longest(Detail::Full) < 34.0);
+    assert!(longest(Detail::Size(32.0)) > 40.0);
+    assert!(longest(Detail::Size(1000.0)) < 34.0);
+}
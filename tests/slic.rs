@@ -0,0 +1,120 @@
+extern crate line_splat;
+
+use line_splat::Error;
+use line_splat::imageprocessing::{region_boundaries,slic};
+
+//Orange on the left and dark blue on the right of a width x height image.
+fn halves(width: usize,height: usize,split: usize) -> Vec<u8> {
+    let mut pixels = vec![];
+    for _ in 0..height {
+        for x in 0..width {
+            pixels.extend_from_slice(if x < split { &[255,140,0] } else { &[10,30,90] });
+        }
+    }
+    pixels
+}
+
+//Check that every label is one 4-connected piece.
+fn connected(labels: &[u32],width: usize,region_count: usize) -> bool {
+    let height = labels.len() / width;
+    let mut seen = vec![false; region_count];
+    let mut visited = vec![false; labels.len()];
+    for start in 0..labels.len() {
+        if visited[start] {
+            continue;
+        }
+        let label = labels[start] as usize;
+        if seen[label] {
+            return false;
+        }
+        seen[label] = true;
+
+        let mut pending = vec![start];
+        visited[start] = true;
+        while let Some(index) = pending.pop() {
+            let (x,y) = (index % width,index / width);
+            let mut neighbors = vec![];
+            if x > 0 {
+                neighbors.push(index - 1);
+            }
+            if x + 1 < width {
+                neighbors.push(index + 1);
+            }
+            if y > 0 {
+                neighbors.push(index - width);
+            }
+            if y + 1 < height {
+                neighbors.push(index + width);
+            }
+            for neighbor in neighbors {
+                if !visited[neighbor] && labels[neighbor] == labels[start] {
+                    visited[neighbor] = true;
+                    pending.push(neighbor);
+                }
+            }
+        }
+    }
+    seen.iter().all(|&seen| seen)
+}
+
+#[test]
+fn regions_follow_color_edges() {
+    //The split doesn't line up with the grid the regions start out on.
+    let (width,height) = (80,60);
+    let segmentation = slic(&halves(width,height,33),width as u32,height as u32,48,10.0).unwrap();
+
+    assert_eq!(segmentation.labels.len(),width * height);
+    let region_count = segmentation.colors.len();
+    assert!((24..=96).contains(&region_count),"{} regions",region_count);
+    assert!(segmentation.labels.iter().all(|&label| (label as usize) < region_count));
+    assert!(connected(&segmentation.labels,width,region_count));
+
+    //No region crosses the split so each one's mean color is exactly one of the halves.
+    for (index,&label) in segmentation.labels.iter().enumerate() {
+        let expected = if index % width < 33 { (255,140,0) } else { (10,30,90) };
+        assert_eq!(segmentation.colors[label as usize],expected,"pixel {},{}",index % width,index / width);
+    }
+}
+
+#[test]
+fn compactness_makes_regions_ignore_color() {
+    //With a huge compactness regions are the cells of the starting grid no matter the colors.
+    let (width,height) = (60,40);
+    let segmentation = slic(&halves(width,height,33),width as u32,height as u32,24,1.0e6).unwrap();
+    assert_eq!(segmentation.colors.len(),24);
+    assert!(connected(&segmentation.labels,width,24));
+    let crossing = (0..height).filter(|&y| segmentation.labels[y * width + 32] == segmentation.labels[y * width + 33]).count();
+    assert_eq!(crossing,height);
+}
+
+#[test]
+fn slic_handles_thin_images() {
+    for &(width,height) in &[(1,1),(9,1),(1,9),(2,2)] {
+        for &region_count in &[1,3,1000] {
+            let segmentation = slic(&halves(width,height,1),width as u32,height as u32,region_count,10.0).unwrap();
+            assert_eq!(segmentation.labels.len(),width * height);
+            assert!(connected(&segmentation.labels,width,segmentation.colors.len()));
+        }
+    }
+
+    assert!(matches!(slic(&[],0,0,10,10.0),Err(Error::EmptyImage)));
+    assert!(matches!(slic(&[0; 9],2,2,10,10.0),Err(Error::InvalidParameter(_))));
+    assert!(matches!(slic(&[0; 12],2,2,0,10.0),Err(Error::InvalidParameter(_))));
+    assert!(matches!(slic(&[0; 12],2,2,1,0.0),Err(Error::InvalidParameter(_))));
+}
+
+#[test]
+fn boundaries_are_marked_on_one_side() {
+    let labels = [
+        0,0,1,
+        0,0,1,
+        2,2,2,
+    ];
+    let boundaries = region_boundaries(&labels,3,3).unwrap();
+    assert_eq!(boundaries,vec![
+        0,255,0,
+        255,255,255,
+        0,0,0,
+    ]);
+    assert!(matches!(region_boundaries(&labels,3,4),Err(Error::InvalidParameter(_))));
+}
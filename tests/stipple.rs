@@ -0,0 +1,78 @@
+extern crate line_splat;
+extern crate rand;
+
+use rand::Rng;
+
+use line_splat::Error;
+use line_splat::stipple::{stipple,tsp_path};
+
+fn path_length(points: &[(f32,f32)],path: &[usize]) -> f32 {
+    path.windows(2).map(|pair| {
+        let ((x1,y1),(x2,y2)) = (points[pair[0]],points[pair[1]]);
+        (x2 - x1).hypot(y2 - y1)
+    }).sum()
+}
+
+#[test]
+fn dots_gather_where_the_density_is_high() {
+    //Left half is four times as dense as the right half.
+    let (width,height) = (64,32);
+    let density: Vec<f32> = (0..width * height).map(|index| if index % width < width / 2 { 0.8 } else { 0.2 }).collect();
+    let points = stipple(&density,width,height,500,10,&mut rand::thread_rng(),|_| true).unwrap();
+
+    assert_eq!(points.len(),500);
+    assert!(points.iter().all(|&(x,y)| x >= 0.0 && y >= 0.0 && x <= width as f32 && y <= height as f32));
+    let left = points.iter().filter(|point| point.0 < width as f32 / 2.0).count();
+    assert!(left > 350 && left < 450,"{} of 500 dots on the dense side",left);
+}
+
+#[test]
+fn stipple_handles_empty_and_blank_maps() {
+    let mut rng = rand::thread_rng();
+    assert!(stipple(&[0.0; 16],4,4,100,5,&mut rng,|_| true).unwrap().is_empty());
+    assert!(stipple(&[1.0; 16],4,4,0,5,&mut rng,|_| true).unwrap().is_empty());
+    assert_eq!(stipple(&[1.0],1,1,20,5,&mut rng,|_| true).unwrap().len(),20);
+}
+
+#[test]
+fn stipple_rejects_maps_of_the_wrong_size() {
+    let mut rng = rand::thread_rng();
+    assert!(matches!(stipple(&[1.0; 15],4,4,10,5,&mut rng,|_| true),Err(Error::InvalidParameter(_))));
+    assert!(matches!(stipple(&[1.0; 17],4,4,10,5,&mut rng,|_| true),Err(Error::InvalidParameter(_))));
+    assert!(matches!(stipple(&[],0,4,10,5,&mut rng,|_| true),Err(Error::InvalidParameter(_))));
+    assert!(matches!(stipple(&[1.0; 4],usize::MAX,2,10,5,&mut rng,|_| true),Err(Error::InvalidParameter(_))));
+}
+
+#[test]
+fn stipple_stops_relaxing_when_asked() {
+    let mut calls = 0;
+    stipple(&[0.5; 100],10,10,20,50,&mut rand::thread_rng(),|iteration| {
+        calls += 1;
+        iteration < 3
+    }).unwrap();
+    assert_eq!(calls,4);
+}
+
+#[test]
+fn tsp_path_visits_every_point_once() {
+    let mut rng = rand::thread_rng();
+    for &count in &[0,1,2,3,4,5,100,2000] {
+        let points: Vec<(f32,f32)> = (0..count).map(|_| (rng.gen_range(0.0,500.0),rng.gen_range(0.0,300.0))).collect();
+        let mut path = tsp_path(&points);
+        path.sort();
+        assert_eq!(path,(0..count).collect::<Vec<_>>());
+    }
+
+    //Every point on top of each other.
+    assert_eq!(tsp_path(&[(3.0,3.0); 10]).len(),10);
+}
+
+#[test]
+fn tsp_path_is_short_on_a_grid() {
+    //The shortest path through a 20 x 20 grid of points one apart is 399 long.
+    let mut points: Vec<(f32,f32)> = (0..400).map(|index| ((index % 20) as f32,(index / 20) as f32)).collect();
+    rand::thread_rng().shuffle(&mut points);
+    let path = tsp_path(&points);
+    let length = path_length(&points,&path);
+    assert!(length < 399.0 * 1.1,"Path is {} long",length);
+}
@@ -0,0 +1,182 @@
+extern crate image;
+extern crate line_splat;
+
+use image::RgbImage;
+
+use line_splat::Error;
+use line_splat::canvas::{Canvas,RgbCanvas};
+use line_splat::render::{ColorMode,Renderer,Source,Style};
+use line_splat::strokelog::{Replayer,StrokeColor,StrokeLogHeader,StrokeLogReader,StrokeLogWriter,StrokeRecord,StrokeShape};
+
+const HEADER: StrokeLogHeader = StrokeLogHeader {
+    width: 40,
+    height: 30,
+    pen_width: 2.5,
+    pen_opacity: 0.5,
+};
+
+fn records() -> Vec<StrokeRecord> {
+    vec![
+        StrokeRecord {
+            shape: StrokeShape::Line { x1: 1.0,y1: 2.0,x2: 30.0,y2: 20.0 },
+            color: StrokeColor::Solid(10,20,30),
+            style: Style::Random,
+            layer: 0,
+        },
+        StrokeRecord {
+            shape: StrokeShape::Curve { x1: 0.5,y1: 29.0,cx: 20.0,cy: -4.0,x2: 39.0,y2: 29.0 },
+            color: StrokeColor::Gradient((255,0,0),(0,0,255)),
+            style: Style::EdgeWeb,
+            layer: 513,
+        },
+        StrokeRecord {
+            shape: StrokeShape::Triangle { x1: 0.0,y1: 0.0,x2: 12.5,y2: 30.0,x3: 40.0,y3: 7.5 },
+            color: StrokeColor::Solid(200,100,0),
+            style: Style::Mesh,
+            layer: 1,
+        },
+        StrokeRecord {
+            shape: StrokeShape::Polyline { points: vec![(1.0,1.0),(20.5,3.25),(10.0,25.0),(1.0,1.0)] },
+            color: StrokeColor::Solid(90,90,90),
+            style: Style::Contour,
+            layer: 2,
+        },
+    ]
+}
+
+fn write_log(records: &[StrokeRecord]) -> Vec<u8> {
+    let mut writer = StrokeLogWriter::new(vec![],&HEADER).unwrap();
+    for record in records {
+        writer.write(record).unwrap();
+    }
+    writer.finish().unwrap()
+}
+
+fn canvas_pixels(canvas: &dyn Canvas) -> Vec<(f32,f32,f32,f32)> {
+    let mut pixels = vec![];
+    for y in 0..canvas.height() {
+        for x in 0..canvas.width() {
+            pixels.push(canvas.pixel(x,y));
+        }
+    }
+    pixels
+}
+
+#[test]
+fn records_round_trip() {
+    let bytes = write_log(&records());
+    let reader = StrokeLogReader::new(bytes.as_slice()).unwrap();
+    assert_eq!(*reader.header(),HEADER);
+    let read: Vec<StrokeRecord> = reader.map(|record| record.unwrap()).collect();
+    assert_eq!(read,records());
+}
+
+#[test]
+fn damaged_logs_are_rejected() {
+    let bytes = write_log(&records());
+    assert!(matches!(StrokeLogReader::new(&b"not a stroke log at all..."[..]),Err(Error::UnsupportedFormat(_))));
+    assert!(matches!(StrokeLogReader::new(&bytes[..10]),Err(Error::UnsupportedFormat(_))));
+
+    //Cutting the log off partway through a record reports an error after the records that were
+    //complete.
+    let reader = StrokeLogReader::new(&bytes[..bytes.len() - 1]).unwrap();
+    let read: Vec<_> = reader.collect();
+    assert_eq!(read.len(),4);
+    assert!(read[..3].iter().all(|record| record.is_ok()));
+    assert!(matches!(read[3],Err(Error::Io(_))));
+
+    //The pen width is the f32 at byte 18 and the opacity is the one at byte 22.
+    for &(offset,value) in &[(18,f32::NAN),(18,0.0),(18,-2.0),(18,f32::INFINITY),(22,f32::NAN),(22,f32::NEG_INFINITY)] {
+        let mut bad_pen = bytes.clone();
+        bad_pen[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+        assert!(matches!(StrokeLogReader::new(bad_pen.as_slice()),Err(Error::InvalidParameter(_))),"{} at {}",value,offset);
+    }
+    let header = StrokeLogHeader {
+        pen_width: f32::NAN,
+        ..HEADER
+    };
+    let mut canvas = RgbCanvas::new(4,4).unwrap();
+    assert!(matches!(Replayer::new().replay(&header,records().into_iter().map(Ok),&mut canvas),Err(Error::InvalidParameter(_))));
+
+    let mut unknown_shape = bytes.clone();
+    unknown_shape[26] = 9;
+    let mut reader = StrokeLogReader::new(unknown_shape.as_slice()).unwrap();
+    assert!(matches!(reader.next(),Some(Err(Error::UnsupportedFormat(_)))));
+    assert!(reader.next().is_none());
+}
+
+#[test]
+fn replay_matches_the_original_render() {
+    let image = RgbImage::from_fn(48,32,|x,y| image::Rgb([(x * 5) as u8,(y * 7) as u8,128]));
+    for &(style,color_mode) in &[(Style::Random,ColorMode::Default),(Style::Energy,ColorMode::Gradient),(Style::EdgeWeb,ColorMode::Ink),(Style::Mesh,ColorMode::Default),(Style::Contour,ColorMode::Level),(Style::Spiral,ColorMode::Ink),(Style::Squiggle,ColorMode::LineAverage),(Style::Radial,ColorMode::Center),(Style::Hough,ColorMode::Default),(Style::Outline,ColorMode::Level),(Style::Regions,ColorMode::Default)] {
+        for &curved in &[false,true] {
+            let source = Source::new(image.clone(),48,32).unwrap();
+            let mut canvas = RgbCanvas::new(48,32).unwrap();
+            let mut renderer = Renderer::new(style);
+            renderer.set_color_mode(color_mode);
+            renderer.set_line_count(500);
+            renderer.set_pen_width(1.5);
+            renderer.set_curved(curved);
+            renderer.set_mesh_points(100);
+            renderer.set_low_poly(curved);
+            renderer.set_extend_lines(curved);
+            renderer.set_region_count(20);
+            renderer.set_clip_to_regions(curved);
+
+            let header = StrokeLogHeader {
+                width: 48,
+                height: 32,
+                pen_width: 1.5,
+                pen_opacity: 1.0,
+            };
+            let mut writer = StrokeLogWriter::new(vec![],&header).unwrap();
+            renderer.render_logged(&source,&mut canvas,|_| true,|record| writer.write(record)).unwrap();
+            let bytes = writer.finish().unwrap();
+
+            let reader = StrokeLogReader::new(bytes.as_slice()).unwrap();
+            let mut replayed = RgbCanvas::new(48,32).unwrap();
+            Replayer::new().replay(&header,reader,&mut replayed).unwrap();
+            assert!(canvas_pixels(&canvas) == canvas_pixels(&replayed),"{:?} replay differs",style);
+        }
+    }
+}
+
+#[test]
+fn replay_stretches_to_the_canvas() {
+    let bytes = write_log(&records()[..1]);
+    let reader = StrokeLogReader::new(bytes.as_slice()).unwrap();
+    let header = *reader.header();
+    let mut canvas = RgbCanvas::new(80,60).unwrap();
+    let mut replayer = Replayer::new();
+    replayer.set_ink(255,255,255);
+    replayer.set_pen_opacity(1.0);
+    assert_eq!(replayer.replay(&header,reader,&mut canvas).unwrap(),1);
+
+    //The line runs from 2.5,4.5 to 60.5,40.5 after being stretched.
+    assert_eq!(canvas.pixel(31,22),(1.0,1.0,1.0,1.0));
+    assert_eq!(canvas.pixel(70,5),(0.0,0.0,0.0,1.0));
+    assert_eq!(canvas.pixel(20,40),(0.0,0.0,0.0,1.0));
+
+    //A line across a row of pixels still runs from the center of the first stretched pixel to the
+    //center of the last one and stays centered on its row.
+    let header = StrokeLogHeader {
+        width: 10,
+        height: 10,
+        pen_width: 1.0,
+        pen_opacity: 1.0,
+    };
+    let record = StrokeRecord {
+        shape: StrokeShape::Line { x1: 0.0,y1: 5.0,x2: 9.0,y2: 5.0 },
+        color: StrokeColor::Solid(255,255,255),
+        style: Style::Random,
+        layer: 0,
+    };
+    let mut canvas = RgbCanvas::new(40,40).unwrap();
+    Replayer::new().replay(&header,vec![Ok(record)],&mut canvas).unwrap();
+    for y in 0..40 {
+        for x in 0..40 {
+            let drawn = canvas.pixel(x,y) != (0.0,0.0,0.0,1.0);
+            assert_eq!(drawn,(2..38).contains(&x) && (20..24).contains(&y),"{},{}",x,y);
+        }
+    }
+}
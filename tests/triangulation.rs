@@ -0,0 +1,62 @@
+extern crate line_splat;
+extern crate rand;
+
+use rand::Rng;
+
+use line_splat::triangulation::triangulate;
+
+fn area(points: &[(f32,f32)],triangle: &[usize; 3]) -> f32 {
+    let [a,b,c] = *triangle;
+    let ((ax,ay),(bx,by),(cx,cy)) = (points[a],points[b],points[c]);
+    ((bx - ax) * (cy - ay) - (by - ay) * (cx - ax)) / 2.0
+}
+
+#[test]
+fn triangles_cover_the_points_and_have_empty_circumcircles() {
+    //Random pixel positions inside of a 200x100 box along with its corners.
+    let mut rng = rand::thread_rng();
+    let mut points = vec![(0.0,0.0),(200.0,0.0),(0.0,100.0),(200.0,100.0)];
+    for _ in 0..300 {
+        points.push((rng.gen_range(1,200) as f32,rng.gen_range(1,100) as f32));
+    }
+    let triangles = triangulate(&points);
+
+    //Every triangle winds the same way and together they fill the box exactly.
+    assert!(triangles.iter().all(|triangle| area(&points,triangle) > 0.0));
+    let total: f32 = triangles.iter().map(|triangle| area(&points,triangle)).sum();
+    assert!((total - 200.0 * 100.0).abs() < 0.5,"triangles cover {} pixels",total);
+
+    for triangle in &triangles {
+        let [a,b,c] = *triangle;
+        let ((ax,ay),(bx,by),(cx,cy)) = (points[a],points[b],points[c]);
+        let d = 2.0 * (ax * (by - cy) + bx * (cy - ay) + cx * (ay - by));
+        let ux = ((ax * ax + ay * ay) * (by - cy) + (bx * bx + by * by) * (cy - ay) + (cx * cx + cy * cy) * (ay - by)) / d;
+        let uy = ((ax * ax + ay * ay) * (cx - bx) + (bx * bx + by * by) * (ax - cx) + (cx * cx + cy * cy) * (bx - ax)) / d;
+        let radius = (ax - ux).hypot(ay - uy);
+        for &(x,y) in &points {
+            assert!((x - ux).hypot(y - uy) > radius - 1.0e-3,"{:?} is inside of the circumcircle of {:?}",(x,y),triangle);
+        }
+    }
+}
+
+#[test]
+fn grid_points_are_split_into_two_triangles_per_cell() {
+    //Every cell of a grid has four points on its circumcircle so this leans on ties being broken
+    //consistently.
+    let points: Vec<(f32,f32)> = (0..100).map(|index| ((index % 10) as f32,(index / 10) as f32)).collect();
+    let triangles = triangulate(&points);
+    assert_eq!(triangles.len(),9 * 9 * 2);
+    assert!(triangles.iter().all(|triangle| (area(&points,triangle) - 0.5).abs() < 1.0e-6));
+}
+
+#[test]
+fn degenerate_points_make_no_triangles() {
+    assert!(triangulate(&[]).is_empty());
+    assert!(triangulate(&[(0.0,0.0),(1.0,1.0)]).is_empty());
+    assert!(triangulate(&[(0.0,0.0),(1.0,1.0),(2.0,2.0),(3.0,3.0)]).is_empty());
+    assert!(triangulate(&[(0.0,0.0),(f32::NAN,1.0),(1.0,0.0)]).is_empty());
+
+    //Duplicates are only used once.
+    let triangles = triangulate(&[(0.0,0.0),(4.0,0.0),(0.0,4.0),(4.0,0.0),(0.0,0.0)]);
+    assert_eq!(triangles.len(),1);
+}